@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use rand::Rng;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::mda::is_round_day;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::rng::Subsystem;
+use crate::scenario::{AcdConfig, AcdTrigger};
+use crate::scheduled::ScheduledActions;
+use crate::{Host, InfectionState, Inoculation, OutputDir, Regimen, SimRng, SimulationEvent, SimulationTime};
+
+#[derive(Resource, Default)]
+pub struct AcdSchedule(pub Option<AcdConfig>);
+
+#[derive(Debug, Clone, Copy)]
+pub struct AcdCampaign {
+    pub day: u32,
+    pub screened: usize,
+    pub positives: usize,
+}
+
+impl AcdCampaign {
+    // Positives per 100 screened
+    pub fn yield_per_100(&self) -> f32 {
+        if self.screened == 0 {
+            0.0
+        } else {
+            100.0 * self.positives as f32 / self.screened as f32
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AcdCampaigns {
+    pub campaigns: Vec<AcdCampaign>,
+    cases_since_campaign: u32, // Clinical treatments, for reactive triggering
+}
+
+#[derive(Resource)]
+pub struct AcdOutput(BufWriter<File>);
+
+pub fn setup_acd_output(
+    mut commands: Commands,
+    schedule: Res<AcdSchedule>,
+    output_dir: Res<OutputDir>,
+    mut report: ResMut<RunReport>,
+) {
+    if schedule.0.is_none() {
+        return;
+    }
+
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("acd_campaigns.csv"))?);
+        writeln!(writer, "day,screened,positives,yield_per_100")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(AcdOutput(writer)),
+        Err(err) => report.output_failed("case detection output", err),
+    }
+}
+
+fn sensitivity(config: &AcdConfig, state: InfectionState) -> f32 {
+    match state {
+        InfectionState::E => config.sensitivity_liver,
+        InfectionState::A => config.sensitivity_acute,
+        InfectionState::C => config.sensitivity_chronic,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_case_detection(
+    mut host_query: Query<(&mut ScheduledActions, Option<&Children>), With<Host>>,
+    inoc_query: Query<&Inoculation>,
+    schedule: Res<AcdSchedule>,
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut events: EventReader<SimulationEvent>,
+    mut campaigns: ResMut<AcdCampaigns>,
+    mut output: Option<ResMut<AcdOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(config) = &schedule.0 else {
+        return;
+    };
+
+    let cases = events.read().filter(|event| **event == SimulationEvent::Treatment(Regimen::Treatment)).count();
+    campaigns.cases_since_campaign += cases as u32;
+
+    let already_ran = campaigns.campaigns.last().is_some_and(|campaign| campaign.day == sim_time.day);
+    let due = match config.trigger {
+        AcdTrigger::Scheduled => is_round_day(sim_time.day, config.start_day, config.interval_days, config.rounds),
+        AcdTrigger::Reactive => campaigns.cases_since_campaign >= config.case_threshold.max(1),
+    };
+    if already_ran || !due {
+        return;
+    }
+
+    let rng = &mut sim_rng.stream(Subsystem::CaseDetection);
+    let mut campaign = AcdCampaign {
+        day: sim_time.day,
+        screened: 0,
+        positives: 0,
+    };
+    for (mut scheduled, children) in host_query.iter_mut() {
+        if rng.gen::<f32>() >= config.coverage {
+            continue;
+        }
+        campaign.screened += 1;
+
+        // Positive if the diagnostic picks up any of the host's inoculations
+        let positive = children.is_some_and(|children| {
+            children
+                .iter()
+                .filter_map(|&child| inoc_query.get(child).ok())
+                .any(|inoc| rng.gen::<f32>() < sensitivity(config, inoc.state))
+        });
+        if positive {
+            campaign.positives += 1;
+            scheduled.request_treatment(sim_time.day, Regimen::Screening);
+        }
+    }
+
+    if let Some(output) = output.as_mut() {
+        let result = writeln!(
+            output.0,
+            "{},{},{},{:.1}",
+            campaign.day,
+            campaign.screened,
+            campaign.positives,
+            campaign.yield_per_100()
+        )
+        .and_then(|_| output.0.flush());
+        if let Err(err) = result {
+            report.output_failed("case detection output", err);
+        }
+    }
+    campaigns.campaigns.push(campaign);
+    campaigns.cases_since_campaign = 0;
+}
+
+#[cfg(feature = "gui")]
+pub fn case_detection_ui(
+    mut contexts: EguiContexts,
+    schedule: Res<AcdSchedule>,
+    campaigns: Res<AcdCampaigns>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    if schedule.0.is_none() {
+        return;
+    }
+
+    egui::Window::new("Case Detection")
+        .default_pos(egui::pos2(450.0, 650.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            if campaigns.campaigns.is_empty() {
+                ui.label("No campaigns yet");
+                return;
+            }
+
+            egui::Grid::new("acd_campaigns").striped(true).show(ui, |ui| {
+                ui.label("Day");
+                ui.label("Screened");
+                ui.label("Positive");
+                ui.label("Yield /100");
+                ui.end_row();
+
+                for campaign in campaigns.campaigns.iter().rev().take(10) {
+                    ui.label(campaign.day.to_string());
+                    ui.label(campaign.screened.to_string());
+                    ui.label(campaign.positives.to_string());
+                    ui.label(format!("{:.1}", campaign.yield_per_100()));
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::fs;
+use std::path::Path;
+
+use crate::clock::DayTick;
+use crate::ids::HostId;
+use crate::infectiousness::{host_infectiousness, InfectiousnessCurve};
+use crate::rng::Subsystem;
+use crate::transmission::TransmissionTree;
+use crate::{Host, Inoculation, Params, SimRng, SimulationTime};
+
+// Daily contacts between age groups, e.g. a POLYMOD matrix, with host-to-host transmission along them
+#[derive(Resource)]
+pub struct AgeMixing {
+    groups: Vec<(String, f32)>, // Label and lower age bound in years, youngest first
+    contacts: Vec<Vec<f32>>,    // Row: contacts per day a member of that group has with each group
+    contact_rate: f32,          // Infection chance per daily contact with a fully infectious host
+    hosts: Vec<usize>,          // Hosts in each group, as of the last daily update
+    infectiousness: Vec<f32>,   // Summed over the hosts in each group, as of the last daily update
+}
+
+impl AgeMixing {
+    // Reads a square matrix with a header of group labels, e.g. "age_group,0-4,5-14,15+", then one row per group
+    // in the same order; each label starts with the lower bound of the group
+    pub fn load(path: &Path, contact_rate: f32) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Self::parse(&contents, contact_rate).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn parse(contents: &str, contact_rate: f32) -> Result<Self, String> {
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header: Vec<&str> = lines.next().map_or(Vec::new(), |(_, line)| line.split(',').map(str::trim).skip(1).collect());
+        if header.is_empty() {
+            return Err("expected a header of age groups".to_owned());
+        }
+        let groups = header
+            .iter()
+            .map(|label| {
+                let digits: String = label.chars().take_while(char::is_ascii_digit).collect();
+                let lower = digits.parse::<f32>().map_err(|_| format!("age group '{}' must start with its lower bound", label))?;
+                Ok((label.to_string(), lower))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if groups.windows(2).any(|pair| pair[0].1 >= pair[1].1) {
+            return Err("age groups must be in increasing order".to_owned());
+        }
+
+        let mut contacts = Vec::new();
+        for (line_number, line) in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let row = fields[1..]
+                .iter()
+                .map(|field| field.parse::<f32>().map_err(|err| format!("line {}: {}", line_number + 1, err)))
+                .collect::<Result<Vec<_>, String>>()?;
+            if fields[0] != groups.get(contacts.len()).map_or("", |(label, _)| label.as_str()) || row.len() != groups.len() {
+                return Err(format!("line {}: expected a row for each age group, in header order", line_number + 1));
+            }
+            if row.iter().any(|value| *value < 0.0) {
+                return Err(format!("line {}: contacts must be non-negative", line_number + 1));
+            }
+            contacts.push(row);
+        }
+        if contacts.len() != groups.len() {
+            return Err(format!("expected {} rows, found {}", groups.len(), contacts.len()));
+        }
+
+        let group_count = groups.len();
+        Ok(Self {
+            groups,
+            contacts,
+            contact_rate,
+            hosts: vec![0; group_count],
+            infectiousness: vec![0.0; group_count],
+        })
+    }
+
+    pub fn group_of(&self, age_years: f32) -> usize {
+        self.groups.iter().rposition(|(_, lower)| age_years >= *lower).unwrap_or(0)
+    }
+
+    // Daily infection chance of a host in `group` from each host in `other`, per unit of that host's infectiousness
+    fn per_host_rate(&self, group: usize, other: usize) -> f32 {
+        if self.hosts[other] == 0 {
+            return 0.0;
+        }
+        self.contact_rate * self.contacts[group][other] / self.hosts[other] as f32
+    }
+}
+
+// Extra daily chance of infection from contacts with infectious hosts of each age group
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AgeMixingExposure(pub f32);
+
+pub fn assign_age_mixing(mut commands: Commands, host_query: Query<Entity, Added<Host>>) {
+    for host in host_query.iter() {
+        commands.entity(host).insert(AgeMixingExposure(0.0));
+    }
+}
+
+// Once a day, regroups hosts by their current age and sets each one's exposure from the infectious hosts it meets
+pub fn update_age_mixing_exposure(
+    mut host_query: Query<(&Host, Option<&Children>, &mut AgeMixingExposure)>,
+    inoc_query: Query<&Inoculation>,
+    curve: Res<InfectiousnessCurve>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut mixing: ResMut<AgeMixing>,
+    mut ticks: EventReader<DayTick>,
+) {
+    if ticks.read().count() == 0 {
+        return;
+    }
+
+    let mut hosts = vec![0; mixing.groups.len()];
+    let mut infectiousness = vec![0.0; mixing.groups.len()];
+    for (host, children, _) in host_query.iter() {
+        let group = mixing.group_of(host.age_years(sim_time.day));
+        hosts[group] += 1;
+        infectiousness[group] += host_infectiousness(children, &inoc_query, &curve, &params, sim_time.day);
+    }
+    mixing.hosts = hosts;
+    mixing.infectiousness = infectiousness;
+
+    for (host, _, mut exposure) in host_query.iter_mut() {
+        let group = mixing.group_of(host.age_years(sim_time.day));
+        exposure.0 = (0..mixing.groups.len()).map(|other| mixing.per_host_rate(group, other) * mixing.infectiousness[other]).sum();
+    }
+}
+
+// A new inoculation in a host, with the share of its infection chance outside venues that came from age-group mixing
+#[derive(Event)]
+pub struct MixingInfection {
+    pub host: Entity,
+    pub mixing_share: f32,
+}
+
+// Decides which infections came from age-group mixing and picks the infector, weighted by contacts between the
+// two hosts' age groups and the infector's infectiousness
+#[allow(clippy::too_many_arguments)]
+pub fn attribute_mixing_infections(
+    mut infections: EventReader<MixingInfection>,
+    host_query: Query<(Entity, &HostId, &Host, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    mixing: Res<AgeMixing>,
+    curve: Res<InfectiousnessCurve>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut tree: ResMut<TransmissionTree>,
+) {
+    for infection in infections.read() {
+        let rng = &mut sim_rng.stream(Subsystem::Infection);
+        let Ok((_, &infectee_id, infectee, _)) = host_query.get(infection.host) else {
+            continue;
+        };
+        if rng.gen::<f32>() >= infection.mixing_share {
+            continue; // Infected by another route
+        }
+        // Already caught at a venue
+        if tree.edges.iter().rev().take_while(|edge| edge.day == sim_time.day).any(|edge| edge.infectee == infection.host) {
+            continue;
+        }
+
+        let group = mixing.group_of(infectee.age_years(sim_time.day));
+        let mut candidates: Vec<(Entity, HostId, f32)> = host_query
+            .iter()
+            .filter(|(other, ..)| *other != infection.host)
+            .map(|(other, &other_id, host, children)| {
+                let rate = mixing.per_host_rate(group, mixing.group_of(host.age_years(sim_time.day)));
+                (other, other_id, rate * host_infectiousness(children, &inoc_query, &curve, &params, sim_time.day))
+            })
+            .filter(|(_, _, weight)| *weight > 0.0)
+            .collect();
+        candidates.sort_by_key(|(entity, _, _)| *entity);
+
+        let total: f32 = candidates.iter().map(|(_, _, weight)| weight).sum();
+        let mut draw = rng.gen::<f32>() * total;
+        if let Some(&(infector, infector_id, _)) = candidates.iter().find(|(_, _, weight)| {
+            draw -= weight;
+            draw < 0.0
+        }) {
+            tree.record((infector, infector_id), (infection.host, infectee_id), sim_time.day);
+        }
+    }
+}
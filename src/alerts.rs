@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+
+use crate::event_log::EventLog;
+use crate::scenario::AlertRule;
+use crate::{Host, HostState, Inoculation, Regimen, SimulationEvent, SimulationTime};
+
+const WEEK_DAYS: u32 = 7;
+
+#[derive(Resource, Default)]
+pub struct AlertRules(pub Vec<AlertRule>);
+
+#[derive(Resource, Default)]
+pub struct AlertState {
+    last_day: Option<u32>,
+    week_cases: u32, // Clinical treatments so far this week
+    last_week_cases: Option<u32>,
+    resistant_seen: bool,
+    above_prevalence: Vec<bool>, // Per rule, so a threshold alarms once per crossing
+    pub banners: Vec<(u32, String)>, // Raised and not yet dismissed
+}
+
+impl AlertState {
+    fn raise(&mut self, day: u32, message: String, event_log: &mut EventLog) {
+        event_log.log(day, format!("Alert: {}", message));
+        self.banners.push((day, message));
+    }
+}
+
+// Checks the rules once a day, or as soon as a treatment fails, on what a surveillance officer would see: treated cases and treatment failures
+#[allow(clippy::too_many_arguments)]
+pub fn check_alerts(
+    host_query: Query<(&Host, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    rules: Res<AlertRules>,
+    sim_time: Res<SimulationTime>,
+    mut events: EventReader<SimulationEvent>,
+    mut state: ResMut<AlertState>,
+    mut event_log: ResMut<EventLog>,
+) {
+    state.week_cases += events.read().filter(|event| **event == SimulationEvent::Treatment(Regimen::Treatment)).count() as u32;
+    let day = sim_time.day;
+
+    // A resistant clone still in a host treated today is a treatment failure the clinic would see
+    let failure = host_query.iter().any(|(host, children)| {
+        host.last_offer == Some((day, Regimen::Treatment, true))
+            && children.is_some_and(|children| children.iter().any(|&child| inoc_query.get(child).is_ok_and(|inoc| inoc.genotype.is_resistant())))
+    });
+    let first_resistant = failure && !state.resistant_seen;
+    state.resistant_seen |= failure;
+    if state.last_day == Some(day) && !first_resistant {
+        return;
+    }
+    let new_day = state.last_day != Some(day);
+    state.last_day = Some(day);
+
+    let week_ended = new_day && day > 0 && day.is_multiple_of(WEEK_DAYS);
+    let host_count = host_query.iter().len();
+    let infected = host_query
+        .iter()
+        .filter(|(host, children)| matches!(host.state(*children, &inoc_query), HostState::A | HostState::C))
+        .count();
+    let prevalence = if host_count > 0 { infected as f32 / host_count as f32 } else { 0.0 };
+
+    state.above_prevalence.resize(rules.0.len(), false);
+    for (index, rule) in rules.0.iter().enumerate() {
+        match *rule {
+            AlertRule::IncidenceDoubling { min_cases } => {
+                let (cases, last_week) = (state.week_cases, state.last_week_cases);
+                if week_ended && cases >= min_cases && last_week.is_some_and(|last_week| cases >= 2 * last_week.max(1)) {
+                    let message = format!("weekly cases doubled ({} -> {})", last_week.unwrap_or(0), cases);
+                    state.raise(day, message, &mut event_log);
+                }
+            }
+            AlertRule::FirstResistant => {
+                if first_resistant {
+                    state.raise(day, "first drug-resistant case detected".to_owned(), &mut event_log);
+                }
+            }
+            AlertRule::Prevalence { threshold } if new_day => {
+                let above = prevalence >= threshold;
+                if above && !state.above_prevalence[index] {
+                    let message = format!("prevalence {:.0}% reached {:.0}%", 100.0 * prevalence, 100.0 * threshold);
+                    state.raise(day, message, &mut event_log);
+                }
+                state.above_prevalence[index] = above;
+            }
+            AlertRule::Prevalence { .. } => {}
+        }
+    }
+
+    if week_ended {
+        state.last_week_cases = Some(state.week_cases);
+        state.week_cases = 0;
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn alerts_ui(mut contexts: EguiContexts, mut state: ResMut<AlertState>) {
+    if state.banners.is_empty() {
+        return;
+    }
+
+    egui::Area::new("alerts".into())
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgb(160, 30, 30))
+                .inner_margin(egui::Margin::symmetric(12.0, 6.0))
+                .rounding(4.0)
+                .show(ui, |ui| {
+                    let mut dismissed = None;
+                    for (index, (day, message)) in state.banners.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let text = format!("Day {}: {}", day, message);
+                            ui.label(egui::RichText::new(text).size(18.0).strong().color(egui::Color32::WHITE));
+                            if ui.small_button("Dismiss").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = dismissed {
+                        state.banners.remove(index);
+                    }
+                });
+        });
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{PlotPoint, PlotUi, Text, VLine};
+use std::fs;
+
+use crate::autosave::write_atomic;
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::{OutputDir, SimulationTime};
+
+// Notes the user pins to simulation days, e.g. "changed incidence here"
+#[derive(Resource, Default)]
+pub struct Annotations {
+    pub notes: Vec<(u32, String)>,
+    draft: String,
+}
+
+impl Annotations {
+    pub fn write_csv(&self, output_dir: &OutputDir) -> std::io::Result<()> {
+        let mut csv = String::from("day,note\n");
+        for (day, note) in &self.notes {
+            csv.push_str(&format!("{},\"{}\"\n", day, note.replace('"', "\"\"")));
+        }
+        fs::create_dir_all(&output_dir.0)?;
+        write_atomic(&output_dir.0.join("annotations.csv"), csv)
+    }
+}
+
+// Vertical marker and label for each note, for any plot with days on the x-axis
+pub fn plot_annotations(plot_ui: &mut PlotUi, annotations: &Annotations) {
+    let top = plot_ui.plot_bounds().max()[1];
+    for (day, note) in &annotations.notes {
+        let color = egui::Color32::from_rgb(230, 200, 80);
+        plot_ui.vline(VLine::new(*day as f64).color(color).style(egui_plot::LineStyle::dotted_dense()));
+        plot_ui.text(Text::new(PlotPoint::new(*day as f64, top), note.as_str()).color(color).anchor(egui::Align2::LEFT_TOP));
+    }
+}
+
+pub fn annotations_ui(
+    mut contexts: EguiContexts,
+    mut annotations: ResMut<Annotations>,
+    sim_time: Res<SimulationTime>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    egui::Window::new("Annotations")
+        .default_pos(egui::pos2(450.0, 650.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.text_edit_singleline(&mut annotations.draft);
+                let submitted = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if (ui.button(format!("Pin to day {}", sim_time.day)).clicked() || submitted) && !annotations.draft.trim().is_empty() {
+                    let note = std::mem::take(&mut annotations.draft).trim().to_owned();
+                    annotations.notes.push((sim_time.day, note));
+                }
+            });
+            for (day, note) in &annotations.notes {
+                ui.label(format!("Day {}: {}", day, note));
+            }
+        });
+}
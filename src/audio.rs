@@ -0,0 +1,93 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::SimulationEvent;
+
+// Sound effects in `audio` builds, the default
+pub fn add_audio_cues(app: &mut App) {
+    app.insert_resource(AudioCues::default())
+        .add_systems(Startup, load_audio_cues)
+        .add_systems(Update, play_audio_cues);
+}
+
+// Optional sound effects for key simulation events, off by default
+#[derive(Resource)]
+pub struct AudioCues {
+    pub enabled: bool,
+    pub infection_volume: f32,
+    pub treatment_volume: f32,
+    pub elimination_volume: f32,
+}
+
+impl Default for AudioCues {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            infection_volume: 0.3,
+            treatment_volume: 0.6,
+            elimination_volume: 1.0,
+        }
+    }
+}
+
+impl AudioCues {
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Audio Cues");
+
+        if self.enabled {
+            ui.add(egui::Slider::new(&mut self.infection_volume, 0.0..=1.0).text("New Infection"));
+            ui.add(egui::Slider::new(&mut self.treatment_volume, 0.0..=1.0).text("Treatment"));
+            ui.add(egui::Slider::new(&mut self.elimination_volume, 0.0..=1.0).text("Elimination"));
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct AudioCueSounds {
+    infection: Handle<AudioSource>,
+    treatment: Handle<AudioSource>,
+    elimination: Handle<AudioSource>,
+}
+
+pub fn load_audio_cues(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AudioCueSounds {
+        infection: asset_server.load("sounds/infection.wav"),
+        treatment: asset_server.load("sounds/treatment.wav"),
+        elimination: asset_server.load("sounds/elimination.wav"),
+    });
+}
+
+// Plays at most one sound per event kind per frame, so bursts of infections don't stack up
+pub fn play_audio_cues(
+    mut commands: Commands,
+    mut events: EventReader<SimulationEvent>,
+    cues: Res<AudioCues>,
+    sounds: Res<AudioCueSounds>,
+) {
+    let (mut infection, mut treatment, mut elimination) = (false, false, false);
+    for event in events.read() {
+        match event {
+            SimulationEvent::Infection => infection = true,
+            SimulationEvent::Treatment(_) => treatment = true,
+            SimulationEvent::Elimination => elimination = true,
+        }
+    }
+
+    if !cues.enabled {
+        return;
+    }
+
+    for (triggered, sound, volume) in [
+        (infection, &sounds.infection, cues.infection_volume),
+        (treatment, &sounds.treatment, cues.treatment_volume),
+        (elimination, &sounds.elimination, cues.elimination_volume),
+    ] {
+        if triggered && volume > 0.0 {
+            commands.spawn(AudioBundle {
+                source: sound.clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+            });
+        }
+    }
+}
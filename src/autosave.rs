@@ -0,0 +1,74 @@
+#[cfg(feature = "gui")]
+use bevy_egui::egui;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::observers::{DaySummary, Observer};
+use crate::OutputDir;
+
+const DAILY_FILE: &str = "host_states_daily.csv";
+
+// Writes to a temporary file and renames it into place, so a crash never leaves a half-written output
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(temporary, path)
+}
+
+// Daily host state counts appended as the run goes, kept as a .partial file until the run completes
+#[derive(Default)]
+pub struct DailyStatesExport {
+    writer: Option<BufWriter<File>>, // None before start and once rotated
+    partial: PathBuf,
+    rows: usize,
+    failed: Option<std::io::Error>, // The first failed daily write, reported when the run finishes
+}
+
+impl Observer for DailyStatesExport {
+    fn name(&self) -> &str {
+        "Daily host states"
+    }
+
+    fn start(&mut self, output_dir: &OutputDir) -> std::io::Result<()> {
+        self.partial = output_dir.0.join(format!("{}.partial", DAILY_FILE));
+        fs::create_dir_all(&output_dir.0)?;
+        let mut writer = BufWriter::new(File::create(&self.partial)?);
+        writeln!(writer, "day,S,E,A,C,P")?;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn on_day_end(&mut self, summary: &DaySummary) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let values: Vec<String> = summary.state_counts.iter().map(|count| count.to_string()).collect();
+        // Flushed every day, so a forced quit loses at most the current day
+        let result = writeln!(writer, "{},{}", summary.day, values.join(",")).and_then(|_| writer.flush());
+        if let Err(err) = result {
+            bevy::log::warn!("Failed to autosave daily statistics: {}", err);
+            self.failed.get_or_insert(err);
+        }
+        self.rows += 1;
+    }
+
+    // Renames the .partial file to its final name
+    fn finalize(&mut self, _output_dir: &OutputDir) -> std::io::Result<()> {
+        if let Some(err) = self.failed.take() {
+            return Err(err);
+        }
+        let Some(mut writer) = self.writer.take() else {
+            return Ok(());
+        };
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&self.partial, self.partial.with_file_name(DAILY_FILE))
+    }
+
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let status = if self.writer.is_some() { "writing" } else { "finished" };
+        ui.label(format!("{} days saved to {} ({})", self.rows, DAILY_FILE, status));
+    }
+}
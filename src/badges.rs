@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::scheduled::{HostAction, ScheduledActions, ScheduledAt};
+use crate::treatment::TreatmentHistory;
+use crate::trial::Vaccinated;
+use crate::{Host, Params, SimulationTime};
+
+const BADGE_SIZE: f32 = 8.0;
+const BADGE_SPACING: f32 = 12.0;
+const BADGE_OFFSET: f32 = 14.0; // Below the host's bar, clear of the cluster and focus outlines
+const COUNTDOWN_WIDTH: f32 = 50.0; // Full protection spans the host's bar
+const COUNTDOWN_OFFSET: f32 = 6.0;
+const REQUEST_OUTLINE: Vec2 = Vec2::new(66.0, 21.0); // Just outside the cluster and focus outlines
+const REQUEST_PIPS: u32 = 7; // Most days left shown, one pip each
+
+// An intervention a host has right now, which the bar's state color can't show alongside its infection state
+#[derive(Clone, Copy)]
+enum Badge {
+    Vaccine,   // Green dot
+    Treatment, // White cross, while a course is scheduled but not yet taken
+}
+
+impl Badge {
+    fn draw(self, gizmos: &mut Gizmos, center: Vec2) {
+        match self {
+            Badge::Vaccine => {
+                gizmos.circle_2d(center, BADGE_SIZE / 2.0, Color::LIME_GREEN);
+            }
+            Badge::Treatment => {
+                let half = BADGE_SIZE / 2.0;
+                gizmos.line_2d(center - Vec2::X * half, center + Vec2::X * half, Color::WHITE);
+                gizmos.line_2d(center - Vec2::Y * half, center + Vec2::Y * half, Color::WHITE);
+            }
+        }
+    }
+}
+
+// Badges in a row under each host, each in its own slot so it keeps its place as others come and go
+pub fn draw_badges(mut gizmos: Gizmos, host_query: Query<(&Transform, &ScheduledActions, Option<&Vaccinated>), With<Host>>) {
+    for (transform, actions, vaccinated) in host_query.iter() {
+        let treatment = actions.iter().any(|ScheduledAt(_, action)| matches!(action, HostAction::Treat(_)));
+        let badges = [(vaccinated.is_some(), Badge::Vaccine), (treatment, Badge::Treatment)];
+        let start = transform.translation.truncate() + Vec2::new(-BADGE_SPACING / 2.0, -BADGE_OFFSET);
+        for (slot, (shown, badge)) in badges.into_iter().enumerate() {
+            if shown {
+                badge.draw(&mut gizmos, start + Vec2::X * slot as f32 * BADGE_SPACING);
+            }
+        }
+    }
+}
+
+// A green line under each host on prophylaxis that shrinks towards the left as protection runs out, moving smoothly
+// within each day, so the lapse can be seen coming rather than the green bar just turning gray
+pub fn draw_prophylaxis_countdown(
+    mut gizmos: Gizmos,
+    host_query: Query<(&Transform, &Host, &ScheduledActions, &TreatmentHistory)>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+) {
+    let now = sim_time.day as f32 + sim_time.timer.fraction();
+    for (transform, host, actions, history) in host_query.iter() {
+        if !host.on_prophylaxis {
+            continue;
+        }
+        let Some(&ScheduledAt(end_day, _)) = actions.iter().find(|ScheduledAt(_, action)| *action == HostAction::EndProphylaxis) else {
+            continue;
+        };
+        // Prophylaxis starts with the latest course; a host restored from a snapshot has none, so it's measured
+        // against the longer of the two drugs' protection
+        let remaining = (end_day as f32 - now).max(0.0);
+        let longest = params.treatment_drug.duration_prophylaxis.max(params.mda_drug.duration_prophylaxis);
+        let total = history.last_course.map_or(longest, |(day, _)| end_day.saturating_sub(day) as f32);
+        if total <= 0.0 {
+            continue;
+        }
+        let fraction = (remaining / total).min(1.0);
+        let left = transform.translation.truncate() + Vec2::new(-COUNTDOWN_WIDTH / 2.0, -COUNTDOWN_OFFSET);
+        gizmos.line_2d(left, left + Vec2::X * COUNTDOWN_WIDTH * fraction, Color::GREEN);
+    }
+}
+
+// A pulsing yellow outline on each host waiting for a course it has asked for or been offered, quickening as the
+// day nears, with a pip to the right of the bar for each whole day left
+pub fn draw_treatment_requests(
+    mut gizmos: Gizmos,
+    host_query: Query<(&Transform, &ScheduledActions), With<Host>>,
+    sim_time: Res<SimulationTime>,
+    time: Res<Time>,
+) {
+    let now = sim_time.day as f32 + sim_time.timer.fraction();
+    for (transform, actions) in host_query.iter() {
+        let Some(due) = actions.iter().filter(|ScheduledAt(_, action)| matches!(action, HostAction::Treat(_))).map(|at| at.0).min() else {
+            continue;
+        };
+        let remaining = (due as f32 - now).max(0.0);
+        let days_left = remaining.floor() as u32;
+        let pulses_per_second = match days_left {
+            0 => 2.0,
+            1 => 1.0,
+            _ => 0.5,
+        };
+        let alpha = 0.3 + 0.7 * (0.5 + 0.5 * (TAU * pulses_per_second * time.elapsed_seconds()).cos());
+        let center = transform.translation.truncate();
+        gizmos.rect_2d(center, 0.0, REQUEST_OUTLINE, Color::YELLOW.with_a(alpha));
+
+        for pip in 0..days_left.min(REQUEST_PIPS) {
+            let position = center + Vec2::new(REQUEST_OUTLINE.x / 2.0 + 5.0 + pip as f32 * 5.0, 0.0);
+            gizmos.line_2d(position - Vec2::Y * 3.0, position + Vec2::Y * 3.0, Color::YELLOW);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+use crate::Host;
+
+const SHIELD_SECS: f32 = 0.8;
+const SHIELD_RADIUS: f32 = 35.0; // Over the host's bar and its lowest inoculation square
+const SHIELD_GROWTH: f32 = 10.0;
+
+// A liver-stage inoculation cleared before reaching the blood, by prophylaxis or by the host's immunity
+#[derive(Event)]
+pub struct ExposureBlocked {
+    pub host: Entity,
+    pub by_prophylaxis: bool,
+}
+
+// Exposures blocked so far in this run
+#[derive(Resource, Default)]
+pub struct BlockedExposures {
+    pub by_prophylaxis: u32,
+    pub by_immunity: u32,
+}
+
+// Hosts whose prophylaxis just blocked an inoculation, with when their shield went up
+#[derive(Resource, Default)]
+pub struct ShieldFlashes(Vec<(Entity, f32)>);
+
+pub fn record_blocked_exposures(
+    mut events: EventReader<ExposureBlocked>,
+    mut blocked: ResMut<BlockedExposures>,
+    mut flashes: ResMut<ShieldFlashes>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        if event.by_prophylaxis {
+            blocked.by_prophylaxis += 1;
+            flashes.0.push((event.host, time.elapsed_seconds()));
+        } else {
+            blocked.by_immunity += 1;
+        }
+    }
+}
+
+// A green dome over the host that swells and fades, in place of the inoculation silently vanishing
+#[cfg(feature = "gui")]
+pub fn draw_shield_flashes(
+    mut gizmos: Gizmos,
+    mut flashes: ResMut<ShieldFlashes>,
+    host_query: Query<&Transform, With<Host>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    flashes.0.retain(|&(_, started)| now - started < SHIELD_SECS);
+    for &(host, started) in &flashes.0 {
+        let Ok(transform) = host_query.get(host) else {
+            continue;
+        };
+        let progress = (now - started) / SHIELD_SECS;
+        let color = Color::GREEN.with_a(1.0 - progress);
+        let radius = SHIELD_RADIUS + SHIELD_GROWTH * progress;
+        gizmos.arc_2d(transform.translation.truncate(), 0.0, PI, radius, color);
+        gizmos.arc_2d(transform.translation.truncate(), 0.0, PI, radius - 3.0, color);
+    }
+}
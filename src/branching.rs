@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ids::HostId;
+use crate::budget::EntityBudget;
+use crate::headless::HeadlessRun;
+use crate::scenario::MdaConfig;
+use crate::supply::{DrugStock, DrugSupply};
+use crate::treatment::{TreatmentHistory, TreatmentPolicy};
+use crate::scheduled::ScheduledActions;
+use crate::environment::Suitability;
+use crate::infectiousness::InfectiousnessCurve;
+use crate::stats::StateHistory;
+use crate::venues::VenueExposure;
+use crate::age_mixing::AgeMixingExposure;
+use crate::occupations::Occupation;
+use crate::trial::Vaccinated;
+use crate::vectors::{VectorPopulation, Vectors};
+use crate::{Host, Inoculation, Params, Regimen, SimRng, SimulationTime};
+
+const REPLICATES: u64 = 20;
+
+// Everything a fork copies from each host
+type ForkedHosts<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Host,
+        &'static HostId,
+        &'static ScheduledActions,
+        &'static TreatmentHistory,
+        Option<&'static Children>,
+        Option<&'static Suitability>,
+        Option<&'static VenueExposure>,
+        Option<&'static AgeMixingExposure>,
+        Option<&'static Occupation>,
+        Option<&'static Vaccinated>,
+    ),
+>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intervention {
+    MdaRound,       // One MDA round today at the default coverage
+    HalveIncidence, // e.g. vector control
+    ImproveTreatment, // Treatment probability raised to 90%
+}
+
+impl Intervention {
+    const ALL: [Intervention; 3] = [Intervention::MdaRound, Intervention::HalveIncidence, Intervention::ImproveTreatment];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Intervention::MdaRound => "MDA round",
+            Intervention::HalveIncidence => "Halve incidence",
+            Intervention::ImproveTreatment => "Treatment to 90%",
+        }
+    }
+}
+
+// Mean infected hosts (acute or chronic) per day after the fork, with and without the intervention
+pub struct WhatIfResult {
+    start_day: u32,
+    intervention: Intervention,
+    without: Vec<f64>,
+    with: Vec<f64>,
+}
+
+#[derive(Resource)]
+pub struct WhatIf {
+    intervention: Intervention,
+    horizon_days: u32,
+    requested: bool,
+    running: Option<Task<WhatIfResult>>, // Forks run off the main thread, so the live run keeps going meanwhile
+    result: Option<WhatIfResult>,
+}
+
+impl Default for WhatIf {
+    fn default() -> Self {
+        Self {
+            intervention: Intervention::MdaRound,
+            horizon_days: 180,
+            requested: false,
+            running: None,
+            result: None,
+        }
+    }
+}
+
+// Copies the hosts and their inoculations into a headless run from today
+#[allow(clippy::too_many_arguments)]
+fn fork_world(
+    host_query: &ForkedHosts,
+    inoc_query: &Query<&Inoculation>,
+    params: &Params,
+    budget: &EntityBudget,
+    treatment: (&TreatmentPolicy, &DrugSupply, &DrugStock),
+    sim_time: &SimulationTime,
+    transmission: (&Vectors, &VectorPopulation, &InfectiousnessCurve),
+    seed: u64,
+) -> HeadlessRun {
+    let mut world = World::new();
+    world.insert_resource(params.clone());
+    world.insert_resource(budget.clone());
+    world.insert_resource(treatment.0.clone());
+    world.insert_resource(treatment.1.clone());
+    world.insert_resource(treatment.2.clone()); // Deliveries carry on in the fork
+    world.insert_resource(transmission.0.clone());
+    world.insert_resource(transmission.1.clone());
+    world.insert_resource(transmission.2.clone());
+    world.insert_resource(SimulationTime::new(sim_time.timer.duration().as_secs_f32()));
+    world.resource_mut::<SimulationTime>().day = sim_time.day;
+    world.insert_resource(SimRng::new(seed));
+
+    for (host, id, scheduled, history, children, suitability, venue_exposure, mixing_exposure, occupation, vaccinated) in
+        host_query.iter()
+    {
+        let inoculations: Vec<Inoculation> = children
+            .into_iter()
+            .flatten()
+            .filter_map(|&child| inoc_query.get(child).ok())
+            .cloned()
+            .collect();
+        let mut entity = world.spawn((host.clone(), *id, scheduled.clone(), history.clone()));
+        if let Some(suitability) = suitability {
+            entity.insert(*suitability);
+        }
+        // Venue and age-group mixing stay at today's level in the fork
+        if let Some(venue_exposure) = venue_exposure {
+            entity.insert(*venue_exposure);
+        }
+        if let Some(mixing_exposure) = mixing_exposure {
+            entity.insert(*mixing_exposure);
+        }
+        // So does work; trips aren't followed
+        if let Some(occupation) = occupation {
+            entity.insert(occupation.clone());
+        }
+        if let Some(vaccinated) = vaccinated {
+            entity.insert(*vaccinated);
+        }
+        entity.with_children(|parent| {
+            for inoc in inoculations {
+                parent.spawn(inoc);
+            }
+        });
+    }
+    HeadlessRun::from_world(world) // Inoculation ids in a fork are never exported, so they start afresh
+}
+
+fn apply_intervention(world: &mut World, intervention: Intervention, day: u32, seed: u64) {
+    match intervention {
+        Intervention::MdaRound => {
+            let coverage = MdaConfig::default().coverage;
+            let mut rng = StdRng::seed_from_u64(seed);
+            for mut scheduled in world.query_filtered::<&mut ScheduledActions, With<Host>>().iter_mut(world) {
+                if rng.gen::<f32>() < coverage {
+                    scheduled.request_treatment(day, Regimen::Mda);
+                }
+            }
+        }
+        Intervention::HalveIncidence => world.resource_mut::<Params>().incidence_rate *= 0.5,
+        Intervention::ImproveTreatment => world.resource_mut::<Params>().prob_treatment = 0.9,
+    }
+}
+
+// Infected hosts on each day of the continuation
+fn run_branch(mut run: HeadlessRun, horizon_days: u32) -> Vec<usize> {
+    for _ in 0..horizon_days {
+        run.step();
+    }
+
+    run.world
+        .resource::<StateHistory>()
+        .samples
+        .iter()
+        .map(|(_, counts)| counts[2] + counts[3]) // Acute and chronic, in HostState::ALL order
+        .collect()
+}
+
+// Runs paired replicates from the current state; each pair shares a seed, so differences come from the intervention
+#[allow(clippy::too_many_arguments)]
+pub fn run_what_if(
+    host_query: ForkedHosts,
+    inoc_query: Query<&Inoculation>,
+    params: Res<Params>,
+    budget: Res<EntityBudget>,
+    policy: Res<TreatmentPolicy>,
+    supply: Res<DrugSupply>,
+    stock: Res<DrugStock>,
+    sim_time: Res<SimulationTime>,
+    sim_rng: Res<SimRng>,
+    vectors: Res<Vectors>,
+    vector_population: Res<VectorPopulation>,
+    curve: Res<InfectiousnessCurve>,
+    mut what_if: ResMut<WhatIf>,
+) {
+    if let Some(running) = what_if.running.as_mut() {
+        if let Some(result) = block_on(poll_once(running)) {
+            what_if.result = Some(result);
+            what_if.running = None;
+        }
+    }
+    if !what_if.requested || what_if.running.is_some() {
+        return;
+    }
+    what_if.requested = false;
+
+    // The forks are copied from today's state here; only running them is left to the task
+    let mut pairs = Vec::new();
+    for replicate in 0..REPLICATES {
+        // Derived rather than drawn, so forking doesn't disturb the main run's random stream
+        let seed = sim_rng.seed ^ ((sim_time.day as u64) << 32) ^ replicate;
+
+        let treatment = (policy.as_ref(), supply.as_ref(), stock.as_ref());
+        let transmission = (vectors.as_ref(), vector_population.as_ref(), curve.as_ref());
+        let baseline = fork_world(&host_query, &inoc_query, &params, &budget, treatment, &sim_time, transmission, seed);
+        let mut treated = fork_world(&host_query, &inoc_query, &params, &budget, treatment, &sim_time, transmission, seed);
+        apply_intervention(&mut treated.world, what_if.intervention, sim_time.day, seed);
+        pairs.push((baseline, treated));
+    }
+
+    let (start_day, intervention, horizon_days) = (sim_time.day, what_if.intervention, what_if.horizon_days);
+    what_if.running = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let horizon = horizon_days as usize;
+        let (mut without, mut with) = (vec![0.0; horizon], vec![0.0; horizon]);
+        for (baseline, treated) in pairs {
+            for (totals, branch) in [(&mut without, baseline), (&mut with, treated)] {
+                for (total, infected) in totals.iter_mut().zip(run_branch(branch, horizon_days)) {
+                    *total += infected as f64 / REPLICATES as f64;
+                }
+            }
+        }
+        WhatIfResult {
+            start_day,
+            intervention,
+            without,
+            with,
+        }
+    }));
+}
+
+pub fn what_if_ui(mut contexts: EguiContexts, mut what_if: ResMut<WhatIf>) {
+    egui::Window::new("What If")
+        .default_pos(egui::pos2(850.0, 50.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("what_if_intervention")
+                    .selected_text(what_if.intervention.label())
+                    .show_ui(ui, |ui| {
+                        for intervention in Intervention::ALL {
+                            ui.selectable_value(&mut what_if.intervention, intervention, intervention.label());
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut what_if.horizon_days, 30..=365).text("Days"));
+            });
+            let idle = what_if.running.is_none();
+            if ui.add_enabled(idle, egui::Button::new("Fork from today")).clicked() {
+                what_if.requested = true;
+            }
+            if !idle {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Running {} paired forks...", REPLICATES));
+                });
+            }
+
+            let Some(result) = &what_if.result else {
+                return;
+            };
+            let averted: f64 = result.without.iter().sum::<f64>() - result.with.iter().sum::<f64>();
+            let baseline: f64 = result.without.iter().sum();
+            ui.label(format!(
+                "{} from day {}: {:.0} infected host-days averted ({:.0}%), mean of {} paired runs",
+                result.intervention.label(),
+                result.start_day,
+                averted,
+                if baseline > 0.0 { 100.0 * averted / baseline } else { 0.0 },
+                REPLICATES
+            ));
+
+            let line = |values: &[f64]| -> PlotPoints {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, value)| [(result.start_day as usize + offset + 1) as f64, *value])
+                    .collect()
+            };
+            Plot::new("what_if")
+                .legend(Legend::default())
+                .include_y(0.0)
+                .height(150.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(line(&result.without)).name("Without"));
+                    plot_ui.line(Line::new(line(&result.with)).name(result.intervention.label()));
+                });
+        });
+}
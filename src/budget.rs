@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+
+use crate::event_log::EventLog;
+use crate::{Host, Inoculation, SimulationTime};
+
+const WARN_FRACTION: f32 = 0.8; // Share of a cap at which the budget warning appears
+
+// Caps that stop extreme settings from spawning inoculations without bound
+#[derive(Resource, Clone)]
+pub struct EntityBudget {
+    pub max_per_host: usize,
+    pub max_total: usize,
+}
+
+impl EntityBudget {
+    pub fn allows(&self, host_inoculations: usize, total: usize) -> bool {
+        host_inoculations < self.max_per_host && total < self.max_total
+    }
+}
+
+// An infection that was drawn but not spawned because a cap was reached
+#[derive(Event)]
+pub struct InoculationRefused;
+
+#[derive(Resource, Default)]
+pub struct BudgetUsage {
+    pub inoculations: usize,
+    pub most_per_host: usize,
+    pub refused: u32,
+    last_refusal_day: Option<u32>,
+}
+
+pub fn track_budget(
+    inoc_query: Query<(), With<Inoculation>>,
+    host_query: Query<&Children, With<Host>>,
+    sim_time: Res<SimulationTime>,
+    mut usage: ResMut<BudgetUsage>,
+    mut refusals: EventReader<InoculationRefused>,
+    mut event_log: ResMut<EventLog>,
+) {
+    usage.inoculations = inoc_query.iter().count();
+    usage.most_per_host = host_query.iter().map(|children| children.len()).max().unwrap_or(0);
+
+    let refused = refusals.read().count() as u32;
+    if refused == 0 {
+        return;
+    }
+    usage.refused += refused;
+    // Noted when refusals start again after at least a day without any
+    if usage.last_refusal_day.is_none_or(|day| sim_time.day > day + 1) {
+        event_log.log(sim_time.day, "Inoculation cap reached; new infections are being refused");
+    }
+    usage.last_refusal_day = Some(sim_time.day);
+}
+
+#[cfg(feature = "gui")]
+pub fn budget_ui(mut contexts: EguiContexts, budget: Res<EntityBudget>, usage: Res<BudgetUsage>) {
+    let near = |count: usize, cap: usize| count as f32 >= WARN_FRACTION * cap as f32;
+    if !near(usage.inoculations, budget.max_total) && !near(usage.most_per_host, budget.max_per_host) && usage.refused == 0 {
+        return;
+    }
+
+    egui::Window::new("Entity Budget")
+        .default_pos(egui::pos2(450.0, 50.0))
+        .show(contexts.ctx_mut(), |ui| {
+            let warning = |ui: &mut egui::Ui, text: String| ui.colored_label(egui::Color32::YELLOW, text);
+            warning(ui, format!("Inoculations: {} of {}", usage.inoculations, budget.max_total));
+            warning(ui, format!("Most in one host: {} of {}", usage.most_per_host, budget.max_per_host));
+            if usage.refused > 0 {
+                warning(ui, format!("{} new infections refused", usage.refused));
+            }
+            ui.label("Raise the caps with --max-inoculations and --max-inoculations-per-host");
+        });
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::ids::{HostId, InoculationId};
+use crate::report::RunReport;
+use crate::{Genotype, InfectionState, Inoculation, OutputDir, Params};
+
+const FADE_SECS: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearCause {
+    Blocked,  // By prophylaxis or immunity at the end of the liver stage
+    Resolved, // Reached the end of its acute or chronic stage
+    Treated,  // Cured by a drug
+    Manual,   // Cleared from the Sandbox window
+}
+
+impl ClearCause {
+    fn label(self) -> &'static str {
+        match self {
+            ClearCause::Blocked => "blocked",
+            ClearCause::Resolved => "resolved",
+            ClearCause::Treated => "treated",
+            ClearCause::Manual => "manual",
+        }
+    }
+}
+
+// An inoculation that has left its host: no longer part of the model, but kept briefly to fade out where it was
+#[derive(Component, Clone, Copy)]
+pub struct Cleared {
+    pub host: Entity,
+    pub state: InfectionState, // Last state before clearing
+    pub genotype: Genotype,
+    pub infected_day: u32,
+    pub day: u32,
+    pub cause: ClearCause,
+    faded_secs: f32,
+}
+
+impl Cleared {
+    pub fn duration_days(&self) -> u32 {
+        self.day.saturating_sub(self.infected_day)
+    }
+}
+
+// Takes the inoculation out of its host and the model; used everywhere an inoculation ends, in place of despawning it
+pub fn clear_inoculation(commands: &mut Commands, host: Entity, entity: Entity, inoc: &Inoculation, day: u32, cause: ClearCause) {
+    commands.entity(host).remove_children(&[entity]);
+    commands.entity(entity).remove::<Inoculation>().insert(Cleared {
+        host,
+        state: inoc.state,
+        genotype: inoc.genotype,
+        infected_day: inoc.infected_day,
+        day,
+        cause,
+        faded_secs: 0.0,
+    });
+}
+
+// How many inoculations a host has had clear and how long they lasted, kept as running totals
+#[derive(Component, Default, Clone)]
+pub struct InfectionHistory {
+    pub count: u32,
+    total_days: u32,
+}
+
+impl InfectionHistory {
+    fn record(&mut self, cleared: &Cleared) {
+        self.count += 1;
+        self.total_days += cleared.duration_days();
+    }
+
+    pub fn mean_days(&self) -> f32 {
+        self.total_days as f32 / self.count.max(1) as f32
+    }
+}
+
+#[derive(Resource)]
+pub struct ClearedOutput(BufWriter<File>);
+
+pub fn setup_cleared_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("cleared_inoculations.csv"))?);
+        writeln!(writer, "inoculation,host,genotype,infected_day,cleared_day,duration_days,last_state,cause")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(ClearedOutput(writer)),
+        Err(err) => report.output_failed("cleared inoculations", err),
+    }
+}
+
+// Pins each newly cleared inoculation where it stood, now that it has no parent to move with, and records it
+pub fn record_cleared(
+    mut cleared_query: Query<(&Cleared, &InoculationId, &mut Transform), Added<Cleared>>,
+    mut host_query: Query<(&HostId, &Transform, &mut InfectionHistory), Without<Cleared>>,
+    params: Res<Params>,
+    mut output: Option<ResMut<ClearedOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    for (cleared, inoc_id, mut transform) in cleared_query.iter_mut() {
+        let Ok((host_id, host_transform, mut history)) = host_query.get_mut(cleared.host) else {
+            continue;
+        };
+        transform.translation = host_transform.transform_point(transform.translation);
+        history.record(cleared);
+
+        if let Some(output) = output.as_mut() {
+            let result = writeln!(
+                output.0,
+                "{},{},{},{},{},{},{:?},{}",
+                inoc_id.0,
+                host_id.0,
+                cleared.genotype.label(params.n_loci),
+                cleared.infected_day,
+                cleared.day,
+                cleared.duration_days(),
+                cleared.state,
+                cleared.cause.label()
+            );
+            if let Err(err) = result.and_then(|_| output.0.flush()) {
+                report.output_failed("cleared inoculations", err);
+            }
+        }
+    }
+}
+
+// Without a window nothing fades cleared inoculations out, so headless runs drop them at the end of each day
+pub fn despawn_cleared(mut commands: Commands, cleared_query: Query<Entity, With<Cleared>>) {
+    for entity in cleared_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+// Fades from the last state color, as update_inoculation_colors no longer touches it
+#[cfg(feature = "gui")]
+pub fn fade_cleared(mut commands: Commands, mut cleared_query: Query<(Entity, &mut Cleared, &mut Sprite)>, time: Res<Time>) {
+    for (entity, mut cleared, mut sprite) in cleared_query.iter_mut() {
+        cleared.faded_secs += time.delta_seconds();
+        if cleared.faded_secs >= FADE_SECS {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sprite.color.set_a(1.0 - cleared.faded_secs / FADE_SECS);
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+const THROUGHPUT_WINDOW_SECS: f32 = 5.0;
+
+// Wall-clock driven simulation days, with pause, speed presets and actions scheduled for later days
+pub struct SimulationClockPlugin {
+    pub seconds_per_day: f32,
+}
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimulationTime::new(self.seconds_per_day))
+            .insert_resource(SimulationSpeed::default())
+            .insert_resource(Throughput::default())
+            .add_event::<DayTick>()
+            // Ahead of Update, so every system sees the new day and its DayTick in the same frame
+            .add_systems(PreUpdate, (tick_clock, run_scheduled, measure_throughput).chain());
+    }
+}
+
+// Sent once at the start of each simulation day, including day 0 of every run
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DayTick {
+    pub day: u32,
+}
+
+type ScheduledAction = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+#[derive(Resource)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Resource))]
+pub struct SimulationTime {
+    pub day: u32,
+    pub timer: Timer,
+    announced: Option<u32>, // Last day sent as a DayTick
+    #[cfg_attr(feature = "inspector", reflect(ignore))]
+    scheduled: Vec<(u32, ScheduledAction)>,
+}
+
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self::new(1.0) // One day per second
+    }
+}
+
+impl SimulationTime {
+    pub fn new(seconds_per_day: f32) -> Self {
+        Self {
+            day: 0,
+            timer: Timer::from_seconds(seconds_per_day, TimerMode::Repeating),
+            announced: None,
+            scheduled: Vec::new(),
+        }
+    }
+
+    // Runs `action` at the start of the day `days` from today; dropped if the run is reset first
+    pub fn schedule_in(&mut self, days: u32, action: impl FnOnce(&mut World) + Send + Sync + 'static) {
+        self.scheduled.push((self.day + days, Box::new(action)));
+    }
+}
+
+#[derive(Resource)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Resource))]
+pub struct SimulationSpeed {
+    pub multiplier: f32, // 1.0 by default
+    pub paused: bool,
+}
+
+impl SimulationSpeed {
+    pub const RANGE: (f32, f32) = (0.5, 5.0);
+    pub const PRESETS: [(&'static str, f32); 4] = [("Slow", 0.5), ("Normal", 1.0), ("Fast", 2.0), ("Fastest", 5.0)];
+    const STEP: f32 = 0.5;
+
+    // Simulated days per wall-clock day, zero while paused
+    pub fn effective(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.multiplier
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn faster(&mut self) {
+        self.multiplier = (self.multiplier + Self::STEP).min(Self::RANGE.1);
+    }
+
+    pub fn slower(&mut self) {
+        self.multiplier = (self.multiplier - Self::STEP).max(Self::RANGE.0);
+    }
+}
+
+impl Default for SimulationSpeed {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            paused: false,
+        }
+    }
+}
+
+// Wall-clock time of the current run and its recent progress in simulated days
+#[derive(Resource, Default)]
+pub struct Throughput {
+    run_started: f32,              // Elapsed app seconds when the run began
+    samples: VecDeque<(f32, f32)>, // Elapsed app seconds and fractional simulation day, over the last few seconds
+}
+
+impl Throughput {
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.samples.back().map_or(0.0, |(now, _)| now - self.run_started)
+    }
+
+    pub fn days_per_second(&self) -> f32 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(start, first)), Some(&(end, last))) if end > start => (last - first) / (end - start),
+            _ => 0.0,
+        }
+    }
+
+    // Wall-clock seconds left until `end_day` at the recent rate, None while the clock is stalled
+    pub fn eta_seconds(&self, end_day: u32) -> Option<f32> {
+        let rate = self.days_per_second();
+        let &(_, day) = self.samples.back()?;
+        (rate > 0.0).then(|| (end_day as f32 - day).max(0.0) / rate)
+    }
+}
+
+pub fn format_duration(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+fn measure_throughput(time: Res<Time>, sim_time: Res<SimulationTime>, mut throughput: ResMut<Throughput>) {
+    let now = time.elapsed_seconds();
+    let day = sim_time.day as f32 + sim_time.timer.fraction();
+    // A reset sends the clock back, so the wall time restarts with it
+    if throughput.samples.is_empty() || throughput.samples.back().is_some_and(|&(_, last)| day < last) {
+        *throughput = Throughput {
+            run_started: now,
+            samples: VecDeque::new(),
+        };
+    }
+    throughput.samples.push_back((now, day));
+    while throughput.samples.front().is_some_and(|&(seconds, _)| now - seconds > THROUGHPUT_WINDOW_SECS) {
+        throughput.samples.pop_front();
+    }
+}
+
+fn tick_clock(time: Res<Time>, speed: Res<SimulationSpeed>, mut sim_time: ResMut<SimulationTime>, mut ticks: EventWriter<DayTick>) {
+    sim_time.timer.tick(time.delta().mul_f32(speed.effective()));
+    if sim_time.timer.just_finished() {
+        sim_time.day += 1;
+    }
+
+    // A reset starts a new clock, so day 0 is announced again
+    if sim_time.announced != Some(sim_time.day) {
+        sim_time.announced = Some(sim_time.day);
+        let _span = info_span!("day_tick", day = sim_time.day).entered();
+        debug!(day = sim_time.day, "day tick");
+        ticks.send(DayTick { day: sim_time.day });
+    }
+}
+
+fn run_scheduled(world: &mut World) {
+    let mut sim_time = world.resource_mut::<SimulationTime>();
+    let today = sim_time.day;
+    if !sim_time.scheduled.iter().any(|(day, _)| *day <= today) {
+        return;
+    }
+    let (due, pending) = std::mem::take(&mut sim_time.scheduled).into_iter().partition(|(day, _)| *day <= today);
+    sim_time.scheduled = pending;
+    for (_, action) in due {
+        action(world);
+    }
+}
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::autosave::write_atomic;
+use crate::ids::InoculationId;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::{InfectionState, Inoculation, OutputDir, Params, SimulationTime};
+
+// Life of one initial inoculation as seen from day to day
+struct Record {
+    id: InoculationId,
+    path: Vec<InfectionState>,
+    durations: [u32; 3], // Days spent in E, A and C
+    entered_day: u32,    // Day the current state started
+    delay_days: f32,     // Scheduled length of the current state
+}
+
+impl Record {
+    fn state(&self) -> InfectionState {
+        *self.path.last().expect("a record always has a state")
+    }
+
+    fn close_state(&mut self, day: u32) {
+        let index = match self.state() {
+            InfectionState::E => 0,
+            InfectionState::A => 1,
+            InfectionState::C => 2,
+        };
+        self.durations[index] += day.saturating_sub(self.entered_day);
+    }
+}
+
+struct Outcome {
+    id: InoculationId,
+    path: String, // e.g. "E>A>C"
+    ending: &'static str,
+    durations: [u32; 3],
+}
+
+// Day-0 cohort for validating natural-history parameters: every host starts with one inoculation and no more arrive
+#[derive(Resource, Default)]
+pub struct Cohort {
+    records: HashMap<Entity, Record>,
+    outcomes: Vec<Outcome>,
+    written: bool,
+}
+
+fn path_label(path: &[InfectionState]) -> String {
+    path.iter().map(|state| format!("{:?}", state)).collect::<Vec<_>>().join(">")
+}
+
+fn write_outcomes(output_dir: &OutputDir, outcomes: &[Outcome]) -> std::io::Result<()> {
+    let mut csv = String::from("inoculation,path,ending,liver_days,acute_days,chronic_days\n");
+    let mut outcomes: Vec<&Outcome> = outcomes.iter().collect();
+    outcomes.sort_by_key(|outcome| outcome.id);
+    for outcome in outcomes {
+        let [liver, acute, chronic] = outcome.durations;
+        csv.push_str(&format!("{},{},{},{},{},{}\n", outcome.id.0, outcome.path, outcome.ending, liver, acute, chronic));
+    }
+    fs::create_dir_all(&output_dir.0)?;
+    write_atomic(&output_dir.0.join("cohort_outcomes.csv"), csv)
+}
+
+pub fn track_cohort(
+    inoc_query: Query<(Entity, &Inoculation, &InoculationId)>,
+    mut removed: RemovedComponents<Inoculation>,
+    sim_time: Res<SimulationTime>,
+    output_dir: Res<OutputDir>,
+    mut cohort: ResMut<Cohort>,
+    mut report: ResMut<RunReport>,
+) {
+    let day = sim_time.day;
+    for (entity, inoc, id) in inoc_query.iter() {
+        let record = cohort.records.entry(entity).or_insert_with(|| Record {
+            id: *id,
+            path: vec![inoc.state],
+            durations: [0; 3],
+            entered_day: inoc.start_day,
+            delay_days: inoc.delay_days,
+        });
+        if record.state() != inoc.state {
+            record.close_state(inoc.start_day);
+            record.path.push(inoc.state);
+            record.entered_day = inoc.start_day;
+        }
+        record.delay_days = inoc.delay_days;
+    }
+
+    for entity in removed.read() {
+        let Some(mut record) = cohort.records.remove(&entity) else {
+            continue;
+        };
+        // Inoculations cleared before their scheduled time were cured by a drug
+        let ending = if ((day - record.entered_day) as f32) < record.delay_days {
+            "treated"
+        } else if record.state() == InfectionState::E {
+            "blocked"
+        } else {
+            "cleared"
+        };
+        record.close_state(day);
+        cohort.outcomes.push(Outcome {
+            id: record.id,
+            path: path_label(&record.path),
+            ending,
+            durations: record.durations,
+        });
+    }
+
+    if !cohort.written && cohort.records.is_empty() && !cohort.outcomes.is_empty() {
+        cohort.written = true;
+        match write_outcomes(&output_dir, &cohort.outcomes) {
+            Ok(()) => info!("Cohort complete on day {}; wrote {}", day, output_dir.0.join("cohort_outcomes.csv").display()),
+            Err(err) => report.output_failed("cohort outcomes", err),
+        }
+    }
+}
+
+fn mean(values: impl Iterator<Item = u32>) -> Option<f32> {
+    let (sum, count) = values.fold((0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum as f32 / count as f32)
+}
+
+// Empirical outcomes next to the values the parameters intend
+#[cfg(feature = "gui")]
+pub fn cohort_ui(mut contexts: EguiContexts, cohort: Res<Cohort>, params: Res<Params>, analytics: Res<AnalyticsWindow>) {
+    egui::Window::new("Cohort")
+        .default_pos(egui::pos2(850.0, 650.0))
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            let done = cohort.outcomes.len();
+            ui.label(format!("Resolved: {} of {}", done, done + cohort.records.len()));
+
+            let mut paths: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+            for outcome in &cohort.outcomes {
+                *paths.entry((outcome.path.as_str(), outcome.ending)).or_default() += 1;
+            }
+            egui::Grid::new("cohort_paths").striped(true).show(ui, |ui| {
+                ui.label("Path");
+                ui.label("Ending");
+                ui.label("Share");
+                ui.end_row();
+                for ((path, ending), count) in paths {
+                    ui.label(path);
+                    ui.label(ending);
+                    ui.label(format!("{:.0}%", 100.0 * count as f32 / done as f32));
+                    ui.end_row();
+                }
+            });
+
+            ui.separator();
+            let reached = |state: InfectionState| {
+                let label = format!("{:?}", state);
+                cohort.outcomes.iter().filter(|o| o.path.contains(&label)).count()
+            };
+            let acute_share = reached(InfectionState::A) as f32 / reached(InfectionState::E).max(1) as f32;
+            ui.label(format!("Acute after liver stage: {:.2} (prob_acute {:.2})", acute_share, params.prob_acute));
+
+            // Durations are only comparable for states that ran their full course
+            let natural = || cohort.outcomes.iter().filter(|o| o.ending == "cleared");
+            let rows = [
+                ("Liver", 0, params.duration_liver),
+                ("Acute", 1, params.duration_acute.mean()),
+                ("Chronic", 2, params.duration_chronic.mean()),
+            ];
+            egui::Grid::new("cohort_durations").striped(true).show(ui, |ui| {
+                ui.label("Stage");
+                ui.label("Mean days");
+                ui.label("Intended");
+                ui.end_row();
+                for (name, index, intended) in rows {
+                    let observed = mean(natural().map(|o| o.durations[index]).filter(|days| *days > 0));
+                    ui.label(name);
+                    ui.label(observed.map_or("-".to_owned(), |days| format!("{:.1}", days)));
+                    ui.label(format!("{:.1}", intended));
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::HostState;
+
+// Host state counts exported by an earlier run
+pub struct SavedRun {
+    pub name: String,
+    pub states: Vec<(u32, [f64; 5])>,
+}
+
+impl SavedRun {
+    // host_states.csv or host_states_daily.csv, or any CSV with day,S,E,A,C,P columns
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header: Vec<&str> = lines.next().map_or(Vec::new(), |(_, line)| line.split(',').map(str::trim).collect());
+        let column = |name: &str| header.iter().position(|field| *field == name).ok_or_else(|| format!("{}: no {} column", path.display(), name));
+        let day_column = column("day")?;
+        let mut state_columns = [0; 5];
+        for (state_column, state) in state_columns.iter_mut().zip(HostState::ALL) {
+            *state_column = column(&format!("{:?}", state))?;
+        }
+
+        let mut states = Vec::new();
+        for (line_number, line) in lines {
+            let error = |message: String| format!("{}: line {}: {}", path.display(), line_number + 1, message);
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |column: usize| fields.get(column).copied().ok_or_else(|| error("missing column".to_owned()));
+            let day = field(day_column)?.parse::<u32>().map_err(|err| error(err.to_string()))?;
+            let mut counts = [0.0; 5];
+            for (count, &column) in counts.iter_mut().zip(&state_columns) {
+                *count = field(column)?.parse::<f64>().map_err(|err| error(err.to_string()))?;
+            }
+            states.push((day, counts));
+        }
+        if states.is_empty() {
+            return Err(format!("{}: no rows", path.display()));
+        }
+        Ok(Self {
+            name: path.display().to_string(),
+            states,
+        })
+    }
+}
+
+// Earlier runs overlaid on the Host States plot, from --compare or loaded in the window
+#[derive(Resource, Default)]
+pub struct RunComparison {
+    pub runs: Vec<SavedRun>,
+    path: String,
+    error: Option<String>,
+}
+
+impl RunComparison {
+    pub fn new(runs: Vec<SavedRun>) -> Self {
+        Self { runs, ..default() }
+    }
+
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).hint_text("output/host_states.csv"));
+            if ui.add_enabled(!self.path.trim().is_empty(), egui::Button::new("Load")).clicked() {
+                match SavedRun::load(&PathBuf::from(self.path.trim())) {
+                    Ok(run) => {
+                        self.runs.push(run);
+                        self.path.clear();
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+
+        let mut removed = None;
+        for (index, run) in self.runs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").clicked() {
+                    removed = Some(index);
+                }
+                ui.label(format!("{} ({} days)", run.name, run.states.last().map_or(0, |(day, _)| *day)));
+            });
+        }
+        if let Some(index) = removed {
+            self.runs.remove(index);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::budget::EntityBudget;
+use crate::coverage::{Dose, DoseOffered, OfferOutcome};
+use crate::event_log::EventLog;
+use crate::focus::pick_host;
+use crate::ids::{HostId, Ids};
+use crate::scenario::TrialIntervention;
+use crate::scheduled::{HostAction, ScheduledActions};
+use crate::trial::{TrialDesign, Vaccinated};
+use crate::{spawn_inoculation, Genotype, Host, Inoculation, MainCamera, Params, Regimen, SimRng, SimulationTime};
+
+const VACCINE_EFFICACY: f32 = 0.6; // When the scenario's trial doesn't set one
+
+#[derive(Clone, Copy)]
+enum MenuAction {
+    Infect,
+    Treat,
+    StartProphylaxis,
+    StopProphylaxis,
+    Vaccinate,
+    Kill,
+}
+
+// Host whose right-click menu is open, where it opened, and the action picked from it
+#[derive(Resource, Default)]
+pub struct HostMenu {
+    host: Option<Entity>,
+    position: egui::Pos2,
+    picked: Option<MenuAction>,
+    draws: Option<StdRng>, // Genotypes of manual infections, apart from the run's streams
+}
+
+// Right click opens the menu for the host under the cursor
+pub fn open_host_menu(
+    mut contexts: EguiContexts,
+    mouse: Res<ButtonInput<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    host_query: Query<(Entity, &GlobalTransform), With<Host>>,
+    mut menu: ResMut<HostMenu>,
+) {
+    if !mouse.just_pressed(MouseButton::Right) || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(window) = window_query.get_single().ok() else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    menu.host = pick_host(cursor, &camera_query, &host_query);
+    // Cursor is in logical pixels; egui's points also include its own scale, e.g. in presentation mode. The menu
+    // opens just under the cursor, so releasing the button doesn't count as a click elsewhere.
+    let points = cursor * window.scale_factor() / contexts.ctx_mut().pixels_per_point();
+    menu.position = egui::pos2(points.x - 4.0, points.y - 4.0);
+}
+
+pub fn host_menu_ui(mut contexts: EguiContexts, mut menu: ResMut<HostMenu>, host_query: Query<(&HostId, &Host)>) {
+    let Some(entity) = menu.host else {
+        return;
+    };
+    let Ok((id, host)) = host_query.get(entity) else {
+        menu.host = None;
+        return;
+    };
+
+    let mut picked = None;
+    let response = egui::Area::new(egui::Id::new("host_menu")).fixed_pos(menu.position).order(egui::Order::Foreground).show(
+        contexts.ctx_mut(),
+        |ui| {
+            egui::Frame::menu(ui.style()).show(ui, |ui| {
+                ui.label(format!("Host {}", id.0));
+                ui.separator();
+                let prophylaxis = if host.on_prophylaxis {
+                    ("Stop prophylaxis", MenuAction::StopProphylaxis)
+                } else {
+                    ("Start prophylaxis", MenuAction::StartProphylaxis)
+                };
+                let actions = [("Infect now", MenuAction::Infect), ("Treat now", MenuAction::Treat), prophylaxis, ("Vaccinate", MenuAction::Vaccinate), ("Kill", MenuAction::Kill)];
+                for (label, action) in actions {
+                    if ui.button(label).clicked() {
+                        picked = Some(action);
+                    }
+                }
+            });
+        },
+    );
+    if picked.is_some() || response.response.clicked_elsewhere() {
+        menu.picked = picked;
+        if picked.is_none() {
+            menu.host = None;
+        }
+    }
+}
+
+// Carries out the picked action, noting it in the Event Log so manual changes to a run are on record
+#[allow(clippy::too_many_arguments)]
+pub fn apply_host_menu_action(
+    mut commands: Commands,
+    mut menu: ResMut<HostMenu>,
+    mut host_query: Query<(&HostId, &mut Host, &mut ScheduledActions, Option<&Children>)>,
+    inoc_query: Query<(), With<Inoculation>>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    sim_rng: Res<SimRng>,
+    mut ids: ResMut<Ids>,
+    budget: Res<EntityBudget>,
+    trial: Res<TrialDesign>,
+    mut event_log: ResMut<EventLog>,
+    mut offers: EventWriter<DoseOffered>,
+) {
+    let (Some(entity), Some(action)) = (menu.host, menu.picked.take()) else {
+        return;
+    };
+    menu.host = None;
+    let Ok((&id, mut host, mut scheduled, children)) = host_query.get_mut(entity) else {
+        return;
+    };
+
+    let day = sim_time.day;
+    let message = match action {
+        MenuAction::Infect => {
+            if !budget.allows(children.map_or(0, |c| c.len()), inoc_query.iter().count()) {
+                event_log.log(day, format!("Host {} could not be infected: inoculation limit reached", id.0));
+                return;
+            }
+            let rng = menu.draws.get_or_insert_with(|| StdRng::seed_from_u64(sim_rng.seed ^ 0x3e4));
+            let genotype = Genotype::random(params.n_loci, params.resistant_fraction, rng);
+            spawn_inoculation(&mut commands, entity, children, genotype, &params, day, &mut ids);
+            "infected by hand"
+        }
+        MenuAction::Treat => {
+            // Offered like any other course, so the host can still refuse it or find the stock empty
+            scheduled.schedule(day, HostAction::Treat(Regimen::Treatment));
+            "offered treatment by hand"
+        }
+        MenuAction::StartProphylaxis => {
+            host.on_prophylaxis = true;
+            scheduled.cancel(|action| *action == HostAction::EndProphylaxis);
+            scheduled.schedule(day + params.treatment_drug.duration_prophylaxis.max(1.0) as u32, HostAction::EndProphylaxis);
+            "put on prophylaxis by hand"
+        }
+        MenuAction::StopProphylaxis => {
+            host.on_prophylaxis = false;
+            scheduled.cancel(|action| *action == HostAction::EndProphylaxis);
+            "taken off prophylaxis by hand"
+        }
+        MenuAction::Vaccinate => {
+            let efficacy = match trial.0.as_ref().and_then(|config| config.intervention) {
+                Some(TrialIntervention::Vaccine { efficacy }) => efficacy,
+                _ => VACCINE_EFFICACY,
+            };
+            commands.entity(entity).insert(Vaccinated(efficacy));
+            offers.send(DoseOffered {
+                host: entity,
+                dose: Dose::Vaccine,
+                outcome: OfferOutcome::Delivered,
+            });
+            "vaccinated by hand"
+        }
+        MenuAction::Kill => {
+            commands.entity(entity).despawn_recursive();
+            "killed by hand"
+        }
+    };
+    event_log.log(day, format!("Host {} {}", id.0, message));
+}
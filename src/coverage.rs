@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::autosave::write_atomic;
+use crate::clock::DayTick;
+use crate::ids::HostId;
+use crate::report::RunReport;
+use crate::{OutputDir, Regimen, ResetSimulation, SimulationTime};
+
+// What a host was offered: a drug course for one of the regimens, or the trial or hand-given vaccine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dose {
+    Drug(Regimen),
+    Vaccine,
+}
+
+impl Dose {
+    const ALL: [Dose; 4] = [Dose::Drug(Regimen::Treatment), Dose::Drug(Regimen::Mda), Dose::Drug(Regimen::Screening), Dose::Vaccine];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Dose::Drug(regimen) => regimen.label(),
+            Dose::Vaccine => "vaccine",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfferOutcome {
+    Delivered,
+    Refused,
+    StockOut, // Accepted but no course in stock
+    Withheld, // The host has had as many courses as the policy allows
+}
+
+// Sent whenever a dose falls due for a host, with what became of it
+#[derive(Event)]
+pub struct DoseOffered {
+    pub host: Entity,
+    pub dose: Dose,
+    pub outcome: OfferOutcome,
+}
+
+#[derive(Default, Clone, Copy)]
+struct DayCounts {
+    offered: u32,
+    delivered: u32,
+    refused: u32,
+    stock_outs: u32,
+    withheld: u32,
+    days_waited: u32, // Summed over today's deliveries
+}
+
+#[derive(Default)]
+struct PersonRecord {
+    offers: u32,
+    doses: u32,
+    first_dose_day: Option<u32>,
+    last_dose_day: Option<u32>,
+    days_waited: u32, // From first falling due to delivery, over all doses
+}
+
+// Realized coverage of every intervention: what was offered and delivered each day, to whom, and how late
+#[derive(Resource, Default)]
+pub struct Coverage {
+    day: u32,
+    today: HashMap<Dose, DayCounts>,
+    waiting: HashMap<(Entity, Dose), u32>, // Day a dose first fell due, while stock-outs hold it back
+    people: BTreeMap<(HostId, &'static str), PersonRecord>,
+    finalized: bool,
+}
+
+impl Coverage {
+    fn record(&mut self, id: HostId, event: &DoseOffered, day: u32) {
+        let counts = self.today.entry(event.dose).or_default();
+        let person = self.people.entry((id, event.dose.label())).or_default();
+        counts.offered += 1;
+        person.offers += 1;
+        let key = (event.host, event.dose);
+        match event.outcome {
+            OfferOutcome::Delivered => {
+                let waited = self.waiting.remove(&key).map_or(0, |due| day - due);
+                counts.delivered += 1;
+                counts.days_waited += waited;
+                person.doses += 1;
+                person.first_dose_day.get_or_insert(day);
+                person.last_dose_day = Some(day);
+                person.days_waited += waited;
+            }
+            OfferOutcome::StockOut => {
+                counts.stock_outs += 1;
+                self.waiting.entry(key).or_insert(day);
+            }
+            OfferOutcome::Refused | OfferOutcome::Withheld => {
+                if event.outcome == OfferOutcome::Refused {
+                    counts.refused += 1;
+                } else {
+                    counts.withheld += 1;
+                }
+                self.waiting.remove(&key);
+            }
+        }
+    }
+
+    // One row per intervention for the day being closed, with the hosts covered so far
+    fn write_day(&mut self, writer: &mut impl Write, hosts: usize) -> std::io::Result<()> {
+        for dose in Dose::ALL {
+            let counts = self.today.remove(&dose).unwrap_or_default();
+            let covered = self.people.iter().filter(|((_, label), person)| *label == dose.label() && person.doses > 0).count();
+            let coverage = if hosts > 0 { covered as f32 / hosts as f32 } else { 0.0 };
+            let mean_delay = if counts.delivered > 0 { format!("{:.2}", counts.days_waited as f32 / counts.delivered as f32) } else { String::new() };
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{:.4},{}",
+                self.day,
+                dose.label(),
+                counts.offered,
+                counts.delivered,
+                counts.refused,
+                counts.stock_outs,
+                counts.withheld,
+                covered,
+                coverage,
+                mean_delay
+            )?;
+        }
+        writer.flush()
+    }
+
+    fn write_people(&self, output_dir: &OutputDir) -> std::io::Result<()> {
+        let mut csv = String::from("host,intervention,offers,doses,first_dose_day,last_dose_day,days_waited\n");
+        let day = |day: Option<u32>| day.map_or(String::new(), |day| day.to_string());
+        for ((id, label), person) in &self.people {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                id.0,
+                label,
+                person.offers,
+                person.doses,
+                day(person.first_dose_day),
+                day(person.last_dose_day),
+                person.days_waited
+            ));
+        }
+        fs::create_dir_all(&output_dir.0)?;
+        write_atomic(&output_dir.0.join("coverage_hosts.csv"), csv)
+    }
+
+    // Coverage in each patch of neighbouring hosts (the Groups panel's patches without venues), to show where an
+    // intervention fell short
+    fn write_patches(&self, output_dir: &OutputDir, hosts: &[HostId]) -> std::io::Result<()> {
+        let mut patches: BTreeMap<u32, Vec<HostId>> = BTreeMap::new();
+        for &id in hosts.iter().collect::<BTreeSet<_>>() {
+            patches.entry(id.patch()).or_default().push(id);
+        }
+        let mut csv = String::from("patch,intervention,hosts,persons_covered,coverage,offers,doses,mean_delay_days\n");
+        for members in patches.values() {
+            let (first, last) = (members[0], members[members.len() - 1]);
+            for dose in Dose::ALL {
+                let people: Vec<&PersonRecord> = members.iter().filter_map(|&id| self.people.get(&(id, dose.label()))).collect();
+                let covered = people.iter().filter(|person| person.doses > 0).count();
+                let doses: u32 = people.iter().map(|person| person.doses).sum();
+                let waited: u32 = people.iter().map(|person| person.days_waited).sum();
+                let mean_delay = if doses > 0 { format!("{:.2}", waited as f32 / doses as f32) } else { String::new() };
+                csv.push_str(&format!(
+                    "{}-{},{},{},{},{:.4},{},{},{}\n",
+                    first.0,
+                    last.0,
+                    dose.label(),
+                    members.len(),
+                    covered,
+                    covered as f32 / members.len() as f32,
+                    people.iter().map(|person| person.offers).sum::<u32>(),
+                    doses,
+                    mean_delay
+                ));
+            }
+        }
+        fs::create_dir_all(&output_dir.0)?;
+        write_atomic(&output_dir.0.join("coverage_patches.csv"), csv)
+    }
+}
+
+#[derive(Resource)]
+pub struct CoverageOutput(BufWriter<File>);
+
+pub fn setup_coverage_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("coverage_daily.csv"))?);
+        writeln!(writer, "day,intervention,offered,delivered,refused,stock_outs,withheld,persons_covered,coverage,mean_delay_days")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(CoverageOutput(writer)),
+        Err(err) => report.output_failed("coverage", err),
+    }
+}
+
+// Tallies offers as they happen and closes out each day once the next one starts; the day in progress and the
+// per-host and per-patch tables are written when the run report is
+#[allow(clippy::too_many_arguments)]
+pub fn record_coverage(
+    host_query: Query<&HostId>,
+    sim_time: Res<SimulationTime>,
+    output_dir: Res<OutputDir>,
+    mut coverage: ResMut<Coverage>,
+    mut report: ResMut<RunReport>,
+    mut ticks: EventReader<DayTick>,
+    mut offers: EventReader<DoseOffered>,
+    mut reset_events: EventReader<ResetSimulation>,
+    mut output: Option<ResMut<CoverageOutput>>,
+) {
+    if reset_events.read().count() > 0 {
+        // The daily table continues across a reset, but the old run's open day is dropped
+        *coverage = Coverage {
+            day: sim_time.day,
+            ..default()
+        };
+        ticks.clear();
+        offers.clear();
+        return;
+    }
+
+    if coverage.finalized {
+        return;
+    }
+    let hosts = host_query.iter().count();
+    let mut close_day = |coverage: &mut Coverage, report: &mut RunReport| {
+        if let Some(output) = output.as_mut() {
+            if let Err(err) = coverage.write_day(&mut output.0, hosts) {
+                report.output_failed("coverage", err);
+            }
+        }
+    };
+    // Every day announced gets its row, even one that starts and ends within a frame
+    for tick in ticks.read() {
+        if tick.day != coverage.day {
+            close_day(&mut coverage, &mut report);
+            coverage.day = tick.day;
+        }
+    }
+
+    let day = coverage.day;
+    for event in offers.read() {
+        if let Ok(&id) = host_query.get(event.host) {
+            coverage.record(id, event, day);
+        }
+    }
+
+    if report.written() {
+        close_day(&mut coverage, &mut report);
+        coverage.finalized = true;
+        if let Err(err) = coverage.write_people(&output_dir) {
+            report.output_failed("coverage by host", err);
+        }
+        let hosts: Vec<HostId> = host_query.iter().copied().collect();
+        if let Err(err) = coverage.write_patches(&output_dir, &hosts) {
+            report.output_failed("coverage by patch", err);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::distributions::Distribution;
+use rand::Rng;
+use rand_distr::Binomial;
+
+use crate::environment::Suitability;
+use crate::portable_math::{self, PortableFloat};
+use crate::rng::Subsystem;
+#[cfg(feature = "gui")]
+use crate::sandbox::Sandbox;
+use crate::vectors::{VectorPopulation, Vectors};
+use crate::venues::VenueExposure;
+use crate::{Host, Params, SimRng, SimulationTime};
+
+// How many people each rendered host stands for: the host itself plus a crowd simulated in aggregate
+#[derive(Resource)]
+pub struct Representation {
+    pub people_per_host: u32,
+}
+
+// The other people_per_host - 1 people behind a host, in the ODE's compartments: S, E, treated and untreated A, C, P
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Crowd {
+    pub counts: [u32; 6],
+    day: u32,
+}
+
+impl Crowd {
+    // Counts in HostState::ALL order
+    pub fn state_counts(&self) -> [usize; 5] {
+        let [s, e, a_treated, a_untreated, c, p] = self.counts.map(|count| count as usize);
+        [s, e, a_treated + a_untreated, c, p]
+    }
+}
+
+// Like the hosts themselves, every crowd starts with a liver-stage infection
+pub fn spawn_crowds(mut commands: Commands, representation: Res<Representation>, host_query: Query<Entity, Added<Host>>) {
+    if representation.people_per_host <= 1 {
+        return;
+    }
+    for entity in host_query.iter() {
+        commands.entity(entity).insert(Crowd {
+            counts: [0, representation.people_per_host - 1, 0, 0, 0, 0],
+            day: 0,
+        });
+    }
+}
+
+fn draw(rng: &mut impl Rng, n: u32, probability: f64) -> u32 {
+    if n == 0 || probability <= 0.0 {
+        return 0;
+    }
+    if portable_math::enabled() {
+        return portable_math::binomial(rng, n, probability);
+    }
+    Binomial::new(n as u64, probability.min(1.0)).map_or(0, |binomial| binomial.sample(rng) as u32)
+}
+
+// Chance of leaving a state within a day at a constant rate
+fn daily(rate: f64) -> f64 {
+    1.0 - (-rate).portable_exp()
+}
+
+// Chain-binomial day step for each crowd, exposed to the same local force of infection as its host. Crowds are
+// infected but, like the ODE, don't carry immunity or add to the infectious reservoir.
+#[cfg(feature = "gui")]
+pub fn step_crowds(
+    mut crowd_query: Query<(&mut Crowd, Option<&Suitability>, Option<&VenueExposure>)>,
+    params: Res<Params>,
+    vectors: Res<Vectors>,
+    vector_population: Res<VectorPopulation>,
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    sandbox: Res<Sandbox>,
+) {
+    let incidence_rate = vectors.incidence_rate(&params, &vector_population) as f64;
+    let liver = daily(1.0 / params.duration_liver as f64);
+    let treatment = daily(1.0 / (params.treatment_delay.mean() as f64).max(0.1));
+    let acute = daily(1.0 / params.duration_acute.mean() as f64);
+    let chronic = daily(1.0 / params.duration_chronic.mean() as f64);
+    let prophylaxis = daily(1.0 / (params.treatment_drug.duration_prophylaxis as f64).max(0.1));
+
+    for (mut crowd, suitability, venue_exposure) in crowd_query.iter_mut() {
+        while crowd.day < sim_time.day {
+            crowd.day += 1;
+            let [s, e, a_treated, a_untreated, c, p] = crowd.counts;
+            let local_rate = incidence_rate * suitability.map_or(1.0, |suitability| suitability.0) as f64
+                + venue_exposure.map_or(0.0, |exposure| exposure.0) as f64;
+            let infected = if sandbox.transmission_off { 0 } else { draw(&mut sim_rng.stream(Subsystem::Infection), s, daily(local_rate)) };
+
+            let rng = &mut sim_rng.stream(Subsystem::Progression);
+            let liver_exit = draw(rng, e, liver);
+            let to_acute = draw(rng, liver_exit, params.prob_acute as f64);
+            let to_treated = draw(rng, to_acute, params.prob_treatment as f64);
+            let treated = draw(rng, a_treated, treatment);
+            let acute_exit = draw(rng, a_untreated, acute);
+            let to_chronic = draw(rng, acute_exit, params.prob_ac as f64);
+            let chronic_exit = draw(rng, c, chronic);
+            let prophylaxis_exit = draw(rng, p, prophylaxis);
+
+            crowd.counts = [
+                s - infected + (acute_exit - to_chronic) + chronic_exit + prophylaxis_exit,
+                e + infected - liver_exit,
+                a_treated + to_treated - treated,
+                a_untreated + (to_acute - to_treated) - acute_exit,
+                c + (liver_exit - to_acute) + to_chronic - chronic_exit,
+                p + treated - prophylaxis_exit,
+            ];
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::ids::HostId;
+use crate::occupations::Occupation;
+use crate::report::RunReport;
+use crate::{Host, Inoculation, OutputDir, ResetSimulation, SimulationTime};
+
+// Long-format host × day records for individual-level analyses, one row per host at the end of each day
+#[derive(Resource)]
+pub struct ExposureDiary {
+    writer: BufWriter<File>,
+    day: u32,                              // Day whose rows are still open
+    exposures: HashMap<(u32, Entity), u32>, // New inoculations by (day, host)
+}
+
+pub fn setup_exposure_diary(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("exposure_diary.csv"))?);
+        writeln!(writer, "day,host,sex,occupation,state,moi,exposures,offered,accepted,comorbidity")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(ExposureDiary {
+            writer,
+            day: 0,
+            exposures: HashMap::new(),
+        }),
+        Err(err) => report.output_failed("exposure diary", err),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_exposure_diary(
+    host_query: Query<(Entity, &HostId, &Host, Option<&Children>)>,
+    occupation_query: Query<&Occupation>,
+    new_inocs: Query<(&Parent, &Inoculation), Added<Inoculation>>,
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut reset_events: EventReader<ResetSimulation>,
+    mut diary: ResMut<ExposureDiary>,
+    mut report: ResMut<RunReport>,
+) {
+    if reset_events.read().count() > 0 {
+        // The diary continues across a reset, but the old run's open day is dropped
+        diary.day = sim_time.day;
+        diary.exposures.clear();
+        return;
+    }
+
+    for (parent, inoc) in new_inocs.iter() {
+        *diary.exposures.entry((inoc.start_day, parent.get())).or_default() += 1;
+    }
+    if sim_time.day == diary.day {
+        return;
+    }
+
+    // Close out the previous day with each host's state as it now stands
+    let day = diary.day;
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort_by_key(|(_, id, _, _)| **id);
+    let mut result = Ok(());
+    for (entity, id, host, children) in hosts {
+        let exposures = diary.exposures.remove(&(day, entity)).unwrap_or(0);
+        let (offered, accepted) = match host.last_offer {
+            Some((offer_day, regimen, accepted)) if offer_day == day => (regimen.label(), accepted.to_string()),
+            _ => ("", String::new()),
+        };
+        result = result.and_then(|_| {
+            writeln!(
+                diary.writer,
+                "{},{},{},{},{:?},{},{},{},{},{}",
+                day,
+                id.0,
+                host.sex.label(),
+                occupation_query.get(entity).map_or("", |occupation| occupation.name.as_str()),
+                host.state(children, &inoc_query),
+                children.map_or(0, |c| c.len()),
+                exposures,
+                offered,
+                accepted,
+                host.comorbidity
+            )
+        });
+    }
+    if let Err(err) = result.and_then(|_| diary.writer.flush()) {
+        report.output_failed("exposure diary", err);
+    }
+    diary.exposures.retain(|(exposure_day, _), _| *exposure_day > day);
+    diary.day = sim_time.day;
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+#[cfg(feature = "gui")]
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::portable_math::{self, PortableFloat};
+use crate::Params;
+
+const PREVIEW_SAMPLES: usize = 2000;
+const PREVIEW_BINS: usize = 40;
+
+type DistField = fn(&mut Params) -> &mut DurationDist;
+
+// Duration in days, e.g. `{ family = "gamma", mean = 25.0, cv = 0.5 }`; cv is the coefficient of variation
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+#[serde(tag = "family", rename_all = "lowercase", deny_unknown_fields)]
+pub enum DurationDist {
+    Uniform { low: f32, high: f32 },
+    Exponential { mean: f32 },
+    Gamma { mean: f32, cv: f32 },
+    LogNormal { mean: f32, cv: f32 },
+}
+
+impl DurationDist {
+    pub fn uniform(low: f32, high: f32) -> Self {
+        DurationDist::Uniform { low, high }
+    }
+
+    pub fn mean(&self) -> f32 {
+        match *self {
+            DurationDist::Uniform { low, high } => (low + high) / 2.0,
+            DurationDist::Exponential { mean } | DurationDist::Gamma { mean, .. } | DurationDist::LogNormal { mean, .. } => mean,
+        }
+    }
+
+    // Why the parameters can't describe a duration, e.g. a negative mean
+    pub fn problem(&self) -> Option<&'static str> {
+        match *self {
+            DurationDist::Uniform { low, high } if !(low.is_finite() && high.is_finite()) => Some("low and high must be finite"),
+            DurationDist::Uniform { low, high } if low < 0.0 || high < low => Some("needs 0 <= low <= high"),
+            DurationDist::Exponential { mean } | DurationDist::Gamma { mean, .. } | DurationDist::LogNormal { mean, .. }
+                if !(mean.is_finite() && mean >= 0.0) =>
+            {
+                Some("mean must be at least 0")
+            }
+            DurationDist::Gamma { cv, .. } | DurationDist::LogNormal { cv, .. } if !(cv.is_finite() && cv >= 0.0) => {
+                Some("cv must be at least 0")
+            }
+            _ => None,
+        }
+    }
+
+    fn family(&self) -> &'static str {
+        match self {
+            DurationDist::Uniform { .. } => "Uniform",
+            DurationDist::Exponential { .. } => "Exponential",
+            DurationDist::Gamma { .. } => "Gamma",
+            DurationDist::LogNormal { .. } => "Log-normal",
+        }
+    }
+
+    // Same mean in another family, with a moderate spread where the family has one
+    fn with_family(&self, family: &str) -> Self {
+        let mean = self.mean();
+        match family {
+            "Uniform" => DurationDist::uniform(mean / 2.0, mean * 1.5),
+            "Exponential" => DurationDist::Exponential { mean },
+            "Gamma" => DurationDist::Gamma { mean, cv: 0.5 },
+            _ => DurationDist::LogNormal { mean, cv: 0.5 },
+        }
+    }
+}
+
+impl Distribution<f32> for DurationDist {
+    // Degenerate parameters give the mean rather than panicking
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        if portable_math::enabled() {
+            return self.sample_portable(rng);
+        }
+        match *self {
+            DurationDist::Uniform { low, high } if high > low => rng.gen_range(low..high),
+            DurationDist::Exponential { mean } if mean > 0.0 => rand_distr::Exp::new(1.0 / mean).map_or(mean, |exp| exp.sample(rng)),
+            DurationDist::Gamma { mean, cv } if mean > 0.0 && cv > 0.0 => {
+                let shape = 1.0 / (cv * cv);
+                rand_distr::Gamma::new(shape, mean / shape).map_or(mean, |gamma| gamma.sample(rng))
+            }
+            DurationDist::LogNormal { mean, cv } if mean > 0.0 && cv > 0.0 => {
+                let sigma_squared = (1.0 + cv * cv).ln();
+                rand_distr::LogNormal::new(mean.ln() - sigma_squared / 2.0, sigma_squared.sqrt())
+                    .map_or(mean, |lognormal| lognormal.sample(rng))
+            }
+            _ => self.mean(),
+        }
+    }
+}
+
+impl DurationDist {
+    fn sample_portable<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        match *self {
+            DurationDist::Uniform { low, high } if high > low => rng.gen_range(low..high),
+            DurationDist::Exponential { mean } if mean > 0.0 => portable_math::exponential(rng, mean as f64) as f32,
+            DurationDist::Gamma { mean, cv } if mean > 0.0 && cv > 0.0 => {
+                let shape = 1.0 / (cv as f64 * cv as f64);
+                portable_math::gamma(rng, shape, mean as f64 / shape) as f32
+            }
+            DurationDist::LogNormal { mean, cv } if mean > 0.0 && cv > 0.0 => {
+                let sigma_squared = (1.0 + cv as f64 * cv as f64).portable_ln();
+                portable_math::log_normal(rng, (mean as f64).portable_ln() - sigma_squared / 2.0, sigma_squared.sqrt()) as f32
+            }
+            _ => self.mean(),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+fn days(value: &mut f32) -> egui::DragValue<'_> {
+    egui::DragValue::new(value).speed(0.5).clamp_range(0.0..=1000.0).suffix(" d")
+}
+
+// Family picker, parameter fields and a density preview from a fixed set of draws; returns whether it changed
+#[cfg(feature = "gui")]
+fn distribution_editor(ui: &mut egui::Ui, id: &str, dist: &mut DurationDist) -> bool {
+    let before = *dist;
+    ui.horizontal(|ui| {
+        let mut family = dist.family();
+        egui::ComboBox::from_id_source(id).selected_text(family).show_ui(ui, |ui| {
+            for option in ["Uniform", "Exponential", "Gamma", "Log-normal"] {
+                ui.selectable_value(&mut family, option, option);
+            }
+        });
+        if family != dist.family() {
+            *dist = dist.with_family(family);
+        }
+
+        match dist {
+            DurationDist::Uniform { low, high } => {
+                ui.add(days(low).prefix("low "));
+                ui.add(days(high).prefix("high "));
+                *high = high.max(*low);
+            }
+            DurationDist::Exponential { mean } => {
+                ui.add(days(mean).prefix("mean "));
+            }
+            DurationDist::Gamma { mean, cv } | DurationDist::LogNormal { mean, cv } => {
+                ui.add(days(mean).prefix("mean "));
+                ui.add(egui::DragValue::new(cv).speed(0.01).clamp_range(0.05..=3.0).prefix("cv "));
+            }
+        }
+    });
+
+    // Histogram density of draws from a fixed seed, so the preview only moves when the parameters do
+    let rng = &mut StdRng::seed_from_u64(0);
+    let mut samples: Vec<f32> = (0..PREVIEW_SAMPLES).map(|_| dist.sample(rng)).collect();
+    samples.sort_by(f32::total_cmp);
+    let upper = samples[PREVIEW_SAMPLES * 99 / 100].max(1.0); // Clip the long tail
+    let width = upper / PREVIEW_BINS as f32;
+    let mut counts = [0usize; PREVIEW_BINS];
+    for sample in samples.iter().filter(|sample| **sample <= upper) {
+        counts[((sample / width) as usize).min(PREVIEW_BINS - 1)] += 1;
+    }
+    let points: PlotPoints = counts
+        .iter()
+        .enumerate()
+        .map(|(bin, count)| [((bin as f32 + 0.5) * width) as f64, (*count as f32 / (PREVIEW_SAMPLES as f32 * width)) as f64])
+        .collect();
+    Plot::new(id).height(80.0).include_y(0.0).allow_drag(false).allow_zoom(false).allow_scroll(false).show(ui, |plot_ui| {
+        plot_ui.line(Line::new(points).fill(0.0));
+        plot_ui.vline(VLine::new(dist.mean() as f64).name("Mean"));
+    });
+
+    *dist != before
+}
+
+#[cfg(feature = "gui")]
+pub fn distributions_ui(mut contexts: EguiContexts, mut params: ResMut<Params>) {
+    egui::Window::new("Distributions")
+        .default_pos(egui::pos2(250.0, 50.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let editors: [(&str, DistField); 3] = [
+                ("Acute duration", |params| &mut params.duration_acute),
+                ("Chronic duration", |params| &mut params.duration_chronic),
+                ("Treatment delay", |params| &mut params.treatment_delay),
+            ];
+            for (label, field) in editors {
+                ui.label(label);
+                // Edit a copy so Params is only marked changed on an actual edit
+                let mut dist = *field(params.bypass_change_detection());
+                if distribution_editor(ui, label, &mut dist) {
+                    *field(&mut params) = dist;
+                }
+            }
+        });
+}
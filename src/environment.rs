@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy::render::render_asset::RenderAssetUsages;
+#[cfg(feature = "gui")]
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+#[cfg(feature = "gui")]
+use bevy::render::texture::ImageSampler;
+use bevy::window::PrimaryWindow;
+use std::fs;
+use std::path::Path;
+
+use crate::Host;
+
+// Grid of suitability values covering the window, top row first, scaled to a mean of 1 so overall incidence is unchanged
+#[derive(Resource)]
+pub struct Environment {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+// Multiplier on a host's exposure from the raster cell it stands in
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Suitability(pub f32);
+
+impl Environment {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let is_csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let (width, height, values) = if is_csv { Self::read_csv(path) } else { Self::read_image(path) }
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        if values.iter().any(|value| !value.is_finite() || *value < 0.0) || mean <= 0.0 {
+            return Err(format!("{}: suitability must be non-negative and not all zero", path.display()));
+        }
+        Ok(Self {
+            width,
+            height,
+            values: values.iter().map(|value| value / mean).collect(),
+        })
+    }
+
+    fn read_csv(path: &Path) -> Result<(usize, usize, Vec<f32>), String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let rows: Vec<Vec<f32>> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(line_number, line)| {
+                line.split(',')
+                    .map(|field| field.trim().parse::<f32>().map_err(|err| format!("line {}: {}", line_number + 1, err)))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let width = rows.first().map_or(0, |row| row.len());
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err("expected a non-empty grid with the same number of values on every line".to_owned());
+        }
+        Ok((width, rows.len(), rows.concat()))
+    }
+
+    // Brightness of each pixel, 0 to 1
+    fn read_image(path: &Path) -> Result<(usize, usize, Vec<f32>), String> {
+        let image = image::open(path).map_err(|err| err.to_string())?.into_luma8();
+        if image.width() == 0 || image.height() == 0 {
+            return Err("expected a non-empty image".to_owned());
+        }
+        let values = image.pixels().map(|pixel| pixel.0[0] as f32 / 255.0).collect();
+        Ok((image.width() as usize, image.height() as usize, values))
+    }
+
+    // Value at a world position, with the raster stretched over a window of the given size
+    pub fn at(&self, position: Vec2, window_size: Vec2) -> f32 {
+        let u = (position.x / window_size.x + 0.5).clamp(0.0, 1.0);
+        let v = (0.5 - position.y / window_size.y).clamp(0.0, 1.0);
+        let column = ((u * self.width as f32) as usize).min(self.width - 1);
+        let row = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.values[row * self.width + column]
+    }
+
+    // Dark blue where unsuitable up to dark amber at the peak, muted so host sprites stand out
+    #[cfg(feature = "gui")]
+    fn heat_image(&self) -> Image {
+        let peak = self.values.iter().cloned().fold(0.0, f32::max);
+        let data = self
+            .values
+            .iter()
+            .flat_map(|value| {
+                let t = value / peak;
+                [(15.0 + 95.0 * t) as u8, (25.0 + 45.0 * t) as u8, (45.0 - 30.0 * t) as u8, 255]
+            })
+            .collect();
+        let size = Extent3d {
+            width: self.width as u32,
+            height: self.height as u32,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image::new(size, TextureDimension::D2, data, TextureFormat::Rgba8UnormSrgb, RenderAssetUsages::default());
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+}
+
+#[derive(Component)]
+pub struct EnvironmentLayer;
+
+#[cfg(feature = "gui")]
+pub fn spawn_environment_layer(
+    mut commands: Commands,
+    environment: Res<Environment>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    commands.spawn((
+        EnvironmentLayer,
+        SpriteBundle {
+            texture: images.add(environment.heat_image()),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(window.width(), window.height())),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, -1.0), // Behind the hosts
+            ..default()
+        },
+    ));
+}
+
+pub fn assign_suitability(
+    mut commands: Commands,
+    environment: Res<Environment>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    host_query: Query<(Entity, &Transform), Added<Host>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    for (entity, transform) in host_query.iter() {
+        let suitability = environment.at(transform.translation.truncate(), window_size);
+        commands.entity(entity).insert(Suitability(suitability));
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::OutputDir;
+
+// Notable things that happened during the run, e.g. parameter changes, by simulation day
+#[derive(Resource, Default)]
+pub struct EventLog {
+    pub entries: Vec<(u32, String)>,
+    written: usize, // Entries already flushed to events.csv
+}
+
+impl EventLog {
+    pub fn log(&mut self, day: u32, message: impl Into<String>) {
+        let message = message.into();
+        info!("Day {}: {}", day, message);
+        self.entries.push((day, message));
+    }
+}
+
+#[derive(Resource)]
+pub struct EventLogOutput(BufWriter<File>);
+
+pub fn setup_event_log_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("events.csv"))?);
+        writeln!(writer, "day,event")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(EventLogOutput(writer)),
+        Err(err) => report.output_failed("event log", err),
+    }
+}
+
+pub fn write_event_log(mut log: ResMut<EventLog>, output: Option<ResMut<EventLogOutput>>, mut report: ResMut<RunReport>) {
+    let Some(mut output) = output else {
+        return;
+    };
+    if log.written >= log.entries.len() {
+        return;
+    }
+
+    let mut result = Ok(());
+    for (day, message) in &log.entries[log.written..] {
+        // Quote the message so commas survive
+        result = result.and_then(|_| writeln!(output.0, "{},\"{}\"", day, message.replace('"', "\"\"")));
+    }
+    if let Err(err) = result.and_then(|_| output.0.flush()) {
+        report.output_failed("event log", err);
+    }
+    log.written = log.entries.len();
+}
+
+#[cfg(feature = "gui")]
+pub fn event_log_ui(mut contexts: EguiContexts, log: Res<EventLog>, analytics: Res<AnalyticsWindow>) {
+    if log.entries.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Event Log")
+        .default_pos(egui::pos2(850.0, 400.0))
+        .default_size(egui::vec2(300.0, 150.0))
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                for (day, message) in &log.entries {
+                    ui.label(format!("Day {}: {}", day, message));
+                }
+            });
+        });
+}
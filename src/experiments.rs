@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::gis::HostLocations;
+use crate::mda::MdaSchedule;
+use crate::scenario::{MdaConfig, Preset};
+use crate::snapshot::{preset_snapshot, Snapshot, WarmStart};
+use crate::{Params, ResetSimulation};
+
+// Presets selectable from the controls; choosing one restarts the run with its params and MDA schedule
+#[derive(Resource)]
+pub struct Experiments {
+    presets: Vec<Preset>,
+    current: Option<usize>, // None until a preset is chosen, i.e. the scenario as loaded
+    base_params: Params,
+    base_mda: Option<MdaConfig>,
+    base_snapshot: Option<Snapshot>,
+}
+
+impl Experiments {
+    pub fn new(presets: Vec<Preset>, base_params: Params, base_mda: Option<MdaConfig>, base_snapshot: Option<Snapshot>) -> Self {
+        Self {
+            presets,
+            current: None,
+            base_params,
+            base_mda,
+            base_snapshot,
+        }
+    }
+}
+
+// Params and MDA schedule for a preset layered over the scenario
+pub fn preset_settings(preset: Option<&Preset>, base_params: &Params, base_mda: &Option<MdaConfig>) -> (Params, Option<MdaConfig>) {
+    let mut params = base_params.clone();
+    let mut mda = base_mda.clone();
+    if let Some(preset) = preset {
+        preset.params.apply(&mut params);
+        if preset.mda.is_some() {
+            mda = preset.mda.clone();
+        }
+    }
+    (params, mda)
+}
+
+pub fn experiment_ui(
+    mut contexts: EguiContexts,
+    mut experiments: ResMut<Experiments>,
+    mut params: ResMut<Params>,
+    mut schedule: ResMut<MdaSchedule>,
+    mut warm_start: ResMut<WarmStart>,
+    locations: Option<Res<HostLocations>>,
+    mut reset_events: EventWriter<ResetSimulation>,
+) {
+    let mut selected = experiments.current;
+    egui::Window::new("Experiments")
+        .default_pos(egui::pos2(300.0, 10.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let current_name = selected
+                .and_then(|index| experiments.presets.get(index))
+                .map_or("Scenario", |preset| preset.name.as_str());
+            egui::ComboBox::from_id_source("experiment")
+                .selected_text(current_name)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "Scenario");
+                    for (index, preset) in experiments.presets.iter().enumerate() {
+                        ui.selectable_value(&mut selected, Some(index), &preset.name);
+                    }
+                });
+
+            if let Some(config) = &schedule.0 {
+                ui.label(format!(
+                    "MDA: {} rounds every {} days from day {}, {:.0}% coverage",
+                    config.rounds,
+                    config.interval_days,
+                    config.start_day,
+                    config.coverage * 100.0
+                ));
+            }
+            if let Some(snapshot) = &warm_start.0 {
+                ui.label(format!("Starts from {} hosts as of day {} of an earlier run", snapshot.hosts.len(), snapshot.day));
+            }
+        });
+
+    if selected != experiments.current {
+        experiments.current = selected;
+        let preset = selected.and_then(|index| experiments.presets.get(index));
+        (*params, schedule.0) = preset_settings(preset, &experiments.base_params, &experiments.base_mda);
+        warm_start.0 = preset_snapshot(preset, &experiments.base_snapshot, locations.as_deref());
+        reset_events.send(ResetSimulation);
+    }
+}
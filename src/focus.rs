@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+use crate::cleared::InfectionHistory;
+use crate::crowds::Crowd;
+use crate::ids::HostId;
+use crate::treatment::TreatmentHistory;
+use crate::{Host, HostState, MainCamera, Params, SimulationTime};
+
+const HOST_HALF_WIDTH: f32 = 25.0;
+const DIMMED_ALPHA: f32 = 0.25;
+
+// Host highlighted for teaching, with its own daily history
+#[derive(Resource, Default)]
+pub struct FocalHost {
+    pub entity: Option<Entity>,
+    history: Vec<(u32, usize, usize, f32)>, // (day, inoculations, genotypes remembered, antibody titre)
+}
+
+impl FocalHost {
+    pub fn select(&mut self, entity: Option<Entity>) {
+        if self.entity != entity {
+            self.entity = entity;
+            self.history.clear();
+        }
+    }
+}
+
+// Left click selects the host whose column is under the cursor, Escape clears the selection
+pub fn select_focal_host(
+    mut contexts: EguiContexts,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    host_query: Query<(Entity, &GlobalTransform), With<Host>>,
+    mut focal: ResMut<FocalHost>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        focal.select(None);
+    }
+
+    if !mouse.just_pressed(MouseButton::Left) || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    let Some(cursor) = window_query.get_single().ok().and_then(|window| window.cursor_position()) else {
+        return;
+    };
+    focal.select(pick_host(cursor, &camera_query, &host_query));
+}
+
+// Host under a point in window coordinates; hosts are bars with their inoculations stacked above, so match on the column
+pub fn pick_host(
+    position: Vec2,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    host_query: &Query<(Entity, &GlobalTransform), With<Host>>,
+) -> Option<Entity> {
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    let point = camera.viewport_to_world_2d(camera_transform, position)?;
+
+    host_query
+        .iter()
+        .map(|(entity, transform)| (entity, (transform.translation().x - point.x).abs(), transform.translation().y))
+        .filter(|(_, dx, y)| *dx <= HOST_HALF_WIDTH && point.y >= y - HOST_HALF_WIDTH)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _, _)| entity)
+}
+
+pub fn record_focal_history(
+    host_query: Query<(&Host, Option<&Children>)>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut focal: ResMut<FocalHost>,
+) {
+    let Some(entity) = focal.entity else {
+        return;
+    };
+    let Ok((host, children)) = host_query.get(entity) else {
+        focal.select(None); // Host no longer exists
+        return;
+    };
+
+    if focal.history.last().is_some_and(|(day, ..)| *day == sim_time.day) {
+        return;
+    }
+    let inoculations = children.map_or(0, |c| c.len());
+    focal.history.push((sim_time.day, inoculations, host.immune_memory.len(), host.antibody_titre(sim_time.day, &params)));
+}
+
+// Runs after the color systems so dimming is applied on top of state colors
+pub fn dim_unfocused(
+    mut host_query: Query<(Entity, &mut Sprite), With<Host>>,
+    mut child_query: Query<(&Parent, &mut Sprite), Without<Host>>, // Inoculations and other host decorations
+    focal: Res<FocalHost>,
+) {
+    let Some(focal_entity) = focal.entity else {
+        return;
+    };
+
+    for (entity, mut sprite) in host_query.iter_mut() {
+        if entity != focal_entity {
+            sprite.color.set_a(DIMMED_ALPHA);
+        }
+    }
+    for (parent, mut sprite) in child_query.iter_mut() {
+        if parent.get() != focal_entity {
+            let alpha = sprite.color.a() * DIMMED_ALPHA;
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+pub fn focal_host_ui(
+    mut contexts: EguiContexts,
+    mut focal: ResMut<FocalHost>,
+    id_query: Query<&HostId>,
+    host_query: Query<&Host>,
+    crowd_query: Query<&Crowd>,
+    history_query: Query<&TreatmentHistory>,
+    infection_query: Query<&InfectionHistory>,
+) {
+    let Some(entity) = focal.entity else {
+        return;
+    };
+
+    let mut clear = false;
+    egui::Window::new("Focal Host")
+        .default_pos(egui::pos2(450.0, 50.0))
+        .default_size(egui::vec2(300.0, 150.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(id_query.get(entity).map_or(format!("Host {:?}", entity), |id| format!("Host {}", id.0)));
+                clear = ui.button("Clear").clicked();
+            });
+            if let Ok(host) = host_query.get(entity) {
+                let comorbidity = if host.comorbidity > 0.0 { format!(", comorbidity score {:.2}", host.comorbidity) } else { String::new() };
+                ui.label(format!("{}{}", host.sex.label(), comorbidity));
+            }
+            if let Ok(crowd) = crowd_query.get(entity) {
+                let counts: Vec<String> = HostState::ALL
+                    .iter()
+                    .zip(crowd.state_counts())
+                    .map(|(state, count)| format!("{:?} {}", state, count))
+                    .collect();
+                ui.label(format!("Crowd of {}: {}", crowd.counts.iter().sum::<u32>(), counts.join(", ")));
+            }
+            if let Ok(history) = history_query.get(entity) {
+                if let Some((day, drug)) = history.last_course {
+                    let failures = history.failures;
+                    ui.label(format!("{} treatment courses ({} failed), last {:?} on day {}", history.courses, failures, drug, day));
+                }
+            }
+            if let Some(past) = infection_query.get(entity).ok().filter(|history| history.count > 0) {
+                ui.label(format!("{} past inoculations, lasting {:.0} days on average", past.count, past.mean_days()));
+            }
+
+            let inoculations: PlotPoints = focal.history.iter().map(|(day, n, ..)| [*day as f64, *n as f64]).collect();
+            let immunity: PlotPoints = focal.history.iter().map(|(day, _, n, _)| [*day as f64, *n as f64]).collect();
+            let antibody: PlotPoints = focal.history.iter().map(|(day, _, _, titre)| [*day as f64, *titre as f64]).collect();
+            Plot::new("focal_host")
+                .legend(Legend::default())
+                .include_y(0.0)
+                .height(120.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(inoculations).name("Inoculations"));
+                    plot_ui.line(Line::new(immunity).name("Genotypes remembered"));
+                    plot_ui.line(Line::new(antibody).name("Antibody titre"));
+                });
+        });
+
+    if clear {
+        focal.select(None);
+    }
+}
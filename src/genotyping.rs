@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::clock::DayTick;
+use crate::report::RunReport;
+use crate::rng::Subsystem;
+use crate::{Genotype, Host, HostState, InfectionState, Inoculation, OutputDir, Params, SimRng};
+
+// Monthly molecular surveillance of detected (acute) infections
+#[derive(Resource)]
+pub struct GenotypingPanel {
+    pub interval_days: u32,
+    pub sample_size: usize,
+    pub detection_limit: f32,          // Minimum relative density for a clone to be called
+    pub chronic_relative_density: f32, // Parasite density of a chronic clone relative to an acute one
+}
+
+impl Default for GenotypingPanel {
+    fn default() -> Self {
+        Self {
+            interval_days: 30,
+            sample_size: 5,
+            detection_limit: 0.2,
+            chronic_relative_density: 0.1,
+        }
+    }
+}
+
+// Observed and true results are kept in separate files for method-validation studies
+#[derive(Resource)]
+pub struct GenotypingOutputs {
+    observed_samples: BufWriter<File>,
+    true_samples: BufWriter<File>,
+    observed_frequencies: BufWriter<File>,
+    true_frequencies: BufWriter<File>,
+}
+
+fn create_csv(output_dir: &OutputDir, name: &str, header: &str) -> std::io::Result<BufWriter<File>> {
+    let mut writer = BufWriter::new(File::create(output_dir.0.join(name))?);
+    writeln!(writer, "{}", header)?;
+    Ok(writer)
+}
+
+pub fn setup_genotyping_outputs(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let outputs = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        Ok(GenotypingOutputs {
+            observed_samples: create_csv(&output_dir, "genotyping_observed.csv", "day,sample,moi,genotypes")?,
+            true_samples: create_csv(&output_dir, "genotyping_true.csv", "day,sample,moi,genotypes")?,
+            observed_frequencies: create_csv(&output_dir, "genotype_frequencies_observed.csv", "day,genotype,count,frequency")?,
+            true_frequencies: create_csv(&output_dir, "genotype_frequencies_true.csv", "day,genotype,count,frequency")?,
+        })
+    });
+
+    match outputs {
+        Ok(outputs) => commands.insert_resource(outputs),
+        Err(err) => report.output_failed("genotyping outputs", err),
+    }
+}
+
+// Blood-stage clones in a host with their parasite density relative to an acute clone
+fn blood_stage_clones(children: &Children, inoc_query: &Query<&Inoculation>, panel: &GenotypingPanel) -> Vec<(Genotype, f32)> {
+    children
+        .iter()
+        .filter_map(|&child| inoc_query.get(child).ok())
+        .filter_map(|inoc| match inoc.state {
+            InfectionState::A => Some((inoc.genotype, 1.0)),
+            InfectionState::C => Some((inoc.genotype, panel.chronic_relative_density)),
+            InfectionState::E => None, // Liver stage is not detectable in blood
+        })
+        .collect()
+}
+
+// Minority clones below the detection limit are missed, and identical genotypes are indistinguishable
+fn observed_genotypes(clones: &[(Genotype, f32)], detection_limit: f32) -> Vec<Genotype> {
+    let total_density: f32 = clones.iter().map(|(_, density)| density).sum();
+    let mut genotypes: Vec<Genotype> = clones
+        .iter()
+        .filter(|(_, density)| density / total_density >= detection_limit)
+        .map(|(genotype, _)| *genotype)
+        .collect();
+    genotypes.sort();
+    genotypes.dedup();
+    genotypes
+}
+
+fn write_samples(writer: &mut BufWriter<File>, day: u32, samples: &[Vec<Genotype>], n_loci: u32) -> std::io::Result<()> {
+    for (index, genotypes) in samples.iter().enumerate() {
+        let labels: Vec<String> = genotypes.iter().map(|g| g.label(n_loci)).collect();
+        writeln!(writer, "{},{},{},{}", day, index, genotypes.len(), labels.join(";"))?;
+    }
+    writer.flush()
+}
+
+fn write_frequencies(writer: &mut BufWriter<File>, day: u32, samples: &[Vec<Genotype>], n_loci: u32) -> std::io::Result<()> {
+    let mut counts: BTreeMap<Genotype, usize> = BTreeMap::new();
+    for genotype in samples.iter().flatten() {
+        *counts.entry(*genotype).or_default() += 1;
+    }
+
+    let total: usize = counts.values().sum();
+    for (genotype, count) in counts {
+        writeln!(writer, "{},{},{},{:.4}", day, genotype.label(n_loci), count, count as f32 / total as f32)?;
+    }
+    writer.flush()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_genotyping_survey(
+    host_query: Query<(&Host, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    params: Res<Params>,
+    mut ticks: EventReader<DayTick>,
+    panel: Res<GenotypingPanel>,
+    outputs: Option<ResMut<GenotypingOutputs>>,
+    mut sim_rng: ResMut<SimRng>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(mut outputs) = outputs else {
+        return;
+    };
+
+    let Some(day) = ticks.read().last().map(|tick| tick.day) else {
+        return;
+    };
+    if day == 0 || !day.is_multiple_of(panel.interval_days) {
+        return;
+    }
+
+    // Detected infections are hosts presenting with acute disease
+    let detected: Vec<&Children> = host_query
+        .iter()
+        .filter(|(host, children)| host.state(*children, &inoc_query) == HostState::A)
+        .filter_map(|(_, children)| children)
+        .collect();
+
+    let sampled = detected.choose_multiple(&mut sim_rng.stream(Subsystem::Genotyping), panel.sample_size);
+
+    let mut true_samples = Vec::new();
+    let mut observed_samples = Vec::new();
+    for children in sampled {
+        let clones = blood_stage_clones(children, &inoc_query, &panel);
+        let mut true_genotypes: Vec<Genotype> = clones.iter().map(|(genotype, _)| *genotype).collect();
+        true_genotypes.sort();
+        observed_samples.push(observed_genotypes(&clones, panel.detection_limit));
+        true_samples.push(true_genotypes);
+    }
+
+    let result = write_samples(&mut outputs.observed_samples, day, &observed_samples, params.n_loci)
+        .and_then(|_| write_samples(&mut outputs.true_samples, day, &true_samples, params.n_loci))
+        .and_then(|_| write_frequencies(&mut outputs.observed_frequencies, day, &observed_samples, params.n_loci))
+        .and_then(|_| write_frequencies(&mut outputs.true_frequencies, day, &true_samples, params.n_loci));
+
+    if let Err(err) = result {
+        report.output_failed("genotyping outputs", format!("day {}: {}", day, err));
+    }
+}
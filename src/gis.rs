@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::Path;
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+// Which point a host stands on, so it can be placed again when the window is resized
+#[derive(Component)]
+pub struct LocationIndex(pub usize);
+
+// Host positions from a map, e.g. household GPS points, in metres east and north of their centroid
+// Coordinates and comorbidity scores as read, and whether the coordinates are lon/lat
+type Parsed = (Vec<(f64, f64)>, Vec<Option<f32>>, bool);
+
+#[derive(Resource, Clone)]
+pub struct HostLocations {
+    points: Vec<Vec2>,
+    comorbidity: Vec<Option<f32>>, // Per point, from an optional comorbidity column or property
+}
+
+impl HostLocations {
+    // GeoJSON Point features, or a CSV with lon,lat (degrees) or x,y (already projected) columns
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let is_csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let (coordinates, comorbidity, geographic) = if is_csv { Self::read_csv(&contents) } else { Self::read_geojson(&contents) }
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        if coordinates.is_empty() {
+            return Err(format!("{}: no points", path.display()));
+        }
+
+        let n = coordinates.len() as f64;
+        let (x0, y0) = coordinates.iter().fold((0.0, 0.0), |(x, y), (px, py)| (x + px / n, y + py / n));
+        let points = coordinates
+            .iter()
+            .map(|&(x, y)| {
+                if geographic {
+                    // Equirectangular projection about the centroid, fine at village scale
+                    let east = (x - x0).to_radians() * y0.to_radians().cos() * EARTH_RADIUS_M;
+                    let north = (y - y0).to_radians() * EARTH_RADIUS_M;
+                    Vec2::new(east as f32, north as f32)
+                } else {
+                    Vec2::new((x - x0) as f32, (y - y0) as f32)
+                }
+            })
+            .collect();
+        Ok(Self { points, comorbidity })
+    }
+
+    fn read_geojson(contents: &str) -> Result<Parsed, String> {
+        let json: serde_json::Value = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+        let features = json["features"].as_array().ok_or("expected a FeatureCollection")?;
+        let coordinates = features
+            .iter()
+            .enumerate()
+            .map(|(index, feature)| {
+                let geometry = &feature["geometry"];
+                match (geometry["type"].as_str(), geometry["coordinates"].as_array()) {
+                    (Some("Point"), Some(position)) if position.len() >= 2 => {
+                        Ok((position[0].as_f64().unwrap_or(f64::NAN), position[1].as_f64().unwrap_or(f64::NAN)))
+                    }
+                    _ => Err(format!("feature {}: expected a Point", index)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if coordinates.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+            return Err("coordinates must be numbers".to_owned());
+        }
+        let comorbidity = features.iter().map(|feature| feature["properties"]["comorbidity"].as_f64().map(|score| score as f32)).collect();
+        Ok((coordinates, comorbidity, true))
+    }
+
+    fn read_csv(contents: &str) -> Result<Parsed, String> {
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<String> = lines.next().unwrap_or("").split(',').map(|name| name.trim().to_lowercase()).collect();
+        let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+        let (x_column, y_column, geographic) = match (column(&["lon", "longitude"]), column(&["lat", "latitude"])) {
+            (Some(x), Some(y)) => (x, y, true),
+            _ => match (column(&["x"]), column(&["y"])) {
+                (Some(x), Some(y)) => (x, y, false),
+                _ => return Err("expected lon,lat or x,y columns".to_owned()),
+            },
+        };
+        let comorbidity_column = column(&["comorbidity"]);
+
+        let coordinates = lines
+            .enumerate()
+            .map(|(index, line)| {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let parse = |column: usize| {
+                    fields
+                        .get(column)
+                        .ok_or_else(|| format!("line {}: missing column", index + 2))?
+                        .parse::<f64>()
+                        .map_err(|err| format!("line {}: {}", index + 2, err))
+                };
+                // An empty comorbidity field leaves that host's score to be drawn
+                let comorbidity = comorbidity_column
+                    .filter(|&column| fields.get(column).is_some_and(|field| !field.is_empty()))
+                    .map(|column| parse(column).map(|score| score as f32))
+                    .transpose()?;
+                Ok(((parse(x_column)?, parse(y_column)?), comorbidity))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (coordinates, comorbidity) = coordinates.into_iter().unzip();
+        Ok((coordinates, comorbidity, geographic))
+    }
+
+    pub fn host_count(&self) -> u32 {
+        self.points.len() as u32
+    }
+
+    // Comorbidity score given for the host at this point, if any
+    pub fn comorbidity(&self, index: usize) -> Option<f32> {
+        self.comorbidity.get(index).copied().flatten()
+    }
+
+    // Scales the layout uniformly to fit the window, leaving room above for inoculation stacks
+    pub fn world_position(&self, index: usize, window: &Window) -> Vec2 {
+        let (min, max) = self
+            .points
+            .iter()
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), point| (min.min(*point), max.max(*point)));
+        let area_min = Vec2::new(-window.width() / 2.0 + 60.0, -window.height() / 2.0 + 40.0);
+        let area_max = Vec2::new(window.width() / 2.0 - 60.0, window.height() / 2.0 - 200.0);
+
+        let extent = (max - min).max(Vec2::splat(1.0));
+        let scale = ((area_max - area_min) / extent).min_element();
+        let center = (area_min + area_max) / 2.0;
+        center + (self.points[index] - (min + max) / 2.0) * scale
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::age_mixing::MixingInfection;
+use crate::blocked::ExposureBlocked;
+use crate::budget::{EntityBudget, InoculationRefused};
+use crate::cleared::despawn_cleared;
+use crate::clock::DayTick;
+use crate::coverage::DoseOffered;
+use crate::event_log::EventLog;
+use crate::ids::Ids;
+use crate::infectiousness::{update_infectious_reservoir, InfectiousReservoir, InfectiousnessCurve};
+use crate::report::RunReport;
+use crate::scenario::Scenario;
+use crate::scheduled::ScheduledActions;
+use crate::stats::{record_host_states, StateHistory};
+use crate::supply::{resupply_drugs, DrugStock, DrugSupply};
+use crate::treatment::{TreatmentHistory, TreatmentPolicy};
+use crate::vectors::{update_vectors, VectorPopulation, Vectors};
+use crate::venues::VenueInfection;
+use crate::{
+    draw_exposure, new_host, process_hosts, process_inoculations, spawn_infections, Params, Sex, SimRng, SimulationEvent,
+    SimulationSpeed, SimulationTime,
+};
+
+// A standalone world running the core daily systems without a window, for tools that run many or quick simulations
+// and for What If forks of the live run
+pub struct HeadlessRun {
+    pub world: World,
+    pub schedule: Schedule, // Extra systems can be added, e.g. to record outputs
+    event_updates: Vec<fn(&mut World)>,
+}
+
+impl HeadlessRun {
+    // A fresh day-0 cohort.  The scenario's vectors, treatment policy and drug stock apply; other sections (MDA, case
+    // detection, venues, mixing) don't.
+    pub fn new(params: &Params, scenario: &Scenario, budget: EntityBudget, curve: &InfectiousnessCurve, hosts: u32, seed: u64) -> Self {
+        let mut world = World::new();
+        world.insert_resource(params.clone());
+        world.insert_resource(budget);
+        world.insert_resource(TreatmentPolicy::new(&scenario.treatment_policy));
+        world.insert_resource(DrugSupply(scenario.drug_stock.clone()));
+        world.insert_resource(DrugStock::default());
+        world.insert_resource(Vectors(scenario.vectors.clone()));
+        world.insert_resource(VectorPopulation::default());
+        world.insert_resource(curve.clone());
+        world.insert_resource(SimulationTime::new(scenario.clock.seconds_per_day));
+        world.insert_resource(Ids::default());
+
+        let mut sim_rng = SimRng::new(seed);
+        let mut sex_rng = StdRng::seed_from_u64(seed ^ 0x5e8);
+        for _ in 0..hosts {
+            let sex = if sex_rng.gen::<f32>() < params.male_fraction { Sex::Male } else { Sex::Female };
+            let (host, inoculation) = new_host(sex, params, scenario.comorbidity.as_ref(), None, 0, &mut sim_rng);
+            let id = world.resource_mut::<Ids>().host();
+            world.spawn((host, id, ScheduledActions::default(), TreatmentHistory::default())).with_children(|parent| {
+                parent.spawn(inoculation);
+            });
+        }
+        world.insert_resource(sim_rng);
+        Self::from_world(world)
+    }
+
+    // Adds the schedule, events and bookkeeping every run shares to a world that already has its hosts and the
+    // model's params, budget, treatment, drug supply and stock, vectors, infectiousness curve, clock and random stream
+    pub fn from_world(mut world: World) -> Self {
+        world.init_resource::<EventLog>();
+        world.init_resource::<InfectiousReservoir>();
+        world.init_resource::<SimulationSpeed>();
+        world.init_resource::<Ids>();
+        world.init_resource::<StateHistory>();
+        world.init_resource::<RunReport>(); // Output writers need one, though headless runs have no outputs of their own
+
+        // One frame per day, so spawn_infections draws each host's daily infection chance once
+        let mut time = Time::<()>::default();
+        time.advance_by(world.resource::<SimulationTime>().timer.duration());
+        world.insert_resource(time);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                update_infectious_reservoir,
+                update_vectors,
+                resupply_drugs,
+                process_inoculations,
+                process_hosts,
+                draw_exposure,
+                spawn_infections,
+                record_host_states,
+                despawn_cleared,
+            )
+                .chain(),
+        );
+        let mut run = Self {
+            world,
+            schedule,
+            event_updates: Vec::new(),
+        };
+        run.add_event::<DayTick>();
+        run.add_event::<SimulationEvent>();
+        run.add_event::<VenueInfection>();
+        run.add_event::<MixingInfection>();
+        run.add_event::<InoculationRefused>();
+        run.add_event::<ExposureBlocked>();
+        run.add_event::<DoseOffered>();
+        run
+    }
+
+    // Registers an event to be updated after every day, as an App would; extra systems' own events need adding too
+    pub fn add_event<E: Event>(&mut self) {
+        self.world.init_resource::<Events<E>>();
+        self.event_updates.push(|world| world.resource_mut::<Events<E>>().update());
+    }
+
+    pub fn day(&self) -> u32 {
+        self.world.resource::<SimulationTime>().day
+    }
+
+    // Runs the next day, returning the simulation events it sent
+    pub fn step(&mut self) -> Vec<SimulationEvent> {
+        let day = self.day() + 1;
+        self.world.resource_mut::<SimulationTime>().day = day;
+        self.world.send_event(DayTick { day });
+        self.schedule.run(&mut self.world);
+        let events = self.world.resource_mut::<Events<SimulationEvent>>().drain().collect();
+        for update in &self.event_updates {
+            update(&mut self.world);
+        }
+        events
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use image::{Rgb, RgbImage};
+use std::fs;
+
+use crate::ids::HostId;
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::{rgb, RunReport};
+use crate::{Host, HostState, Inoculation, OutputDir, SimulationTime};
+
+const MAX_MOI_SHADE: usize = 5; // MOI at which the color ramp saturates
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapColor {
+    State,
+    Moi,
+}
+
+// Daily state and MOI of every host, one column per day with hosts in spawn order
+#[derive(Resource)]
+pub struct HostHeatmap {
+    pub color: HeatmapColor,
+    columns: Vec<(u32, Vec<(HostState, usize)>)>,
+    texture: Option<egui::TextureHandle>,
+    dirty: bool,
+}
+
+impl Default for HostHeatmap {
+    fn default() -> Self {
+        Self {
+            color: HeatmapColor::State,
+            columns: Vec::new(),
+            texture: None,
+            dirty: true,
+        }
+    }
+}
+
+impl HostHeatmap {
+    fn size(&self) -> (usize, usize) {
+        let hosts = self.columns.iter().map(|(_, hosts)| hosts.len()).max().unwrap_or(0);
+        (self.columns.len(), hosts)
+    }
+
+    fn pixel(&self, day: usize, host: usize) -> [u8; 3] {
+        let Some(&(state, moi)) = self.columns[day].1.get(host) else {
+            return [0, 0, 0];
+        };
+        match self.color {
+            HeatmapColor::State => rgb(state.color()),
+            HeatmapColor::Moi if moi == 0 => [30, 30, 30],
+            HeatmapColor::Moi => {
+                // Dark red for a single clone up to yellow for many
+                let t = (moi - 1).min(MAX_MOI_SHADE - 1) as f32 / (MAX_MOI_SHADE - 1) as f32;
+                [(100.0 + 155.0 * t) as u8, (220.0 * t) as u8, 0]
+            }
+        }
+    }
+
+    fn image(&self) -> RgbImage {
+        let (days, hosts) = self.size();
+        RgbImage::from_fn(days as u32, hosts as u32, |x, y| Rgb(self.pixel(x as usize, y as usize)))
+    }
+
+    // For display, every nth day and host so neither side exceeds the GPU's largest texture; the PNG keeps them all
+    fn preview(&self, max_side: usize) -> egui::ColorImage {
+        let (days, hosts) = self.size();
+        let (day_step, host_step) = (days.div_ceil(max_side.max(1)), hosts.div_ceil(max_side.max(1)));
+        let (width, height) = (days.div_ceil(day_step), hosts.div_ceil(host_step));
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend(self.pixel(x * day_step, y * host_step));
+            }
+        }
+        egui::ColorImage::from_rgb([width, height], &pixels)
+    }
+}
+
+pub fn record_heatmap(
+    host_query: Query<(&HostId, &Host, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut heatmap: ResMut<HostHeatmap>,
+) {
+    if heatmap.columns.last().is_some_and(|(day, _)| *day == sim_time.day) {
+        return;
+    }
+
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort_by_key(|(id, _, _)| **id); // Rows in host id order
+    let column = hosts
+        .into_iter()
+        .map(|(_, host, children)| (host.state(children, &inoc_query), children.map_or(0, |c| c.len())))
+        .collect();
+    heatmap.columns.push((sim_time.day, column));
+    heatmap.dirty = true;
+}
+
+pub fn heatmap_ui(
+    mut contexts: EguiContexts,
+    mut heatmap: ResMut<HostHeatmap>,
+    analytics: Res<AnalyticsWindow>,
+    output_dir: Res<OutputDir>,
+    mut report: ResMut<RunReport>,
+) {
+    let ctx = analytics_ctx(&mut contexts, &analytics).clone();
+    if analytics.is_changed() {
+        // Textures belong to one window's egui context
+        heatmap.texture = None;
+        heatmap.dirty = true;
+    }
+
+    let (days, hosts) = heatmap.size();
+    if heatmap.dirty && days > 0 && hosts > 0 {
+        let pixels = heatmap.preview(ctx.input(|input| input.max_texture_side));
+        match &mut heatmap.texture {
+            Some(texture) => texture.set(pixels, egui::TextureOptions::NEAREST),
+            None => heatmap.texture = Some(ctx.load_texture("host_heatmap", pixels, egui::TextureOptions::NEAREST)),
+        }
+        heatmap.dirty = false;
+    }
+
+    egui::Window::new("Host Heatmap")
+        .default_pos(egui::pos2(450.0, 400.0))
+        .default_size(egui::vec2(400.0, 200.0))
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                let previous = heatmap.color;
+                ui.selectable_value(&mut heatmap.color, HeatmapColor::State, "State");
+                ui.selectable_value(&mut heatmap.color, HeatmapColor::Moi, "MOI");
+                if heatmap.color != previous {
+                    heatmap.dirty = true;
+                }
+
+                if ui.button("Save PNG").clicked() {
+                    let path = output_dir.0.join("heatmap.png");
+                    let result = fs::create_dir_all(&output_dir.0).map_err(|err| err.to_string());
+                    match result.and_then(|()| heatmap.image().save(&path).map_err(|err| err.to_string())) {
+                        Ok(()) => info!("Wrote heatmap to {}", path.display()),
+                        Err(err) => report.output_failed("heatmap", err),
+                    }
+                }
+            });
+
+            // Hosts down, days across, stretched to fill the window
+            if let Some(texture) = &heatmap.texture {
+                let size = egui::vec2(ui.available_width(), ui.available_height().max(100.0));
+                ui.image((texture.id(), size));
+            }
+        });
+}
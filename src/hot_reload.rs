@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::event_log::EventLog;
+use crate::param_docs;
+use crate::scenario::Scenario;
+use crate::{Params, SimulationTime};
+
+const POLL_SECONDS: f32 = 1.0;
+
+// Scenario file watched for parameter edits while the simulation runs
+#[derive(Resource)]
+pub struct ScenarioWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    params_table: toml::Table, // Last applied [params] section, to report what changed
+    timer: Timer,
+}
+
+fn params_table(contents: &str) -> toml::Table {
+    let table: toml::Table = contents.parse().unwrap_or_default();
+    match table.get("params") {
+        Some(toml::Value::Table(params)) => params.clone(),
+        _ => toml::Table::new(),
+    }
+}
+
+impl ScenarioWatch {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        let params_table = fs::read_to_string(&path).map(|contents| params_table(&contents)).unwrap_or_default();
+        Self {
+            path,
+            modified,
+            params_table,
+            timer: Timer::from_seconds(POLL_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+// Polls the file's modification time and applies its [params] over the current values when it changes
+pub fn reload_scenario_params(
+    time: Res<Time>,
+    sim_time: Res<SimulationTime>,
+    mut watch: ResMut<ScenarioWatch>,
+    mut params: ResMut<Params>,
+    mut log: ResMut<EventLog>,
+) {
+    if !watch.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = fs::metadata(&watch.path).and_then(|metadata| metadata.modified()).ok();
+    if modified.is_none() || modified == watch.modified {
+        return;
+    }
+    watch.modified = modified;
+
+    let scenario = fs::read_to_string(&watch.path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| Scenario::parse(&contents).map(|scenario| (scenario, contents)));
+    let (scenario, contents) = match scenario {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            // Keep running on the old values until the file is fixed
+            warn!("Ignoring edited scenario {}: {}", watch.path.display(), err);
+            return;
+        }
+    };
+
+    let table = params_table(&contents);
+    let mut changes: Vec<String> = table
+        .iter()
+        .filter(|(key, value)| watch.params_table.get(*key) != Some(value))
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect();
+    changes.extend(
+        watch.params_table.keys().filter(|key| !table.contains_key(*key)).map(|key| format!("{} removed (value kept)", key)),
+    );
+    watch.params_table = table;
+    if changes.is_empty() {
+        return;
+    }
+
+    let mut reloaded = params.clone();
+    scenario.params.apply(&mut reloaded);
+    if let Err(errors) = param_docs::validate(&reloaded) {
+        warn!("Ignoring edited scenario {}: {}", watch.path.display(), errors.join("; "));
+        return;
+    }
+    *params = reloaded;
+    log.log(sim_time.day, format!("Params reloaded: {}", changes.join(", ")));
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+
+// Stable keys for joining outputs across runs: numbered in spawn order from the start of each run, where Entity
+// ids depend on what else was spawned and are reused after a despawn
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
+pub struct HostId(pub u32);
+
+pub const PATCH_SIZE: u32 = 5; // Neighbouring hosts per patch, as hosts are laid out in id order
+
+impl HostId {
+    pub fn patch(&self) -> u32 {
+        self.0 / PATCH_SIZE
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
+pub struct InoculationId(pub u32);
+
+// Next free ids; reset along with the host population
+#[derive(Resource, Default)]
+pub struct Ids {
+    next_host: u32,
+    next_inoculation: u32,
+}
+
+impl Ids {
+    pub fn host(&mut self) -> HostId {
+        self.next_host += 1;
+        HostId(self.next_host - 1)
+    }
+
+    pub fn inoculation(&mut self) -> InoculationId {
+        self.next_inoculation += 1;
+        InoculationId(self.next_inoculation - 1)
+    }
+}
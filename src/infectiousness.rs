@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::Path;
+
+use crate::{Host, InfectionState, Inoculation, Params, SimulationTime};
+
+// Infectiousness over time since entering each state, as (day, infectiousness) breakpoints
+#[derive(Resource, Clone)]
+pub struct InfectiousnessCurve {
+    acute: Vec<(f32, f32)>,
+    chronic: Vec<(f32, f32)>,
+}
+
+impl Default for InfectiousnessCurve {
+    fn default() -> Self {
+        Self {
+            acute: vec![(0.0, 0.2), (5.0, 1.0), (20.0, 0.6), (40.0, 0.4)],
+            chronic: vec![(0.0, 0.3), (30.0, 0.15), (200.0, 0.05)],
+        }
+    }
+}
+
+impl InfectiousnessCurve {
+    // Reads a "state,day,infectiousness" lookup table, e.g. assets/infectiousness.csv
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut curve = Self { acute: Vec::new(), chronic: Vec::new() };
+
+        for (line_number, line) in contents.lines().enumerate().skip(1) {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if let [state, day, value] = fields[..] {
+                let point = (
+                    day.parse::<f32>().map_err(|err| format!("line {}: {}", line_number + 1, err))?,
+                    value.parse::<f32>().map_err(|err| format!("line {}: {}", line_number + 1, err))?,
+                );
+                match state {
+                    "A" => curve.acute.push(point),
+                    "C" => curve.chronic.push(point),
+                    _ => return Err(format!("line {}: unknown state '{}'", line_number + 1, state)),
+                }
+            } else if !line.trim().is_empty() {
+                return Err(format!("line {}: expected 3 fields", line_number + 1));
+            }
+        }
+
+        curve.acute.sort_by(|a, b| a.0.total_cmp(&b.0));
+        curve.chronic.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(curve)
+    }
+
+    // Linear interpolation between breakpoints, held constant beyond the last one
+    fn lookup(points: &[(f32, f32)], day: f32) -> f32 {
+        match points.iter().position(|&(d, _)| d > day) {
+            None => points.last().map_or(0.0, |&(_, value)| value),
+            Some(0) => points[0].1,
+            Some(i) => {
+                let (d0, v0) = points[i - 1];
+                let (d1, v1) = points[i];
+                v0 + (v1 - v0) * (day - d0) / (d1 - d0)
+            }
+        }
+    }
+
+    pub fn infectiousness(&self, inoc: &Inoculation, day: u32) -> f32 {
+        let days_in_state = day as f32 - inoc.start_day as f32;
+        match inoc.state {
+            InfectionState::E => 0.0, // Liver stage has no gametocytes
+            InfectionState::A => Self::lookup(&self.acute, days_in_state),
+            InfectionState::C => Self::lookup(&self.chronic, days_in_state),
+        }
+    }
+}
+
+// Probability that a host infects a biting vector, combining its inoculations independently
+pub fn host_infectiousness(
+    children: Option<&Children>,
+    inoc_query: &Query<&Inoculation>,
+    curve: &InfectiousnessCurve,
+    params: &Params,
+    day: u32,
+) -> f32 {
+    let not_infectious: f32 = children
+        .map(|children| {
+            children
+                .iter()
+                .filter_map(|&child| inoc_query.get(child).ok())
+                .map(|inoc| 1.0 - (curve.infectiousness(inoc, day) * inoc.genotype.transmission_fitness(params)).clamp(0.0, 1.0))
+                .product()
+        })
+        .unwrap_or(1.0);
+    1.0 - not_infectious
+}
+
+#[derive(Resource, Default)]
+pub struct InfectiousReservoir {
+    pub mean: f32, // Average host infectiousness across the population
+}
+
+pub fn load_infectiousness_curve(mut commands: Commands) {
+    let path = Path::new("assets/infectiousness.csv");
+    let curve = InfectiousnessCurve::load(path).unwrap_or_else(|err| {
+        warn!("Using default infectiousness curve ({}: {})", path.display(), err);
+        InfectiousnessCurve::default()
+    });
+    commands.insert_resource(curve);
+}
+
+pub fn update_infectious_reservoir(
+    host_query: Query<Option<&Children>, With<Host>>,
+    inoc_query: Query<&Inoculation>,
+    curve: Res<InfectiousnessCurve>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut reservoir: ResMut<InfectiousReservoir>,
+) {
+    let host_count = host_query.iter().len();
+    if host_count == 0 {
+        return;
+    }
+
+    let total: f32 = host_query
+        .iter()
+        .map(|children| host_infectiousness(children, &inoc_query, &curve, &params, sim_time.day))
+        .sum();
+    reservoir.mean = total / host_count as f32;
+}
@@ -0,0 +1,82 @@
+use bevy::input::touch::Touches;
+use bevy::prelude::*;
+
+use crate::focus::{pick_host, FocalHost};
+use crate::kiosk::Kiosk;
+use crate::{Host, MainCamera, SimulationSpeed};
+
+const TAP_DISTANCE: f32 = 10.0; // Max finger travel for a touch to count as a tap
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.0;
+
+// Tap to inspect a host, pinch to zoom, and two-finger drag to pan the host view
+pub fn touch_controls(
+    touches: Res<Touches>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut projection_query: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+    host_query: Query<(Entity, &GlobalTransform), With<Host>>,
+    mut focal: ResMut<FocalHost>,
+    mut multi_touch: Local<bool>, // Whether the current gesture has used two fingers
+) {
+    let active: Vec<_> = touches.iter().collect();
+
+    if let [first, second] = active[..] {
+        *multi_touch = true;
+        let Ok((mut projection, mut transform)) = projection_query.get_single_mut() else {
+            return;
+        };
+
+        let previous_span = first.previous_position().distance(second.previous_position());
+        let span = first.position().distance(second.position());
+        if previous_span > 0.0 && span > 0.0 {
+            projection.scale = (projection.scale * previous_span / span).clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+
+        // Window y grows downward while world y grows upward
+        let midpoint_delta = (first.delta() + second.delta()) / 2.0;
+        transform.translation.x -= midpoint_delta.x * projection.scale;
+        transform.translation.y += midpoint_delta.y * projection.scale;
+        return;
+    }
+
+    for touch in touches.iter_just_released() {
+        if !*multi_touch && touch.distance().length() <= TAP_DISTANCE {
+            focal.select(pick_host(touch.position(), &camera_query, &host_query));
+        }
+    }
+
+    if active.is_empty() {
+        *multi_touch = false;
+    }
+}
+
+// South/Start toggles pause (except on a kiosk, as in Simulation Controls), D-pad up/down changes speed, Select resets
+// the view
+pub fn gamepad_controls(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut projection_query: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+    kiosk: Option<Res<Kiosk>>,
+) {
+    for gamepad in gamepads.iter() {
+        let pressed = |button_type| buttons.just_pressed(GamepadButton::new(gamepad, button_type));
+
+        if kiosk.is_none() && (pressed(GamepadButtonType::South) || pressed(GamepadButtonType::Start)) {
+            speed.toggle_pause();
+        }
+        if pressed(GamepadButtonType::DPadUp) || pressed(GamepadButtonType::RightTrigger) {
+            speed.faster();
+        }
+        if pressed(GamepadButtonType::DPadDown) || pressed(GamepadButtonType::LeftTrigger) {
+            speed.slower();
+        }
+        if pressed(GamepadButtonType::Select) {
+            if let Ok((mut projection, mut transform)) = projection_query.get_single_mut() {
+                projection.scale = 1.0;
+                transform.translation.x = 0.0;
+                transform.translation.y = 0.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use bevy::input::common_conditions::input_toggle_active;
+use bevy::prelude::*;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+use crate::distributions::DurationDist;
+use crate::ids::{HostId, InoculationId};
+use crate::presentation::not_presenting;
+use crate::treatment::{CourseDrug, TreatmentHistory};
+use crate::scheduled::{HostAction, ScheduledActions, ScheduledAt};
+use crate::occupations::Occupation;
+use crate::{Drug, Genotype, Host, InfectionState, Inoculation, Params, Regimen, Sex, SimulationSpeed, SimulationTime};
+
+// Developer view of every entity, component and resource, toggled with F12; only in `--features inspector` builds
+pub fn add_inspector(app: &mut App) {
+    app.register_type::<Host>()
+        .register_type::<Inoculation>()
+        .register_type::<HostId>()
+        .register_type::<Sex>()
+        .register_type::<Occupation>()
+        .register_type::<TreatmentHistory>()
+        .register_type::<CourseDrug>()
+        .register_type::<InoculationId>()
+        .register_type::<InfectionState>()
+        .register_type::<Genotype>()
+        .register_type::<Regimen>()
+        .register_type::<ScheduledActions>()
+        .register_type::<ScheduledAt>()
+        .register_type::<HostAction>()
+        .register_type::<Params>()
+        .register_type::<Drug>()
+        .register_type::<DurationDist>()
+        .register_type::<SimulationTime>()
+        .register_type::<SimulationSpeed>()
+        .add_plugins(WorldInspectorPlugin::new().run_if(input_toggle_active(false, KeyCode::F12).and_then(not_presenting)));
+}
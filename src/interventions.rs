@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::rng::{CountingRng, Subsystem};
+use crate::{Host, SimRng, SimulationTime};
+
+// A population-level intervention, run in rounds over all hosts. Register with `app.add_intervention(...)`.
+pub trait Intervention: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    // Random stream for eligibility draws, so each intervention keeps its own sequence
+    fn subsystem(&self) -> Subsystem;
+
+    // Whether a round runs today
+    fn active(&self, world: &World, day: u32) -> bool;
+
+    // Whether the host is reached by today's round
+    fn eligible(&self, world: &World, host: &Host, rng: &mut CountingRng) -> bool;
+
+    // Applies the intervention to a reached host, after eligibility has been decided for everyone
+    fn apply(&self, world: &mut World, host: Entity, day: u32);
+}
+
+#[derive(Resource, Default)]
+pub struct Interventions(Vec<Box<dyn Intervention>>);
+
+pub trait InterventionAppExt {
+    fn add_intervention(&mut self, intervention: impl Intervention) -> &mut Self;
+}
+
+impl InterventionAppExt for App {
+    fn add_intervention(&mut self, intervention: impl Intervention) -> &mut Self {
+        self.init_resource::<Interventions>();
+        self.world.resource_mut::<Interventions>().0.push(Box::new(intervention));
+        self
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Coverage {
+    pub rounds: u32,
+    pub reached: u32,  // Host-rounds
+    pub eligible: u32, // Hosts present, summed over rounds
+    last_round_day: Option<u32>,
+}
+
+impl Coverage {
+    pub fn fraction(&self) -> f32 {
+        if self.eligible > 0 {
+            self.reached as f32 / self.eligible as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+// Rounds and hosts reached by each registered intervention, in registration order
+#[derive(Resource, Default)]
+pub struct InterventionCoverage(pub Vec<Coverage>);
+
+pub fn run_interventions(world: &mut World) {
+    let day = world.resource::<SimulationTime>().day;
+    world.resource_scope(|world, interventions: Mut<Interventions>| {
+        for (index, intervention) in interventions.0.iter().enumerate() {
+            let mut coverage = world.resource::<InterventionCoverage>().0.get(index).cloned().unwrap_or_default();
+            if coverage.last_round_day == Some(day) || !intervention.active(world, day) {
+                continue;
+            }
+
+            let mut hosts = world.query::<(Entity, &Host)>();
+            let mut reached = Vec::new();
+            world.resource_scope(|world, mut sim_rng: Mut<SimRng>| {
+                for (entity, host) in hosts.iter(world) {
+                    coverage.eligible += 1;
+                    if intervention.eligible(world, host, &mut sim_rng.stream(intervention.subsystem())) {
+                        reached.push(entity);
+                    }
+                }
+            });
+            for &entity in &reached {
+                intervention.apply(world, entity, day);
+            }
+
+            coverage.rounds += 1;
+            coverage.reached += reached.len() as u32;
+            coverage.last_round_day = Some(day);
+            let mut accounts = world.resource_mut::<InterventionCoverage>();
+            if accounts.0.len() <= index {
+                accounts.0.resize(index + 1, Coverage::default());
+            }
+            accounts.0[index] = coverage;
+        }
+    });
+}
+
+#[cfg(feature = "gui")]
+pub fn interventions_ui(
+    mut contexts: EguiContexts,
+    interventions: Res<Interventions>,
+    coverage: Res<InterventionCoverage>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    if coverage.0.iter().all(|coverage| coverage.rounds == 0) {
+        return;
+    }
+
+    egui::Window::new("Interventions")
+        .default_pos(egui::pos2(450.0, 550.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            egui::Grid::new("intervention_coverage").striped(true).show(ui, |ui| {
+                ui.label("Intervention");
+                ui.label("Rounds");
+                ui.label("Reached");
+                ui.label("Coverage");
+                ui.end_row();
+
+                for (intervention, coverage) in interventions.0.iter().zip(&coverage.0) {
+                    ui.label(intervention.name());
+                    ui.label(coverage.rounds.to_string());
+                    ui.label(coverage.reached.to_string());
+                    ui.label(format!("{:.0}%", 100.0 * coverage.fraction()));
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,102 @@
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::input::touch::Touches;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::experiments::preset_settings;
+use crate::mda::MdaSchedule;
+use crate::gis::HostLocations;
+use crate::scenario::{KioskConfig, MdaConfig, Preset};
+use crate::snapshot::{preset_snapshot, Snapshot, WarmStart};
+use crate::{Params, ResetSimulation, SimulationSpeed};
+
+// Exhibit mode: resets after a period without input, cycling through presets
+#[derive(Resource)]
+pub struct Kiosk {
+    config: KioskConfig,
+    presets: Vec<Preset>,
+    current: usize,
+    base_params: Params,
+    base_mda: Option<MdaConfig>,
+    base_snapshot: Option<Snapshot>,
+    idle_seconds: f32,
+}
+
+impl Kiosk {
+    pub fn new(config: KioskConfig, presets: Vec<Preset>, base_params: Params, base_mda: Option<MdaConfig>, base_snapshot: Option<Snapshot>) -> Self {
+        Self {
+            config,
+            presets,
+            current: 0,
+            base_params,
+            base_mda,
+            base_snapshot,
+            idle_seconds: 0.0,
+        }
+    }
+
+    // Base scenario params and MDA schedule with the current preset applied
+    pub fn settings(&self) -> (Params, Option<MdaConfig>) {
+        preset_settings(self.presets.get(self.current), &self.base_params, &self.base_mda)
+    }
+
+    pub fn snapshot(&self, locations: Option<&HostLocations>) -> Option<Snapshot> {
+        preset_snapshot(self.presets.get(self.current), &self.base_snapshot, locations)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn track_kiosk_activity(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    touches: Res<Touches>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut kiosk: ResMut<Kiosk>,
+    mut params: ResMut<Params>,
+    mut schedule: ResMut<MdaSchedule>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut warm_start: ResMut<WarmStart>,
+    locations: Option<Res<HostLocations>>,
+    mut reset_events: EventWriter<ResetSimulation>,
+) {
+    let active = keys.get_pressed().next().is_some()
+        || mouse_buttons.get_pressed().next().is_some()
+        || gamepad_buttons.get_pressed().next().is_some()
+        || touches.iter().next().is_some()
+        || mouse_motion.read().count() > 0
+        || mouse_wheel.read().count() > 0;
+
+    if active {
+        kiosk.idle_seconds = 0.0;
+        return;
+    }
+
+    kiosk.idle_seconds += time.delta_seconds();
+    if kiosk.idle_seconds < kiosk.config.idle_minutes * 60.0 {
+        return;
+    }
+
+    kiosk.idle_seconds = 0.0;
+    if kiosk.config.cycle_presets && !kiosk.presets.is_empty() {
+        kiosk.current = (kiosk.current + 1) % kiosk.presets.len();
+    }
+    (*params, schedule.0) = kiosk.settings();
+    warm_start.0 = kiosk.snapshot(locations.as_deref());
+    *speed = SimulationSpeed::default();
+    reset_events.send(ResetSimulation);
+}
+
+pub fn kiosk_ui(mut contexts: EguiContexts, kiosk: Res<Kiosk>) {
+    let Some(preset) = kiosk.presets.get(kiosk.current) else {
+        return;
+    };
+
+    egui::Area::new("kiosk_preset".into())
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(egui::RichText::new(&preset.name).size(24.0).color(egui::Color32::WHITE));
+        });
+}
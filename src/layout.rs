@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResized};
+use bevy_egui::egui;
+
+use crate::environment::EnvironmentLayer;
+use crate::gis::{HostLocations, LocationIndex};
+use crate::{Host, HostState, Inoculation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostOrder {
+    Spawn, // Order hosts were created in
+    State,
+    Moi, // Number of inoculations
+}
+
+#[derive(Resource)]
+pub struct HostLayout {
+    pub order: HostOrder,
+    pub grouped: bool, // Leave a gap between hosts with different sort keys
+    pub speed: f32,    // Fraction of the remaining distance covered per second
+}
+
+impl Default for HostLayout {
+    fn default() -> Self {
+        Self {
+            order: HostOrder::Spawn,
+            grouped: false,
+            speed: 4.0,
+        }
+    }
+}
+
+impl HostLayout {
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Host Order");
+
+        egui::ComboBox::from_id_source("host_order")
+            .selected_text(format!("{:?}", self.order))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.order, HostOrder::Spawn, "Spawn");
+                ui.selectable_value(&mut self.order, HostOrder::State, "State");
+                ui.selectable_value(&mut self.order, HostOrder::Moi, "MOI");
+            });
+        ui.checkbox(&mut self.grouped, "Group by sort key");
+    }
+}
+
+// Height of the host row, comfortably above the bottom edge
+pub fn bottom_y(window: &Window) -> f32 {
+    -window.height() / 2.0 + 40.0
+}
+
+// Moves hosts to the new bottom margin, or their scaled map position, when the main window is resized or goes
+// fullscreen; layout_hosts then spreads the row across the new width
+pub fn reflow_on_resize(
+    mut resize_events: EventReader<WindowResized>,
+    window_query: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut host_query: Query<(&mut Transform, Option<&LocationIndex>), With<Host>>,
+    mut layer_query: Query<&mut Sprite, With<EnvironmentLayer>>,
+    locations: Option<Res<HostLocations>>,
+) {
+    let Ok((window_entity, window)) = window_query.get_single() else {
+        return;
+    };
+    if !resize_events.read().any(|event| event.window == window_entity) {
+        return;
+    }
+
+    for (mut transform, index) in host_query.iter_mut() {
+        match (locations.as_deref(), index) {
+            (Some(locations), Some(index)) => {
+                let position = locations.world_position(index.0, window);
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+            }
+            _ => transform.translation.y = bottom_y(window),
+        }
+    }
+    for mut sprite in layer_query.iter_mut() {
+        sprite.custom_size = Some(Vec2::new(window.width(), window.height()));
+    }
+}
+
+// Slides hosts toward their sorted position along the bottom of the window
+pub fn layout_hosts(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut host_query: Query<(Entity, &Host, Option<&Children>, &mut Transform)>,
+    inoc_query: Query<&Inoculation>,
+    layout: Res<HostLayout>,
+    time: Res<Time>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let mut keyed: Vec<(usize, Entity)> = host_query
+        .iter()
+        .map(|(entity, host, children, _)| {
+            let key = match layout.order {
+                HostOrder::Spawn => 0,
+                HostOrder::State => {
+                    let state = host.state(children, &inoc_query);
+                    HostState::ALL.iter().position(|s| *s == state).unwrap_or(0)
+                }
+                HostOrder::Moi => children.map_or(0, |c| c.len()),
+            };
+            (key, entity)
+        })
+        .collect();
+    keyed.sort();
+
+    let groups = if layout.grouped {
+        keyed.windows(2).filter(|pair| pair[0].0 != pair[1].0).count()
+    } else {
+        0
+    };
+    let slots = keyed.len() + groups;
+    let spacing = window.width() / (slots as f32 + 1.0);
+
+    let mut slot = 0;
+    let step = (layout.speed * time.delta_seconds()).min(1.0);
+    for (index, &(key, entity)) in keyed.iter().enumerate() {
+        if layout.grouped && index > 0 && keyed[index - 1].0 != key {
+            slot += 1;
+        }
+        slot += 1;
+
+        if let Ok((_, _, _, mut transform)) = host_query.get_mut(entity) {
+            let target_x = slot as f32 * spacing - window.width() / 2.0;
+            transform.translation.x += (target_x - transform.translation.x) * step;
+        }
+    }
+}
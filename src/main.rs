@@ -1,14 +1,152 @@
+// Without the gui feature only the subcommands are built, leaving the app's systems and their imports unused
+#![cfg_attr(not(feature = "gui"), allow(dead_code, unused_imports))]
+
 use bevy::prelude::*;
+#[cfg(feature = "gui")]
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
-use bevy::window::PrimaryWindow;
-use rand::distributions::{Uniform, Distribution};
+use bevy::window::{ExitCondition, PrimaryWindow};
+use chrono::{Datelike, NaiveDate};
+use clap::Parser;
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use clock::{SimulationClockPlugin, SimulationSpeed, SimulationTime};
+use distributions::DurationDist;
+use ids::Ids;
+use portable_math::PortableFloat;
+use scheduled::{HostAction, ScheduledActions};
+use interventions::InterventionAppExt;
+use observers::ObserverAppExt;
+
+mod acd;
+mod age_mixing;
+mod alerts;
+#[cfg(feature = "gui")]
+mod annotations;
+#[cfg(feature = "audio")]
+mod audio;
+mod autosave;
+#[cfg(feature = "gui")]
+mod badges;
+mod blocked;
+#[cfg(feature = "gui")]
+mod branching;
+mod budget;
+mod cleared;
+mod clock;
+#[cfg(feature = "gui")]
+mod comparison;
+#[cfg(feature = "gui")]
+mod context_menu;
+mod coverage;
+mod cohort;
+mod crowds;
+mod diary;
+mod distributions;
+mod environment;
+#[cfg(feature = "gui")]
+mod experiments;
+#[cfg(feature = "gui")]
+mod focus;
+mod genotyping;
+mod gis;
+mod event_log;
+mod headless;
+#[cfg(feature = "gui")]
+mod heatmap;
+mod hot_reload;
+mod ids;
+mod infectiousness;
+#[cfg(feature = "gui")]
+mod input;
+mod interventions;
+#[cfg(feature = "inspector")]
+mod inspector;
+#[cfg(feature = "gui")]
+mod kiosk;
+#[cfg(feature = "gui")]
+mod layout;
+mod mda;
+mod migration;
+mod observers;
+mod param_docs;
+mod occupations;
+mod ode;
+#[cfg(feature = "gui")]
+mod plots;
+mod portable_math;
+mod prcc;
+#[cfg(feature = "gui")]
+mod presentation;
+mod report;
+mod rng;
+#[cfg(feature = "gui")]
+mod sandbox;
+mod scenario;
+mod scheduled;
+mod sensitivity;
+mod serology;
+mod snapshot;
+#[cfg(feature = "gui")]
+mod sparklines;
+#[cfg(feature = "gui")]
+mod state_debugger;
+mod state_hash;
+mod stats;
+mod strata;
+mod supply;
+mod terms;
+mod test_negative;
+mod transmission;
+mod treatment;
+mod trial;
+#[cfg(feature = "gui")]
+mod tutorial;
+#[cfg(feature = "gui")]
+mod undo;
+mod validate;
+mod venues;
+mod vectors;
 
 // Components
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
 struct Host {
-    on_prophylaxis: bool,
-    prophylaxis_end_day: Option<u32>, // Tracks when prophylaxis ends
-    treat_request_day: Option<u32>,  // Pending treatment
+    on_prophylaxis: bool, // Ended by a scheduled HostAction::EndProphylaxis
+    propensity: f32, // Uniform draw at spawn; hosts with a high propensity are the ones who refuse interventions
+    last_offer: Option<(u32, Regimen, bool)>, // Day, regimen and acceptance of the latest treatment offer
+    birth_day: f32, // Simulation day the host was born, negative for hosts older than the run
+    #[cfg_attr(feature = "inspector", reflect(ignore))]
+    immune_memory: BTreeSet<Genotype>, // Antigenic types this host has seen at blood stage
+    exposure: Option<f32>, // Today's multiplier on the infection rate when exposure is overdispersed
+    antibody: f32, // Titre as of antibody_day, in units of one boost
+    antibody_day: u32,
+    comorbidity: f32, // Score drawn at spawn with a [comorbidity] section, otherwise 0
+    sex: Sex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+#[serde(rename_all = "lowercase")]
+enum Sex {
+    #[default]
+    Female,
+    Male,
+}
+
+impl Sex {
+    pub const ALL: [Sex; 2] = [Sex::Female, Sex::Male];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Sex::Female => "female",
+            Sex::Male => "male",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,7 +158,94 @@ enum HostState {
     P, // Prophylaxis
 }
 
+impl HostState {
+    pub const ALL: [HostState; 5] = [HostState::S, HostState::E, HostState::A, HostState::C, HostState::P];
+
+    #[cfg(feature = "gui")]
+    pub fn color(&self) -> Color {
+        match self {
+            HostState::P => Color::GREEN, // Prophylaxis
+            HostState::A => Color::RED,   // Acute
+            HostState::C => Color::ORANGE, // Chronic
+            HostState::E => Color::BLUE,  // Exposed
+            HostState::S => Color::GRAY,  // Susceptible
+        }
+    }
+}
+
+// Young-skewed age structure of a high-transmission setting, as an exponential truncated at the oldest age
+const MEAN_AGE_YEARS: f32 = 22.0;
+const MAX_AGE_YEARS: f32 = 85.0;
+
 impl Host {
+    pub fn age_years(&self, day: u32) -> f32 {
+        (day as f32 - self.birth_day) / 365.0
+    }
+
+    // Antibody titre on `day`, decayed since the last boost
+    pub fn antibody_titre(&self, day: u32, params: &Params) -> f32 {
+        let elapsed = day.saturating_sub(self.antibody_day) as f32;
+        self.antibody * 0.5f32.portable_powf(elapsed / params.antibody_half_life.max(1.0))
+    }
+
+    pub fn boost_antibodies(&mut self, day: u32, params: &Params) {
+        self.antibody = self.antibody_titre(day, params) + params.boost_size;
+        self.antibody_day = day;
+    }
+
+    // Multiplier on the vector-borne infection rate, e.g. for men who work in the forest
+    pub fn sex_exposure(&self, params: &Params) -> f32 {
+        match self.sex {
+            Sex::Female => 1.0,
+            Sex::Male => params.male_exposure,
+        }
+    }
+
+    // Chance an acute case seeks treatment
+    pub fn prob_treatment(&self, params: &Params) -> f32 {
+        match self.sex {
+            Sex::Female => params.prob_treatment,
+            Sex::Male => (params.prob_treatment * params.male_care_seeking).min(1.0),
+        }
+    }
+
+    // Chance a blood-stage infection is acute, with the odds raised by comorbidity
+    pub fn prob_acute(&self, params: &Params) -> f32 {
+        if self.comorbidity <= 0.0 || params.prob_acute >= 1.0 {
+            return params.prob_acute;
+        }
+        let odds = params.prob_acute / (1.0 - params.prob_acute) * params.comorbidity_odds_ratio.portable_powf(self.comorbidity);
+        odds / (1.0 + odds)
+    }
+
+    // Probability that a new inoculation of this genotype is blocked at the liver stage
+    pub fn protection_against(&self, genotype: Genotype, params: &Params) -> f32 {
+        if self.immune_memory.contains(&genotype.antigen()) {
+            params.immunity_protection
+        } else if !self.immune_memory.is_empty() {
+            params.immunity_protection * params.cross_protection
+        } else {
+            0.0
+        }
+    }
+
+    // Whether the host takes up an offered intervention; with correlated acceptance the same hosts keep refusing
+    pub fn accepts(&self, regimen: Regimen, params: &Params, rng: &mut impl Rng) -> bool {
+        let acceptance = match regimen {
+            Regimen::Treatment | Regimen::Screening => params.acceptance_treatment,
+            Regimen::Mda => params.acceptance_mda,
+        };
+        if acceptance >= 1.0 {
+            return true;
+        }
+        let draw = if rng.gen::<f32>() < params.acceptance_correlation {
+            self.propensity
+        } else {
+            rng.gen::<f32>()
+        };
+        draw < acceptance
+    }
+
     pub fn state(&self, inoculations: Option<&Children>, inoc_query: &Query<&Inoculation>) -> HostState {
         if self.on_prophylaxis {
             return HostState::P;
@@ -56,120 +281,421 @@ impl Host {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
 struct Inoculation {
     state: InfectionState,
     start_day: u32,
     delay_days: f32,
+    genotype: Genotype,
+    infected_day: u32, // Day the host was inoculated, for the whole infection's duration once it clears
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
 enum InfectionState {
     E, // Exposed
     A, // Acute
     C, // Chronic
 }
 
+// Biallelic loci packed into bits, e.g. 0b0110 for four loci, plus a drug resistance marker in the top bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+struct Genotype(u16);
+
+impl Genotype {
+    const RESISTANCE_BIT: u16 = 1 << 15;
+
+    pub fn random(n_loci: u32, resistant_fraction: f32, rng: &mut impl Rng) -> Self {
+        let antigen = rng.gen_range(0..1u16 << n_loci);
+        // Only draw for resistance when it's in play, so drug-free runs keep their random streams
+        if resistant_fraction > 0.0 && rng.gen::<f32>() < resistant_fraction {
+            Genotype(antigen | Self::RESISTANCE_BIT)
+        } else {
+            Genotype(antigen)
+        }
+    }
+
+    pub fn is_resistant(&self) -> bool {
+        self.0 & Self::RESISTANCE_BIT != 0
+    }
+
+    // Loci seen by the immune system; resistance doesn't change the antigenic type
+    pub fn antigen(&self) -> Genotype {
+        Genotype(self.0 & !Self::RESISTANCE_BIT)
+    }
+
+    pub fn label(&self, n_loci: u32) -> String {
+        let suffix = if self.is_resistant() { "R" } else { "" };
+        format!("{:0width$b}{}", self.antigen().0, suffix, width = n_loci as usize)
+    }
+
+    // Relative blood-stage duration; resistance carries a fitness cost
+    pub fn duration_fitness(&self, params: &Params) -> f32 {
+        if self.is_resistant() {
+            1.0 - params.resistance_cost_duration
+        } else {
+            1.0
+        }
+    }
+
+    // Relative infectiousness to vectors
+    pub fn transmission_fitness(&self, params: &Params) -> f32 {
+        if self.is_resistant() {
+            1.0 - params.resistance_cost_transmission
+        } else {
+            1.0
+        }
+    }
+
+    // Whether a clone escapes a drug (treatment, MDA or prophylaxis)
+    pub fn survives_drug(&self, params: &Params, rng: &mut impl Rng) -> bool {
+        self.is_resistant() && rng.gen::<f32>() < params.resistance_level
+    }
+}
+
+#[cfg(feature = "gui")]
 #[derive(Component)]
 struct TimeText;
 
+#[cfg(feature = "gui")]
+#[derive(Component)]
+struct MainCamera; // Camera rendering the hosts in the primary window
+
+// Events
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+enum SimulationEvent {
+    Infection,   // New inoculation spawned
+    Treatment(Regimen), // Host treated and started prophylaxis
+    Elimination, // Last inoculation in the population cleared
+}
+
+#[derive(Event)]
+struct ResetSimulation; // Restart from day 0, e.g. when a kiosk goes idle
+
 // Resources
-#[derive(Resource)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+enum Regimen {
+    #[default]
+    Treatment, // Clinical treatment after seeking care
+    Mda,       // Mass drug administration
+    Screening, // Positive in active case detection, given the treatment drug
+}
+
+impl Regimen {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Regimen::Treatment => "treatment",
+            Regimen::Mda => "mda",
+            Regimen::Screening => "screening",
+        }
+    }
+}
+
+// Curative and prophylactic effects of a drug, parameterized separately
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+struct Drug {
+    cure_liver: f32, // Probability of clearing each inoculation, by its state
+    cure_acute: f32,
+    cure_chronic: f32,
+    duration_prophylaxis: f32, // Days of protection against new inoculations after a dose
+}
+
+impl Default for Drug {
+    fn default() -> Self {
+        Self {
+            cure_liver: 1.0,
+            cure_acute: 1.0,
+            cure_chronic: 1.0,
+            duration_prophylaxis: 14.0,
+        }
+    }
+}
+
+impl Drug {
+    pub fn cures(&self, state: InfectionState, rng: &mut impl Rng) -> bool {
+        let prob = match state {
+            InfectionState::E => self.cure_liver,
+            InfectionState::A => self.cure_acute,
+            InfectionState::C => self.cure_chronic,
+        };
+        prob >= 1.0 || rng.gen::<f32>() < prob
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Resource))]
 struct Params {
     duration_liver: f32,
-    duration_prophylaxis: f32,
+    treatment_drug: Drug,
+    mda_drug: Drug,
     prob_acute: f32,
     prob_ac: f32,
     prob_treatment: f32,
-    duration_acute: Uniform<f32>,
-    duration_chronic: Uniform<f32>,
-    treatment_delay: Uniform<f32>,
+    prob_treatment_chronic: f32, // Chance a new chronic infection is detected, e.g. by incidental testing, and treated
+    duration_acute: DurationDist,
+    duration_chronic: DurationDist,
+    treatment_delay: DurationDist,
     incidence_rate: f32, // New infections per SimulationTime.day
+    exposure_dispersion: f32, // k of the negative binomial daily exposure per host; 0 for a common rate
+    n_loci: u32, // Number of biallelic loci in each inoculation's genotype
+    immunity_protection: f32, // Protection against a previously seen genotype
+    cross_protection: f32, // Fraction of that protection extended to unseen genotypes
+    boost_probability: f32, // Chance that an exposure blocked by prophylaxis or immunity still boosts antibodies
+    boost_size: f32, // Antibody titre added by a boost; every blood-stage infection boosts
+    antibody_half_life: f32, // Days for the antibody titre to halve
+    resistant_fraction: f32, // Fraction of new inoculations carrying drug resistance
+    resistance_level: f32, // Probability that a drug fails against a resistant clone
+    resistance_cost_duration: f32, // Fractional reduction in resistant clones' blood-stage duration
+    resistance_cost_transmission: f32, // Fractional reduction in resistant clones' infectiousness
+    acceptance_treatment: f32, // Probability a host accepts treatment (clinical or after screening)
+    acceptance_mda: f32, // Probability a host takes an MDA dose
+    acceptance_correlation: f32, // 1 = the same hosts always refuse, 0 = refusals are independent each time
+    comorbidity_odds_ratio: f32, // Odds ratio for acute disease per unit of a host's comorbidity score
+    male_fraction: f32, // Chance a new host is male
+    male_exposure: f32, // Vector-borne infection rate of males relative to females
+    male_care_seeking: f32, // Chance an acute male case seeks treatment, relative to a female one
+}
+
+impl Params {
+    pub fn drug(&self, regimen: Regimen) -> &Drug {
+        match regimen {
+            Regimen::Treatment | Regimen::Screening => &self.treatment_drug,
+            Regimen::Mda => &self.mda_drug,
+        }
+    }
 }
 
 impl Default for Params {
     fn default() -> Self {
         Self {
             duration_liver: 7.0,
-            duration_prophylaxis: 14.0,
+            treatment_drug: Drug::default(),
+            mda_drug: Drug::default(),
             prob_acute: 0.7,
             prob_ac: 0.2,
             prob_treatment: 0.4,
-            duration_acute: Uniform::new(10.0, 40.0),
-            duration_chronic: Uniform::new(100.0, 400.0),
-            treatment_delay: Uniform::new(0.0, 2.0),
+            prob_treatment_chronic: 0.0,
+            duration_acute: DurationDist::uniform(10.0, 40.0),
+            duration_chronic: DurationDist::uniform(100.0, 400.0),
+            treatment_delay: DurationDist::uniform(0.0, 2.0),
             incidence_rate: 0.1,
+            exposure_dispersion: 0.0,
+            n_loci: 4,
+            immunity_protection: 0.8,
+            cross_protection: 0.25,
+            boost_probability: 0.0,
+            boost_size: 1.0,
+            antibody_half_life: 180.0,
+            resistant_fraction: 0.0,
+            resistance_level: 1.0,
+            resistance_cost_duration: 0.0,
+            resistance_cost_transmission: 0.0,
+            acceptance_treatment: 1.0,
+            acceptance_mda: 1.0,
+            acceptance_correlation: 0.8,
+            comorbidity_odds_ratio: 2.0,
+            male_fraction: 0.5,
+            male_exposure: 1.0,
+            male_care_seeking: 1.0,
+        }
+    }
+}
+
+// Maps simulation days onto calendar dates when a start date is configured
+#[derive(Resource, Default)]
+struct Calendar {
+    start_date: Option<NaiveDate>,
+}
+
+impl Calendar {
+    pub fn date(&self, day: u32) -> Option<NaiveDate> {
+        self.start_date
+            .and_then(|start| start.checked_add_days(chrono::Days::new(day as u64)))
+    }
+
+    // 0 for Monday; without a start date, day 0 is a Monday
+    pub fn weekday(&self, day: u32) -> usize {
+        match self.date(day) {
+            Some(date) => date.weekday().num_days_from_monday() as usize,
+            None => day as usize % 7,
+        }
+    }
+
+    pub fn label(&self, day: u32) -> String {
+        match self.date(day) {
+            Some(date) => format!("{}, day {}", date.format("%-d %B %Y"), day),
+            None => format!("Day {}", day),
         }
     }
 }
 
 #[derive(Resource)]
-struct SimulationTime {
-    day: u32,
-    timer: Timer,
+struct OutputDir(PathBuf);
+
+// Single seeded source of randomness for the simulation, so runs can be reported and replayed
+#[derive(Resource)]
+struct SimRng {
+    seed: u64,
+    rng: StdRng,
+    audit_day: u32,
+    daily_draws: [u64; rng::Subsystem::ALL.len()], // Per subsystem on audit_day
+    total_draws: [u64; rng::Subsystem::ALL.len()],
 }
 
-impl Default for SimulationTime {
-    fn default() -> Self {
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
         Self {
-            day: 0,
-            timer: Timer::from_seconds(1.0, TimerMode::Repeating), // One day per second
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            audit_day: 0,
+            daily_draws: [0; rng::Subsystem::ALL.len()],
+            total_draws: [0; rng::Subsystem::ALL.len()],
+        }
+    }
+
+    // Draws are counted against the subsystem making them
+    pub fn stream(&mut self, subsystem: rng::Subsystem) -> rng::CountingRng<'_> {
+        let index = subsystem.index();
+        rng::CountingRng {
+            rng: &mut self.rng,
+            daily: &mut self.daily_draws[index],
+            total: &mut self.total_draws[index],
         }
     }
 }
 
 #[derive(Resource)]
-struct SimulationSpeed {
-    multiplier: f32, // 1.0 by default
+struct RunConfig {
+    end_day: Option<u32>, // Stop and write the run report on this day
+    host_count: u32,
+    comorbidity: Option<scenario::ComorbidityConfig>,
 }
 
-impl Default for SimulationSpeed {
+impl Default for RunConfig {
     fn default() -> Self {
-        Self { multiplier: 1.0 }
+        Self {
+            end_day: None,
+            host_count: 10,
+            comorbidity: None,
+        }
+    }
+}
+
+impl RunConfig {
+    // Fraction of the way to the end day, with days done and the time left at the recent rate
+    fn progress(&self, day: u32, throughput: &clock::Throughput) -> Option<(f32, String)> {
+        let end_day = self.end_day?;
+        let fraction = (day as f32 / end_day.max(1) as f32).min(1.0);
+        let eta = throughput.eta_seconds(end_day).map_or("--".to_owned(), clock::format_duration);
+        Some((fraction, format!("{} of {} days ({:.0}%), ETA {}", day.min(end_day), end_day, fraction * 100.0, eta)))
     }
 }
 
 // Systems
-fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    query: Query<&Window, With<PrimaryWindow>>, // Query for the primary window
-    params: Res<Params>,
-    sim_time: Res<SimulationTime>,
-) {
-    let window = query.single(); // Get the primary window
+// A host for a fresh cohort, with the liver-stage inoculation every host starts with
+fn new_host(
+    sex: Sex,
+    params: &Params,
+    comorbidity: Option<&scenario::ComorbidityConfig>,
+    location_comorbidity: Option<f32>,
+    day: u32,
+    sim_rng: &mut SimRng,
+) -> (Host, Inoculation) {
+    let mut host = Host {
+        sex,
+        propensity: sim_rng.stream(rng::Subsystem::Spawn).gen(),
+        birth_day: {
+            let u: f32 = sim_rng.stream(rng::Subsystem::Spawn).gen();
+            let age = (-MEAN_AGE_YEARS * (1.0 - u).portable_ln()).min(MAX_AGE_YEARS);
+            day as f32 - 365.0 * age
+        },
+        ..default()
+    };
+    // Only drawn with a [comorbidity] section, so other runs keep their random streams
+    if let Some(config) = comorbidity {
+        host.comorbidity = location_comorbidity.unwrap_or_else(|| {
+            let u: f32 = sim_rng.stream(rng::Subsystem::Spawn).gen();
+            -config.mean_score * (1.0 - u).portable_ln()
+        });
+    }
+    let inoculation = Inoculation {
+        state: InfectionState::E,
+        start_day: day,
+        delay_days: params.duration_liver,
+        genotype: Genotype::random(params.n_loci, params.resistant_fraction, &mut sim_rng.stream(rng::Subsystem::Spawn)),
+        infected_day: day,
+    };
+    (host, inoculation)
+}
 
-    let bottom_y = -window.height() / 2.0 + 40.0; // Adjusted to position hosts comfortably above the bottom edge
+// Spawns the host population, each with one liver-stage inoculation, or as saved in a warm-start snapshot
+#[cfg(feature = "gui")]
+#[allow(clippy::too_many_arguments)]
+fn spawn_hosts(
+    commands: &mut Commands,
+    window: &Window,
+    run_config: &RunConfig,
+    locations: Option<&gis::HostLocations>,
+    snapshot: Option<&snapshot::Snapshot>,
+    params: &Params,
+    sim_time: &SimulationTime,
+    sim_rng: &mut SimRng,
+    ids: &mut Ids,
+) {
+    let bottom_y = layout::bottom_y(window);
+    let host_count = snapshot.map_or(run_config.host_count, |snapshot| snapshot.hosts.len() as u32);
 
-    let host_count = 10;
     let spacing = window.width() / (host_count as f32 + 1.0) / 1.0; // Dynamically calculate spacing based on window width
+    // Sexes come from their own seeded stream, so adding them left every other draw of a run unchanged
+    let mut sex_rng = StdRng::seed_from_u64(sim_rng.seed ^ 0x5e8);
 
     for i in 0..host_count {
         let x = (i as f32 + 1.0) * spacing - window.width() / 2.0; // Distribute hosts evenly across the screen
+        let position = locations.map_or(Vec2::new(x, bottom_y), |locations| locations.world_position(i as usize, window));
 
-        // Spawn Host with Inoculation
-        commands
-            .spawn((
-                Host {
-                    ..default()
-                },
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: Color::GRAY, // Default to susceptible
-                        custom_size: Some(Vec2::new(50.0, 5.0)),
-                        ..default()
-                    },
-                    transform: Transform::from_xyz(x, bottom_y, 0.0),
+        let sex = if sex_rng.gen::<f32>() < params.male_fraction { Sex::Male } else { Sex::Female };
+        // Restored hosts keep their saved ages, propensities and genotypes rather than drawing new ones
+        let (mut host, actions, inoculations) = match snapshot {
+            Some(snapshot) => snapshot.hosts[i as usize].restore(sim_time.day),
+            None => {
+                let location_comorbidity = locations.and_then(|locations| locations.comorbidity(i as usize));
+                let (host, inoculation) = new_host(sex, params, run_config.comorbidity.as_ref(), location_comorbidity, sim_time.day, sim_rng);
+                (host, ScheduledActions::default(), vec![inoculation])
+            }
+        };
+        // Snapshots from before hosts had a sex get one drawn
+        if snapshot.is_some_and(|snapshot| snapshot.hosts[i as usize].sex.is_none()) {
+            host.sex = sex;
+        }
+
+        let mut host = commands.spawn((
+            host,
+            ids.host(),
+            actions,
+            treatment::TreatmentHistory::default(),
+            cleared::InfectionHistory::default(),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::GRAY, // Default to susceptible
+                    custom_size: Some(Vec2::new(50.0, 5.0)),
                     ..default()
                 },
-            ))
-            .with_children(|parent| {
+                transform: Transform::from_xyz(position.x, position.y, 0.0),
+                ..default()
+            },
+        ));
+        if locations.is_some() {
+            host.insert(gis::LocationIndex(i as usize));
+        }
+        host.with_children(|parent| {
+            for inoculation in inoculations {
                 parent.spawn((
-                    Inoculation {
-                        state: InfectionState::E,
-                        start_day: sim_time.day,
-                        delay_days: params.duration_liver,
-                    },
+                    inoculation,
+                    ids.inoculation(),
                     SpriteBundle {
                         sprite: Sprite {
                             color: Color::BLUE,
@@ -180,21 +706,54 @@ fn setup(
                         ..default()
                     },
                 ));
-            });
+            }
+        });
     }
+}
+
+#[cfg(feature = "gui")]
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<&Window, With<PrimaryWindow>>, // Query for the primary window
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut ids: ResMut<Ids>,
+    calendar: Res<Calendar>,
+    run_config: Res<RunConfig>,
+    locations: Option<Res<gis::HostLocations>>,
+    warm_start: Res<snapshot::WarmStart>,
+) {
+    let window = query.single(); // Get the primary window
+
+    let snapshot = warm_start.0.as_ref();
+    spawn_hosts(&mut commands, window, &run_config, locations.as_deref(), snapshot, &params, &sim_time, &mut sim_rng, &mut ids);
 
     // Add UI text
     commands.spawn((
         TimeText,
         TextBundle {
-            text: Text::from_section(
-                "t = 0",
-                TextStyle {
-                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: 30.0,
-                    color: Color::WHITE,
-                },
-            ),
+            text: Text::from_sections([
+                TextSection::new(
+                    calendar.label(sim_time.day),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                // Wall time, speed and progress, filled in by update_time_text
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 18.0,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                    },
+                ),
+            ]),
             style: Style {
                 position_type: PositionType::Absolute,
                 top: Val::Px(10.0),
@@ -206,35 +765,58 @@ fn setup(
     ));
 
     // Add a default 2D camera
-    commands.spawn(Camera2dBundle {
-        ..default()
-    });
+    commands.spawn((
+        MainCamera,
+        Camera2dBundle {
+            ..default()
+        },
+    ));
+}
+
+// Chronic infections rarely cause symptoms, so are only treated when a care pathway picks them up
+fn chronic_detected(params: &Params, rng: &mut impl Rng) -> bool {
+    params.prob_treatment_chronic > 0.0 && rng.gen::<f32>() < params.prob_treatment_chronic
 }
 
 fn process_inoculations(
     mut commands: Commands,
     mut inoc_query: Query<(Entity, &mut Inoculation, &Parent)>,
-    mut host_query: Query<(Entity, &mut Host, Option<&Children>)>,
+    mut host_query: Query<(&mut Host, &mut ScheduledActions)>,
     params: Res<Params>,
     sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut blocked_events: EventWriter<blocked::ExposureBlocked>,
 ) {
+    let _span = info_span!("process_inoculations", day = sim_time.day).entered();
+    let rng = &mut sim_rng.stream(rng::Subsystem::Progression);
+
     // Iterate over all inoculations and update their state based on elapsed time and host conditions
     for (entity, mut inoc, parent) in inoc_query.iter_mut() {
         let days_elapsed = sim_time.day as f32 - inoc.start_day as f32;
 
         match inoc.state {
             InfectionState::E if days_elapsed >= inoc.delay_days => {
-                // If the host is under prophylaxis, clear the inoculation
-                if let Ok((_, host, _)) = host_query.get(parent.get()) {
-                    if host.on_prophylaxis {
-                        commands.entity(parent.get()).remove_children(&[entity]);
-                        commands.entity(entity).despawn();
+                // If the host is under prophylaxis (and the clone is sensitive) or immune to this strain, clear the inoculation
+                if let Ok((host, _)) = host_query.get(parent.get()) {
+                    let by_prophylaxis = host.on_prophylaxis && !inoc.genotype.survives_drug(&params, rng);
+                    if by_prophylaxis || rng.gen::<f32>() < host.protection_against(inoc.genotype, &params) {
+                        blocked_events.send(blocked::ExposureBlocked {
+                            host: parent.get(),
+                            by_prophylaxis,
+                        });
+                        // Only drawn when boosting is on, so runs without it keep their random streams
+                        if params.boost_probability > 0.0 && rng.gen::<f32>() < params.boost_probability {
+                            if let Ok((mut host, _)) = host_query.get_mut(parent.get()) {
+                                host.boost_antibodies(sim_time.day, &params);
+                            }
+                        }
+                        cleared::clear_inoculation(&mut commands, parent.get(), entity, &inoc, sim_time.day, cleared::ClearCause::Blocked);
                         continue;
                     }
                 }
 
-                let mut rng = rand::thread_rng();
-                let goes_acute = rand::random::<f32>() < params.prob_acute;
+                let prob_acute = host_query.get(parent.get()).map_or(params.prob_acute, |(host, _)| host.prob_acute(&params));
+                let goes_acute = rng.gen::<f32>() < prob_acute;
 
                 // Transition to acute or chronic state
                 inoc.state = if goes_acute {
@@ -245,39 +827,52 @@ fn process_inoculations(
 
                 inoc.start_day = sim_time.day;
                 inoc.delay_days = if goes_acute {
-                    params.duration_acute.sample(&mut rng)
+                    params.duration_acute.sample(rng)
                 } else {
-                    params.duration_chronic.sample(&mut rng)
-                };
+                    params.duration_chronic.sample(rng)
+                } * inoc.genotype.duration_fitness(&params);
 
-                // If acute and treatment is likely, schedule treatment for the host
-                if goes_acute && rand::random::<f32>() < params.prob_treatment {
-                    if let Ok((_, mut host, _)) = host_query.get_mut(parent.get()) {
-                        let new_treat_request_day = sim_time.day + params.treatment_delay.sample(&mut rng) as u32;
-                        if host.treat_request_day.is_none() || new_treat_request_day < host.treat_request_day.unwrap() {
-                            host.treat_request_day = Some(new_treat_request_day);
-                        }
+                // Blood-stage antigens build strain-specific memory
+                if let Ok((mut host, _)) = host_query.get_mut(parent.get()) {
+                    host.immune_memory.insert(inoc.genotype.antigen());
+                    host.boost_antibodies(sim_time.day, &params);
+                }
+
+                // Acute cases seek treatment with prob_treatment; chronic ones only through a care pathway
+                let detected = if goes_acute {
+                    let prob_treatment = host_query.get(parent.get()).map_or(params.prob_treatment, |(host, _)| host.prob_treatment(&params));
+                    rng.gen::<f32>() < prob_treatment
+                } else {
+                    chronic_detected(&params, rng)
+                };
+                if detected {
+                    if let Ok((_, mut scheduled)) = host_query.get_mut(parent.get()) {
+                        scheduled.request_treatment(sim_time.day + params.treatment_delay.sample(rng) as u32, Regimen::Treatment);
                     }
                 }
             }
 
             InfectionState::A if days_elapsed >= inoc.delay_days => {
                 // Transition from acute to chronic or clear the inoculation
-                let goes_chronic = rand::random::<f32>() < params.prob_ac;
+                let goes_chronic = rng.gen::<f32>() < params.prob_ac;
                 if goes_chronic {
                     inoc.state = InfectionState::C;
                     inoc.start_day = sim_time.day;
-                    inoc.delay_days = params.duration_chronic.sample(&mut rand::thread_rng());
+                    inoc.delay_days = params.duration_chronic.sample(rng) * inoc.genotype.duration_fitness(&params);
+
+                    if chronic_detected(&params, rng) {
+                        if let Ok((_, mut scheduled)) = host_query.get_mut(parent.get()) {
+                            scheduled.request_treatment(sim_time.day + params.treatment_delay.sample(rng) as u32, Regimen::Treatment);
+                        }
+                    }
                 } else {
-                    commands.entity(parent.get()).remove_children(&[entity]);
-                    commands.entity(entity).despawn();
+                    cleared::clear_inoculation(&mut commands, parent.get(), entity, &inoc, sim_time.day, cleared::ClearCause::Resolved);
                 }
             }
 
             InfectionState::C if days_elapsed >= inoc.delay_days => {
                 // Clear chronic inoculations after their duration
-                commands.entity(parent.get()).remove_children(&[entity]);
-                commands.entity(entity).despawn();
+                cleared::clear_inoculation(&mut commands, parent.get(), entity, &inoc, sim_time.day, cleared::ClearCause::Resolved);
             }
 
             _ => {}
@@ -285,132 +880,378 @@ fn process_inoculations(
     }
 }
 
-fn update_simulation_time(
-    time: Res<Time>,
-    speed: Res<SimulationSpeed>,
-    mut sim_time: ResMut<SimulationTime>,
+#[cfg(feature = "gui")]
+fn update_time_text(
     mut text_query: Query<&mut Text, With<TimeText>>,
+    calendar: Res<Calendar>,
+    sim_time: Res<SimulationTime>,
+    speed: Res<SimulationSpeed>,
+    run_config: Res<RunConfig>,
+    throughput: Res<clock::Throughput>,
 ) {
-    sim_time.timer.tick(time.delta().mul_f32(speed.multiplier));
+    let rate = if speed.paused { "paused".to_owned() } else { format!("{:.1} days/s", throughput.days_per_second()) };
+    let mut status = format!("\n{} elapsed, {}", clock::format_duration(throughput.elapsed_seconds()), rate);
+    if let Some((_, progress)) = run_config.progress(sim_time.day, &throughput) {
+        status.push_str(&format!(", {}", progress));
+    }
 
-    if sim_time.timer.just_finished() {
-        sim_time.day += 1;
-        for mut text in text_query.iter_mut() {
-            text.sections[0].value = format!("t = {}", sim_time.day);
+    let label = calendar.label(sim_time.day);
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+        if text.sections[1].value != status {
+            text.sections[1].value = status.clone();
         }
     }
 }
 
+// Logs every tenth of the way to the end day, so a long run shows its progress in the terminal
+fn log_progress(sim_time: Res<SimulationTime>, run_config: Res<RunConfig>, throughput: Res<clock::Throughput>, mut logged: Local<Option<u32>>) {
+    let Some((fraction, progress)) = run_config.progress(sim_time.day, &throughput) else {
+        return;
+    };
+    let tenths = (fraction * 10.0) as u32;
+    // A reset starts the count again
+    if logged.is_some_and(|logged| tenths < logged) {
+        *logged = None;
+    }
+    if logged.is_some_and(|logged| tenths == logged) {
+        return;
+    }
+    if logged.is_some() {
+        info!("{} at {:.1} days/s", progress, throughput.days_per_second());
+    }
+    *logged = Some(tenths);
+}
+
+// Carries out each host's scheduled actions as they fall due
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn process_hosts(
     mut commands: Commands,
-    mut host_query: Query<(Entity, &mut Host, Option<&Children>)>,
+    mut host_query: Query<(Entity, &mut Host, &mut ScheduledActions, &mut treatment::TreatmentHistory, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
     params: Res<Params>,
+    policy: Res<treatment::TreatmentPolicy>,
+    supply: Res<supply::DrugSupply>,
+    mut stock: ResMut<supply::DrugStock>,
     sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut events: EventWriter<SimulationEvent>,
+    mut offers: EventWriter<coverage::DoseOffered>,
+    mut event_log: ResMut<event_log::EventLog>,
 ) {
-    // Iterate over all hosts and process treatment requests and prophylaxis duration
-    for (host_entity, mut host, children) in host_query.iter_mut() {
-        if let Some(treat_request_day) = host.treat_request_day {
-            if sim_time.day >= treat_request_day {
-                // Clear all inoculations for this host if there are children
-                if let Some(children) = children {
-                    for &child in children.iter() {
-                        commands.entity(host_entity).remove_children(&[child]);
-                        commands.entity(child).despawn();
+    let _span = info_span!("process_hosts", day = sim_time.day).entered();
+
+    for (host_entity, mut host, mut scheduled, mut history, children) in host_query.iter_mut() {
+        while let Some(action) = scheduled.next_due(sim_time.day) {
+            match action {
+                HostAction::Treat(regimen) => {
+                    let dose = coverage::Dose::Drug(regimen);
+                    let mut offered = |outcome| offers.send(coverage::DoseOffered { host: host_entity, dose, outcome });
+                    if !policy.course_allowed(&history) {
+                        offered(coverage::OfferOutcome::Withheld);
+                        continue;
                     }
-                }
 
-                // Start prophylaxis for the host
-                host.on_prophylaxis = true;
-                host.prophylaxis_end_day = Some(sim_time.day + params.duration_prophylaxis as u32);
-                host.treat_request_day = None;
-            }
-        }
+                    // The host may refuse the offered treatment
+                    let accepted = host.accepts(regimen, &params, &mut sim_rng.stream(rng::Subsystem::Treatment));
+                    if !accepted {
+                        host.last_offer = Some((sim_time.day, regimen, false));
+                        offered(coverage::OfferOutcome::Refused);
+                        continue;
+                    }
+                    // A course the stock can't cover isn't an offer taken up, so it's only recorded once delivered
+                    if let Some(config) = &supply.0 {
+                        if !stock.take(config, sim_time.day, &mut event_log) {
+                            if config.on_stock_out == scenario::StockOutRule::Delay {
+                                scheduled.request_treatment(sim_time.day + config.retry_days.max(1), regimen);
+                            }
+                            offered(coverage::OfferOutcome::StockOut);
+                            continue;
+                        }
+                    }
+                    host.last_offer = Some((sim_time.day, regimen, true));
+                    offered(coverage::OfferOutcome::Delivered);
+                    let (course_drug, drug) = policy.drug(regimen, &history, &params);
 
-        // End prophylaxis if the duration has passed
-        if let Some(prophylaxis_end_day) = host.prophylaxis_end_day {
-            if sim_time.day >= prophylaxis_end_day {
-                host.on_prophylaxis = false;
-                host.prophylaxis_end_day = None;
+                    // Clear the host's inoculations that the drug cures; resistance is to the first-line drugs only
+                    let mut failed = false;
+                    if let Some(children) = children {
+                        for (child, inoc) in children.iter().filter_map(|&child| Some((child, inoc_query.get(child).ok()?))) {
+                            let rng = &mut sim_rng.stream(rng::Subsystem::Treatment);
+                            let resisted = course_drug != treatment::CourseDrug::SecondLine && inoc.genotype.survives_drug(&params, rng);
+                            if resisted || !drug.cures(inoc.state, rng) {
+                                failed = true;
+                                continue;
+                            }
+                            cleared::clear_inoculation(&mut commands, host_entity, child, inoc, sim_time.day, cleared::ClearCause::Treated);
+                        }
+                    }
+                    history.record(sim_time.day, course_drug, failed);
+
+                    // Start (or extend) prophylaxis for the host
+                    host.on_prophylaxis = true;
+                    scheduled.cancel(|action| *action == HostAction::EndProphylaxis);
+                    scheduled.schedule(sim_time.day + drug.duration_prophylaxis as u32, HostAction::EndProphylaxis);
+                    events.send(SimulationEvent::Treatment(regimen));
+                }
+                HostAction::EndProphylaxis => host.on_prophylaxis = false,
             }
         }
     }
 }
 
+// Once a day, gives each host a gamma-distributed exposure multiplier with mean 1 and shape k, so the daily number of
+// infectious bites is negative binomial rather than Poisson with a common rate
+fn draw_exposure(
+    mut host_query: Query<&mut Host>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut last_day: Local<Option<u32>>,
+) {
+    if *last_day == Some(sim_time.day) {
+        return;
+    }
+    *last_day = Some(sim_time.day);
+
+    let k = params.exposure_dispersion;
+    let gamma = (k > 0.0).then(|| rand_distr::Gamma::new(k, 1.0 / k).ok()).flatten();
+    for mut host in host_query.iter_mut() {
+        let rng = &mut sim_rng.stream(rng::Subsystem::Infection);
+        host.exposure = match gamma {
+            Some(_) if portable_math::enabled() => Some(portable_math::gamma(rng, k as f64, 1.0 / k as f64) as f32),
+            gamma => gamma.map(|gamma| gamma.sample(rng)),
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn spawn_infections(
     mut commands: Commands,
-    mut host_query: Query<(Entity, Option<&Children>), With<Host>>, // Wrap Children in Option<>
+    mut host_query: Query<
+        (
+            Entity,
+            &Host,
+            Option<&Children>,
+            Option<&environment::Suitability>,
+            Option<&venues::VenueExposure>,
+            Option<&age_mixing::AgeMixingExposure>,
+            Option<&occupations::Occupation>,
+            Option<&trial::Vaccinated>,
+        ),
+    >, // Wrap Children in Option<>
     params: Res<Params>,
     sim_time: Res<SimulationTime>,
     time: Res<Time>,
     speed: Res<SimulationSpeed>,
+    mut sim_rng: ResMut<SimRng>,
+    mut events: EventWriter<SimulationEvent>,
+    vectors: Res<vectors::Vectors>,
+    vector_population: Res<vectors::VectorPopulation>,
+    mut venue_infections: EventWriter<venues::VenueInfection>,
+    mut mixing_infections: EventWriter<age_mixing::MixingInfection>,
+    mut ids: ResMut<Ids>,
+    inoc_query: Query<(), With<Inoculation>>,
+    budget: Res<budget::EntityBudget>,
+    mut refusals: EventWriter<budget::InoculationRefused>,
 ) {
-    for (host_entity, children) in host_query.iter_mut() {
-        if rand::random::<f32>() < params.incidence_rate * time.delta_seconds() * speed.multiplier {
-            // Calculate position for the new inoculation
-            let y_offset = children.map_or(0.0, |c| c.len() as f32 * 40.0); // Handle optional children
+    let _span = info_span!("spawn_infections", day = sim_time.day).entered();
+    let incidence_rate = vectors.incidence_rate(&params, &vector_population);
+    let mut total_inoculations = inoc_query.iter().count();
+    for (host_entity, host, children, suitability, venue_exposure, mixing_exposure, occupation, vaccinated) in host_query.iter_mut() {
+        let days_elapsed = time.delta_seconds() * speed.effective() / sim_time.timer.duration().as_secs_f32();
+        let venue_rate = venue_exposure.map_or(0.0, |exposure| exposure.0);
+        let mixing_rate = mixing_exposure.map_or(0.0, |exposure| exposure.0);
+        let work = occupation.map_or(1.0, |occupation| occupation.exposure);
+        let vector_rate = incidence_rate * suitability.map_or(1.0, |suitability| suitability.0) * host.sex_exposure(&params) * work;
+        let local_rate = vector_rate + venue_rate + mixing_rate;
+        let protection = 1.0 - vaccinated.map_or(0.0, |vaccinated| vaccinated.0);
+        if sim_rng.stream(rng::Subsystem::Infection).gen::<f32>() < local_rate * host.exposure.unwrap_or(1.0) * protection * days_elapsed {
+            if !budget.allows(children.map_or(0, |c| c.len()), total_inoculations) {
+                refusals.send(budget::InoculationRefused);
+                continue;
+            }
+            total_inoculations += 1;
+            if venue_rate > 0.0 {
+                venue_infections.send(venues::VenueInfection {
+                    host: host_entity,
+                    venue_share: venue_rate / local_rate,
+                });
+            }
+            if mixing_rate > 0.0 {
+                mixing_infections.send(age_mixing::MixingInfection {
+                    host: host_entity,
+                    mixing_share: mixing_rate / (local_rate - venue_rate),
+                });
+            }
 
-            // Spawn a new Inoculation as a child of the Host
-            commands.entity(host_entity).with_children(|parent| {
-                parent.spawn((
-                    Inoculation {
-                        state: InfectionState::E,
-                        start_day: sim_time.day,
-                        delay_days: params.duration_liver,
-                    },
-                    SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::rgba(0.0, 0.0, 1.0, 0.0), // Transparent blue on spawn
-                            custom_size: Some(Vec2::splat(30.0)),
-                            ..default()
-                        },
-                        transform: Transform::from_xyz(0.0, y_offset, 0.1),
-                        ..default()
-                    },
-                ));
-            });
+            let genotype = Genotype::random(params.n_loci, params.resistant_fraction, &mut sim_rng.stream(rng::Subsystem::Infection));
+            spawn_inoculation(&mut commands, host_entity, children, genotype, &params, sim_time.day, &mut ids);
+            events.send(SimulationEvent::Infection);
         }
     }
 }
 
-fn simulation_controls_ui(mut contexts: EguiContexts, mut params: ResMut<Params>, mut speed: ResMut<SimulationSpeed>) {
+// A new liver-stage inoculation in the host, stacked above the ones it already carries
+fn spawn_inoculation(commands: &mut Commands, host: Entity, children: Option<&Children>, genotype: Genotype, params: &Params, day: u32, ids: &mut Ids) {
+    // Calculate position for the new inoculation
+    let y_offset = children.map_or(0.0, |c| c.len() as f32 * 40.0); // Handle optional children
+
+    // Spawn a new Inoculation as a child of the Host
+    commands.entity(host).with_children(|parent| {
+        parent.spawn((
+            Inoculation {
+                state: InfectionState::E,
+                start_day: day,
+                delay_days: params.duration_liver,
+                genotype,
+                infected_day: day,
+            },
+            ids.inoculation(),
+            inoculation_sprite(y_offset),
+        ));
+    });
+}
+
+#[cfg(feature = "gui")]
+fn inoculation_sprite(y_offset: f32) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.0, 0.0, 1.0, 0.0), // Transparent blue on spawn
+            custom_size: Some(Vec2::splat(30.0)),
+            ..default()
+        },
+        transform: Transform::from_xyz(0.0, y_offset, 0.1),
+        ..default()
+    }
+}
+
+// Nothing is drawn without a window, but inoculations keep their place above the host
+#[cfg(not(feature = "gui"))]
+fn inoculation_sprite(y_offset: f32) -> TransformBundle {
+    TransformBundle::from_transform(Transform::from_xyz(0.0, y_offset, 0.1))
+}
+
+// Restarts the run from day 0 with a fresh host population and the current params
+#[cfg(feature = "gui")]
+#[allow(clippy::too_many_arguments)]
+fn reset_simulation(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetSimulation>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    host_query: Query<Entity, With<Host>>,
+    params: Res<Params>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    mut ids: ResMut<Ids>,
+    run_config: Res<RunConfig>,
+    locations: Option<Res<gis::HostLocations>>,
+    warm_start: Res<snapshot::WarmStart>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+
+    for entity in host_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *sim_time = SimulationTime::new(sim_time.timer.duration().as_secs_f32());
+    *ids = Ids::default();
+
+    if let Ok(window) = window_query.get_single() {
+        let snapshot = warm_start.0.as_ref();
+        spawn_hosts(&mut commands, window, &run_config, locations.as_deref(), snapshot, &params, &sim_time, &mut sim_rng, &mut ids);
+    }
+}
+
+// Restores a run-scoped resource to its initial value on reset
+fn reset_resource<R: Resource + Default>(mut reset_events: EventReader<ResetSimulation>, mut resource: ResMut<R>) {
+    if reset_events.read().count() > 0 {
+        *resource = R::default();
+    }
+}
+
+fn detect_elimination(
+    inoc_query: Query<(), With<Inoculation>>,
+    mut had_infections: Local<bool>,
+    mut events: EventWriter<SimulationEvent>,
+) {
+    if !inoc_query.is_empty() {
+        *had_infections = true;
+    } else if *had_infections {
+        *had_infections = false;
+        events.send(SimulationEvent::Elimination);
+    }
+}
+
+#[cfg(feature = "gui")]
+#[allow(clippy::too_many_arguments)]
+fn simulation_controls_ui(
+    mut contexts: EguiContexts,
+    mut params: ResMut<Params>,
+    mut history: ResMut<undo::ParamHistory>,
+    sim_time: Res<SimulationTime>,
+    mut event_log: ResMut<event_log::EventLog>,
+    mut speed: ResMut<SimulationSpeed>,
+    reservoir: Res<infectiousness::InfectiousReservoir>,
+    mut layout: ResMut<layout::HostLayout>,
+    mut sparklines: ResMut<sparklines::SparklinePanel>,
+    run_config: Res<RunConfig>,
+    throughput: Res<clock::Throughput>,
+    #[cfg(feature = "audio")] mut audio_cues: ResMut<audio::AudioCues>,
+    kiosk: Option<Res<kiosk::Kiosk>>,
+) {
     egui::Window::new("Simulation Controls")
         .default_pos(egui::pos2(10.0, 50.0))
         .show(contexts.ctx_mut(), |ui| {
+            // Pausing would leave an unattended kiosk frozen until it goes idle
+            if kiosk.is_none() {
+                ui.checkbox(&mut speed.paused, "Paused");
+            }
+            if let Some((fraction, progress)) = run_config.progress(sim_time.day, &throughput) {
+                ui.add(egui::ProgressBar::new(fraction).text(progress));
+            }
+
             ui.label("Simulation Speed");
+            ui.horizontal(|ui| {
+                for (label, multiplier) in SimulationSpeed::PRESETS {
+                    if ui.selectable_label(speed.multiplier == multiplier, label).clicked() {
+                        speed.multiplier = multiplier;
+                    }
+                }
+            });
 
             let mut param_value = speed.multiplier;
-            let response = ui.add(egui::Slider::new(&mut param_value, 0.5..=5.0).text("Speed Multiplier"));
+            let (min, max) = SimulationSpeed::RANGE;
+            let response = ui.add(egui::Slider::new(&mut param_value, min..=max).text("Speed Multiplier"));
 
             if response.changed() {
                 speed.multiplier = param_value;
             }
 
-            ui.label("Incidence Rate");
-
-            let mut param_value = params.incidence_rate;
-            let response = ui.add(egui::Slider::new(&mut param_value, 0.0..=0.2).text("Incidence Rate"));
-
-            if response.changed() {
-                params.incidence_rate = param_value;
+            for (index, group) in param_docs::groups().into_iter().enumerate() {
+                egui::CollapsingHeader::new(group).default_open(index == 0).show(ui, |ui| {
+                    for info in param_docs::PARAMS.iter().filter(|info| info.group == group) {
+                        history.slider(ui, &mut params, info);
+                    }
+                });
             }
+            history.controls_ui(ui, &mut params, sim_time.day, &mut event_log);
 
-            ui.label("Prophylaxis Duration");
-
-            let mut param_value = params.duration_prophylaxis;
-            let response = ui.add(egui::Slider::new(&mut param_value, 1.0..=30.0).text("Prophylaxis Duration"));
+            ui.separator();
+            layout.controls_ui(ui);
+            ui.checkbox(&mut sparklines.shown, "Group sparklines");
 
-            if response.changed() {
-                params.duration_prophylaxis = param_value;
+            #[cfg(feature = "audio")]
+            {
+                ui.separator();
+                audio_cues.controls_ui(ui);
             }
 
-            ui.label("Treatment Probability");
-
-            let mut param_value = params.prob_treatment;
-            let response = ui.add(egui::Slider::new(&mut param_value, 0.0..=1.0).text("Treatment Probability"));
-
-            if response.changed() {
-                params.prob_treatment = param_value;
-            }
+            ui.separator();
+            ui.label(format!("Mean Host Infectiousness: {:.3}", reservoir.mean));
         });
 }
 
@@ -427,48 +1268,486 @@ fn update_inoculation_positions(
     }
 }
 
-fn update_inoculation_colors(mut inoc_query: Query<(&Inoculation, &mut Sprite)>) {
+const MAX_AGE_SHADING: f32 = 0.5; // Darkening of an inoculation at the end of its state
+
+// State color, darkening through the state's drawn duration so transitions that are due soon stand out
+#[cfg(feature = "gui")]
+fn update_inoculation_colors(mut inoc_query: Query<(&Inoculation, &mut Sprite)>, sim_time: Res<SimulationTime>) {
+    let now = sim_time.day as f32 + sim_time.timer.fraction();
     for (inoc, mut sprite) in inoc_query.iter_mut() {
-        sprite.color = match inoc.state {
+        let [r, g, b, _] = match inoc.state {
             InfectionState::E => Color::BLUE,
             InfectionState::A => Color::RED,
             InfectionState::C => Color::ORANGE,
-        };
+        }
+        .as_rgba_f32();
+        let progress = if inoc.delay_days > 0.0 { ((now - inoc.start_day as f32) / inoc.delay_days).clamp(0.0, 1.0) } else { 1.0 };
+        let shade = 1.0 - MAX_AGE_SHADING * progress;
+        sprite.color = Color::rgb(r * shade, g * shade, b * shade);
     }
 }
 
+#[cfg(feature = "gui")]
 fn update_host_sprites(
     mut host_query: Query<(&Host, Option<&Children>, &mut Sprite)>, // Made host_query mutable
     inoc_query: Query<&Inoculation>,
 ) {
     for (host, children, mut sprite) in host_query.iter_mut() {
-        let state = host.state(children, &inoc_query);
-        sprite.color = match state {
-            HostState::P => Color::GREEN, // Prophylaxis
-            HostState::A => Color::RED,   // Acute
-            HostState::C => Color::ORANGE, // Chronic
-            HostState::E => Color::BLUE,  // Exposed
-            HostState::S => Color::GRAY,  // Susceptible
-        };
+        sprite.color = host.state(children, &inoc_query).color();
     }
 }
 
+// Command-line options
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Scenario file (TOML) with parameter overrides, presets and kiosk settings
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+
+    /// Seed for the random number generator (drawn at random if omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Stop on this day and write the run report
+    #[arg(long)]
+    end_day: Option<u32>,
+
+    /// Open the analytics window (plots) alongside the host view; toggle with F2
+    #[arg(long)]
+    analytics_window: bool,
+
+    /// Number of hosts
+    #[arg(long, default_value_t = 10)]
+    hosts: u32,
+
+    /// People each rendered host represents; all but one are simulated in aggregate, so thousands can run behind ~50 sprites
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    people_per_host: u32,
+
+    /// Natural-history cohort: no new infections after day 0; writes the outcome of every initial infection
+    #[arg(long)]
+    cohort: bool,
+
+    /// Write a per-host, per-day diary of states, exposures and interventions to exposure_diary.csv
+    #[arg(long)]
+    diary: bool,
+
+    /// Step through a narrated tutorial on a fixed scenario
+    #[arg(long)]
+    tutorial: bool,
+
+    /// Directory for output files
+    #[arg(long, default_value = "output")]
+    output_dir: PathBuf,
+
+    /// Re-read the scenario file when it changes and apply its [params] live
+    #[arg(long, requires = "scenario")]
+    watch: bool,
+
+    /// Most inoculations one host can carry; further infections of that host are refused
+    #[arg(long, default_value_t = 20)]
+    max_inoculations_per_host: usize,
+
+    /// Most inoculations in the whole population; further infections are refused
+    #[arg(long, default_value_t = 10_000)]
+    max_inoculations: usize,
+
+    /// Start from the hosts saved in this snapshot (JSON) instead of a fresh day-0 cohort; overrides the scenario's
+    #[arg(long)]
+    snapshot: Option<PathBuf>,
+
+    /// Write the hosts as they stand on the end day to this snapshot file, e.g. to warm-start later runs at equilibrium
+    #[arg(long, requires = "end_day")]
+    write_snapshot: Option<PathBuf>,
+
+    /// Draw durations and infections with platform-independent math, so a seed gives the same run on any OS or CPU
+    #[arg(long)]
+    portable_math: bool,
+
+    /// Overlay the host states exported by an earlier run (host_states.csv) on the Host States plot; repeatable
+    #[arg(long)]
+    compare: Vec<PathBuf>,
+
+    /// Time resolution for plots and exported statistics
+    #[arg(long, value_enum, default_value_t)]
+    aggregation: stats::Aggregation,
+}
+
+// Tools that run without opening the app
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Compare two snapshots host by host, e.g. from runs with the same seed that should match; exits 1 if they differ
+    DiffSnapshots { a: PathBuf, b: PathBuf },
+    /// Compare two scenario files key by key, with the old and new value of each change; exits 1 if they differ
+    DiffScenarios { a: PathBuf, b: PathBuf },
+    /// Layer override scenario files, in order, onto a base scenario and print the merged scenario
+    MergeScenarios {
+        base: PathBuf,
+        #[arg(required = true)]
+        overrides: Vec<PathBuf>,
+        /// Write the merged scenario to this file instead of printing it
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Run a Latin hypercube sample of the param ranges in a design file headlessly and write sensitivity.csv to the output directory
+    Sensitivity { design: PathBuf },
+    /// Run a small fixed-seed simulation and check the model's invariants, determinism and output formats, e.g. after changing the code
+    Validate,
+}
+
 // App setup
 fn main() {
-    env_logger::init(); // Initializes logging
+    let cli = Cli::parse();
+    if cli.portable_math {
+        portable_math::enable();
+    }
+    match &cli.command {
+        Some(Command::DiffSnapshots { a, b }) => std::process::exit(snapshot::diff_snapshots(a, b)),
+        Some(Command::Validate) => std::process::exit(validate::validate()),
+        Some(Command::DiffScenarios { a, b }) => std::process::exit(scenario::diff_scenarios(a, b)),
+        Some(Command::MergeScenarios { base, overrides, output }) => {
+            std::process::exit(scenario::merge_scenarios(base, overrides, output.as_deref()))
+        }
+        Some(Command::Sensitivity { design }) => std::process::exit(sensitivity::run_sensitivity(design, &cli)),
+        None => {}
+    }
+    #[cfg(feature = "gui")]
+    run_app(cli);
+    #[cfg(not(feature = "gui"))]
+    {
+        eprintln!("Built without the gui feature, so only the subcommands are available; see --help");
+        std::process::exit(2);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_app(cli: Cli) {
+    let seed = match (cli.seed, cli.tutorial) {
+        (Some(seed), _) => seed,
+        (None, true) => tutorial::TUTORIAL_SEED,
+        (None, false) => rand::random(),
+    };
+
+    let scenario = match &cli.scenario {
+        Some(path) => scenario::Scenario::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load scenario: {}", err);
+            std::process::exit(2);
+        }),
+        None => scenario::Scenario::default(),
+    };
+    if scenario.clock.seconds_per_day <= 0.0 {
+        eprintln!("Failed to load scenario: clock.seconds_per_day must be positive");
+        std::process::exit(2);
+    }
+    let environment = scenario.environment.as_ref().map(|config| {
+        environment::Environment::load(&config.raster).unwrap_or_else(|err| {
+            eprintln!("Failed to load environment raster: {}", err);
+            std::process::exit(2);
+        })
+    });
+    let locations = scenario.host_locations.as_ref().map(|path| {
+        gis::HostLocations::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load host locations: {}", err);
+            std::process::exit(2);
+        })
+    });
+    let age_mixing = scenario.age_mixing.as_ref().map(|config| {
+        age_mixing::AgeMixing::load(&config.matrix, config.contact_rate).unwrap_or_else(|err| {
+            eprintln!("Failed to load age mixing matrix: {}", err);
+            std::process::exit(2);
+        })
+    });
+    let term_calendar = scenario.term_calendar.as_ref().map(|path| {
+        terms::TermCalendar::load(path).unwrap_or_else(|err| {
+            eprintln!("Failed to load term calendar: {}", err);
+            std::process::exit(2);
+        })
+    });
+    let snapshot = cli.snapshot.as_ref().or(scenario.snapshot.as_ref()).map(|path| {
+        snapshot::Snapshot::load(path, locations.as_ref()).unwrap_or_else(|err| {
+            eprintln!("Failed to load snapshot: {}", err);
+            std::process::exit(2);
+        })
+    });
+    let saved_runs = cli
+        .compare
+        .iter()
+        .map(|path| {
+            comparison::SavedRun::load(path).unwrap_or_else(|err| {
+                eprintln!("Failed to load run to compare: {}", err);
+                std::process::exit(2);
+            })
+        })
+        .collect();
+    let mut params = Params::default();
+    scenario.params.apply(&mut params);
+    let mut errors = param_docs::validate(&params).err().unwrap_or_default();
+    for preset in &scenario.presets {
+        let (preset_params, _) = experiments::preset_settings(Some(preset), &params, &None);
+        let preset_errors = param_docs::validate(&preset_params).err().unwrap_or_default();
+        errors.extend(preset_errors.into_iter().map(|err| format!("preset {}: {}", preset.name, err)));
+    }
+    if !errors.is_empty() {
+        eprintln!("Invalid params:\n  {}", errors.join("\n  "));
+        std::process::exit(2);
+    }
+
+    let mut mda = scenario.mda.clone();
+    let mut warm_start = snapshot.clone();
 
-    App::new()
+    let mut app = App::new();
+    if cli.tutorial {
+        app.insert_resource(tutorial::Tutorial::default());
+    }
+    if cli.cohort {
+        app.insert_resource(cohort::Cohort::default());
+    }
+    if cli.diary {
+        app.add_systems(Startup, diary::setup_exposure_diary);
+    }
+    if let Some(environment) = environment {
+        app.insert_resource(environment)
+            .add_systems(Startup, environment::spawn_environment_layer)
+            .add_systems(Update, environment::assign_suitability.before(spawn_infections));
+    }
+    if let Some(age_mixing) = age_mixing {
+        app.insert_resource(age_mixing)
+            .add_systems(Update, (age_mixing::assign_age_mixing, age_mixing::update_age_mixing_exposure).chain().before(spawn_infections))
+            .add_systems(
+                Update,
+                age_mixing::attribute_mixing_infections
+                    .after(spawn_infections)
+                    .after(venues::attribute_venue_infections)
+                    .before(transmission::write_transmission_edges),
+            );
+    }
+    let host_count = locations.as_ref().map_or(cli.hosts, |locations| locations.host_count());
+    if let Some(locations) = &locations {
+        app.insert_resource(locations.clone());
+    }
+    if let Some(term_calendar) = term_calendar {
+        app.insert_resource(term_calendar);
+    }
+    if let Some(path) = cli.write_snapshot {
+        app.insert_resource(snapshot::SnapshotOutput::new(path))
+            .add_systems(Last, snapshot::write_snapshot.before(report::finish_run));
+    }
+    #[cfg(feature = "inspector")]
+    inspector::add_inspector(&mut app);
+    #[cfg(feature = "audio")]
+    audio::add_audio_cues(&mut app);
+    if let Some(path) = cli.scenario.clone().filter(|_| cli.watch) {
+        app.insert_resource(hot_reload::ScenarioWatch::new(path));
+    }
+    if let Some(kiosk_config) = scenario.kiosk.clone() {
+        let kiosk = kiosk::Kiosk::new(kiosk_config, scenario.presets_or_builtin(), params.clone(), mda.clone(), snapshot.clone());
+        (params, mda) = kiosk.settings();
+        warm_start = kiosk.snapshot(locations.as_ref());
+        app.insert_resource(kiosk);
+    }
+
+    app
         .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.1)))
-        .insert_resource(Params::default())
-        .insert_resource(SimulationTime::default())
-        .insert_resource(SimulationSpeed::default())
-        .add_plugins(DefaultPlugins)
+        .insert_resource(experiments::Experiments::new(scenario.presets_or_builtin(), params.clone(), mda.clone(), snapshot))
+        .insert_resource(snapshot::WarmStart(warm_start))
+        .insert_resource(mda::MdaSchedule(mda))
+        .insert_resource(acd::AcdSchedule(scenario.acd.clone()))
+        .insert_resource(treatment::TreatmentPolicy::new(&scenario.treatment_policy))
+        .insert_resource(supply::DrugSupply(scenario.drug_stock.clone()))
+        .insert_resource(supply::DrugStock::default())
+        .insert_resource(stats::Diagnostic(scenario.diagnostic.clone()))
+        .insert_resource(trial::TrialDesign(scenario.trial.clone()))
+        .insert_resource(trial::Trial::default())
+        .insert_resource(test_negative::TestNegativeDesign(scenario.test_negative.clone()))
+        .insert_resource(test_negative::TestNegativeStudy::default())
+        .insert_resource(vectors::Vectors(scenario.vectors.clone()))
+        .insert_resource(vectors::VectorPopulation::default())
+        .insert_resource(venues::VenueSetup(scenario.venues.clone()))
+        .insert_resource(occupations::Occupations(scenario.occupations.clone()))
+        .insert_resource(strata::RiskStrata::default())
+        .insert_resource(coverage::Coverage::default())
+        .insert_resource(transmission::TransmissionTree::default())
+        .insert_resource(state_hash::StateHashes::default())
+        .insert_resource(blocked::BlockedExposures::default())
+        .insert_resource(blocked::ShieldFlashes::default())
+        .insert_resource(transmission::TransmissionView::default())
+        .insert_resource(transmission::Clusters::default())
+        .insert_resource(acd::AcdCampaigns::default())
+        .insert_resource(interventions::InterventionCoverage::default())
+        .insert_resource(alerts::AlertRules(scenario.alerts_or_builtin()))
+        .insert_resource(alerts::AlertState::default())
+        .insert_resource(annotations::Annotations::default())
+        .insert_resource(undo::ParamHistory::default())
+        .insert_resource(event_log::EventLog::default())
+        .insert_resource(branching::WhatIf::default())
+        .insert_resource(prcc::SensitivityResults::default())
+        .insert_resource(params)
+        .add_plugins(SimulationClockPlugin {
+            seconds_per_day: scenario.clock.seconds_per_day,
+        })
+        .insert_resource(Calendar {
+            start_date: scenario.clock.start_date,
+        })
+        .insert_resource(OutputDir(cli.output_dir))
+        .insert_resource(SimRng::new(seed))
+        .insert_resource(Ids::default())
+        .insert_resource(budget::EntityBudget {
+            max_per_host: cli.max_inoculations_per_host,
+            max_total: cli.max_inoculations,
+        })
+        .insert_resource(budget::BudgetUsage::default())
+        .insert_resource(RunConfig {
+            end_day: cli.end_day,
+            host_count,
+            comorbidity: scenario.comorbidity.clone(),
+        })
+        .insert_resource(report::RunReport::default())
+        .insert_resource(genotyping::GenotypingPanel::default())
+        .insert_resource(serology::SerologySurvey::default())
+        .insert_resource(infectiousness::InfectiousReservoir::default())
+        .insert_resource(stats::GenotypeHistory::default())
+        .insert_resource(stats::StateHistory::default())
+        .insert_resource(stats::ResistanceHistory::default())
+        .insert_resource(cli.aggregation)
+        .insert_resource(ode::OdeModel::default())
+        .insert_resource(plots::PlotSettings::default())
+        .insert_resource(comparison::RunComparison::new(saved_runs))
+        .insert_resource(heatmap::HostHeatmap::default())
+        .insert_resource(sparklines::GroupIncidence::default())
+        .insert_resource(sparklines::SparklinePanel::default())
+        .insert_resource(layout::HostLayout::default())
+        .insert_resource(focus::FocalHost::default())
+        .insert_resource(context_menu::HostMenu::default())
+        .insert_resource(sandbox::Sandbox::default())
+        .insert_resource(state_debugger::InspectedInoculation::default())
+        .insert_resource(presentation::Presentation::default())
+        .add_observer(autosave::DailyStatesExport::default())
+        .add_intervention(mda::Mda)
+        .insert_resource(crowds::Representation {
+            people_per_host: cli.people_per_host,
+        })
+        .add_event::<SimulationEvent>()
+        .add_event::<ResetSimulation>()
+        .add_event::<venues::VenueInfection>()
+        .add_event::<age_mixing::MixingInfection>()
+        .add_event::<budget::InoculationRefused>()
+        .add_event::<blocked::ExposureBlocked>()
+        .add_event::<coverage::DoseOffered>()
+        .insert_resource(plots::AnalyticsWindow::new(cli.analytics_window))
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            exit_condition: ExitCondition::OnPrimaryClosed, // The analytics window can close on its own
+            ..default()
+        }))
         .add_plugins(EguiPlugin)
-        .add_systems(Startup, setup)
-        .add_systems(Update, (update_simulation_time, process_inoculations, process_hosts))
-        .add_systems(Update, spawn_infections)
-        .add_systems(Update, simulation_controls_ui)
+        .add_systems(Startup, (setup, genotyping::setup_genotyping_outputs, serology::setup_serology_output, test_negative::setup_test_negative_output, infectiousness::load_infectiousness_curve, acd::setup_acd_output, rng::setup_rng_audit, event_log::setup_event_log_output, transmission::setup_transmission_output, observers::start_observers, vectors::setup_vector_output, venues::spawn_venues, state_hash::setup_state_hash_output, cleared::setup_cleared_output, coverage::setup_coverage_output))
+        .add_systems(Update, (update_time_text, log_progress, process_inoculations, process_hosts))
+        .add_systems(Update, (draw_exposure, spawn_infections.run_if(sandbox::transmission_on)).chain().run_if(not(resource_exists::<cohort::Cohort>)))
+        .add_systems(Update, detect_elimination)
+        .add_systems(Update, (budget::track_budget.after(spawn_infections), budget::budget_ui))
+        .add_systems(
+            Update,
+            (crowds::spawn_crowds, crowds::step_crowds.after(spawn_infections))
+                .chain()
+                .before(stats::record_host_states)
+                .run_if(not(resource_exists::<cohort::Cohort>)),
+        )
+        .add_systems(Update, diary::record_exposure_diary.after(spawn_infections).run_if(resource_exists::<diary::ExposureDiary>))
+        .add_systems(Update, (reset_resource::<cohort::Cohort>, cohort::track_cohort, cohort::cohort_ui).chain().after(process_hosts).run_if(resource_exists::<cohort::Cohort>))
+        .add_systems(Update, (interventions::run_interventions, acd::run_case_detection).before(process_hosts))
+        .add_systems(Update, (acd::case_detection_ui, interventions::interventions_ui))
+        .add_systems(Update, ((venues::assign_memberships, venues::update_venue_exposure).chain().before(spawn_infections), venues::venues_ui))
+        .add_systems(Update, (occupations::assign_occupations, occupations::update_occupational_exposure).chain().before(spawn_infections))
+        .add_systems(Update, (venues::attribute_venue_infections.after(spawn_infections), transmission::draw_transmission_arcs, transmission::transmission_ui))
+        .add_systems(Update, transmission::write_transmission_edges.after(venues::attribute_venue_infections))
+        .add_systems(Update, (transmission::detect_clusters, transmission::draw_clusters).chain())
+        .add_systems(Update, (cleared::record_cleared.after(process_inoculations).after(process_hosts), cleared::fade_cleared))
+        .add_systems(
+            Update,
+            (reset_resource::<blocked::BlockedExposures>, blocked::record_blocked_exposures, blocked::draw_shield_flashes)
+                .chain()
+                .after(process_inoculations),
+        )
+        .add_systems(Update, (badges::draw_badges, badges::draw_prophylaxis_countdown, badges::draw_treatment_requests))
+        .add_systems(Update, strata::record_risk_strata.after(process_hosts))
+        .add_systems(
+            Update,
+            (reset_resource::<state_hash::StateHashes>, state_hash::record_state_hash)
+                .chain()
+                .after(process_hosts)
+                .after(process_inoculations)
+                .after(spawn_infections),
+        )
+        .add_systems(Update, (supply::resupply_drugs.before(process_hosts), supply::drug_stock_ui))
+        .add_systems(Update, (trial::run_trial.before(process_hosts), trial::trial_ui))
+        .add_systems(Update, (test_negative::enroll_care_seekers.before(process_hosts), test_negative::test_negative_ui))
+        .add_systems(Update, (alerts::check_alerts.after(process_hosts), alerts::alerts_ui))
+        .add_systems(Update, (vectors::update_vectors.after(infectiousness::update_infectious_reservoir).before(spawn_infections), vectors::vectors_ui))
+        .add_systems(Update, hot_reload::reload_scenario_params.run_if(resource_exists::<hot_reload::ScenarioWatch>))
+        .add_systems(Update, (event_log::write_event_log, event_log::event_log_ui))
+        .add_systems(Update, (branching::what_if_ui, branching::run_what_if))
+        .add_systems(Update, prcc::sensitivity_ui)
+        .add_systems(
+            Update,
+            (
+                reset_simulation,
+                reset_resource::<stats::GenotypeHistory>,
+                reset_resource::<stats::StateHistory>,
+                reset_resource::<stats::ResistanceHistory>,
+                reset_resource::<acd::AcdCampaigns>,
+                reset_resource::<supply::DrugStock>,
+                reset_resource::<trial::Trial>,
+                reset_resource::<budget::BudgetUsage>,
+                reset_resource::<test_negative::TestNegativeStudy>,
+                reset_resource::<interventions::InterventionCoverage>,
+                reset_resource::<event_log::EventLog>,
+                reset_resource::<ode::OdeModel>,
+                reset_resource::<heatmap::HostHeatmap>,
+                reset_resource::<sparklines::GroupIncidence>,
+                reset_resource::<focus::FocalHost>,
+                reset_resource::<vectors::VectorPopulation>,
+                reset_resource::<strata::RiskStrata>,
+                reset_resource::<transmission::TransmissionTree>,
+                reset_resource::<alerts::AlertState>,
+                (reset_resource::<annotations::Annotations>, reset_resource::<sandbox::Sandbox>),
+            ),
+        )
+        .add_systems(Update, (kiosk::track_kiosk_activity, kiosk::kiosk_ui).run_if(resource_exists::<kiosk::Kiosk>))
+        .add_systems(Update, (simulation_controls_ui, distributions::distributions_ui.run_if(presentation::not_presenting)))
+        .add_systems(Update, (presentation::toggle_presentation, presentation::scale_hosts))
         .add_systems(Update, (update_inoculation_positions, update_inoculation_colors))
-        .add_systems(Update, update_host_sprites)
+        .add_systems(Update, (update_host_sprites, layout::layout_hosts.run_if(not(resource_exists::<gis::HostLocations>))))
+        .add_systems(Update, layout::reflow_on_resize)
+        .add_systems(Update, (focus::select_focal_host, focus::record_focal_history, focus::focal_host_ui))
+        .add_systems(
+            Update,
+            (context_menu::open_host_menu, context_menu::host_menu_ui, context_menu::apply_host_menu_action)
+                .chain()
+                .run_if(not(resource_exists::<kiosk::Kiosk>)),
+        )
+        .add_systems(Update, (sandbox::sandbox_ui, sandbox::apply_sandbox_action).chain().run_if(not(resource_exists::<kiosk::Kiosk>)))
+        .add_systems(
+            Update,
+            (state_debugger::select_inoculation, state_debugger::outline_inspected, state_debugger::state_debugger_ui)
+                .run_if(presentation::not_presenting),
+        )
+        .add_systems(Update, (input::touch_controls, input::gamepad_controls))
+        .add_systems(Update, focus::dim_unfocused.after(update_host_sprites).after(update_inoculation_colors))
+        .add_systems(Update, (genotyping::run_genotyping_survey, serology::run_serosurvey))
+        .add_systems(Update, infectiousness::update_infectious_reservoir)
+        .add_systems(Update, (stats::record_genotype_frequencies, stats::record_host_states, stats::record_resistance, ode::integrate_ode))
+        .add_systems(Update, (observers::notify_observers.after(stats::record_host_states), observers::observers_ui))
+        .add_systems(Update, plots::toggle_analytics_window.run_if(not(resource_exists::<kiosk::Kiosk>)))
+        .add_systems(Update, experiments::experiment_ui.run_if(not(resource_exists::<kiosk::Kiosk>)))
+        .add_systems(Update, (plots::genotype_frequency_plot_ui, plots::host_state_plot_ui, annotations::annotations_ui))
+        .add_systems(Update, (heatmap::record_heatmap, heatmap::heatmap_ui))
+        .add_systems(Update, (sparklines::record_group_incidence, sparklines::sparklines_ui, sparklines::outline_hovered_group))
+        .add_systems(Update, (tutorial::advance_tutorial, tutorial::tutorial_ui).run_if(resource_exists::<tutorial::Tutorial>))
+        .add_systems(Last, (rng::record_rng_usage, report::finish_run, observers::finalize_observers.after(report::finish_run)))
+        .add_systems(Last, coverage::record_coverage.after(report::finish_run).before(report::write_run_status))
+        .add_systems(Last, report::write_run_status.after(observers::finalize_observers))
         .run();
+    std::process::exit(report::EXIT_CODE.load(std::sync::atomic::Ordering::Relaxed));
 }
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::interventions::Intervention;
+use crate::rng::{CountingRng, Subsystem};
+use crate::scenario::MdaConfig;
+use crate::scheduled::ScheduledActions;
+use crate::{Host, Regimen};
+
+// Active MDA schedule, if any; replaced when a preset is selected
+#[derive(Resource, Default)]
+pub struct MdaSchedule(pub Option<MdaConfig>);
+
+impl MdaSchedule {
+    pub fn is_round_day(&self, day: u32) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|config| is_round_day(day, config.start_day, config.interval_days, config.rounds))
+    }
+}
+
+// Whether one of `rounds` evenly spaced rounds falls on this day
+pub fn is_round_day(day: u32, start_day: u32, interval_days: u32, rounds: u32) -> bool {
+    if day < start_day {
+        return false;
+    }
+    let since_start = day - start_day;
+    let interval = interval_days.max(1);
+    since_start.is_multiple_of(interval) && since_start / interval < rounds
+}
+
+// Treats a coverage fraction of hosts on each round day; treatment itself runs through process_hosts
+pub struct Mda;
+
+impl Intervention for Mda {
+    fn name(&self) -> &str {
+        "MDA"
+    }
+
+    fn subsystem(&self) -> Subsystem {
+        Subsystem::Mda
+    }
+
+    fn active(&self, world: &World, day: u32) -> bool {
+        world.resource::<MdaSchedule>().is_round_day(day)
+    }
+
+    fn eligible(&self, world: &World, _host: &Host, rng: &mut CountingRng) -> bool {
+        let coverage = world.resource::<MdaSchedule>().0.as_ref().map_or(0.0, |config| config.coverage);
+        rng.gen::<f32>() < coverage
+    }
+
+    fn apply(&self, world: &mut World, host: Entity, day: u32) {
+        if let Some(mut scheduled) = world.get_mut::<ScheduledActions>(host) {
+            scheduled.request_treatment(day, Regimen::Mda);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Versioned file formats: scenario and snapshot files record the format they were written in, and older files are
+// upgraded one version at a time before they're parsed, so a format change only needs a migration added to its list
+// rather than breaking files saved by earlier builds
+
+pub const VERSION_KEY: &str = "format_version";
+
+// The top-level table of a file, before it is parsed into its own type
+pub trait Document {
+    fn version(&self) -> Option<Option<i64>>; // None if missing, Some(None) if not an integer
+    fn set_version(&mut self, version: u32);
+}
+
+impl Document for toml::Table {
+    fn version(&self) -> Option<Option<i64>> {
+        self.get(VERSION_KEY).map(toml::Value::as_integer)
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.insert(VERSION_KEY.to_owned(), toml::Value::Integer(version as i64));
+    }
+}
+
+impl Document for serde_json::Map<String, serde_json::Value> {
+    fn version(&self) -> Option<Option<i64>> {
+        self.get(VERSION_KEY).map(serde_json::Value::as_i64)
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.insert(VERSION_KEY.to_owned(), serde_json::Value::from(version));
+    }
+}
+
+// The version a format is at when it has these migrations; the nth migration upgrades version n to n + 1
+pub const fn current_version<T>(migrations: &[fn(&mut T)]) -> u32 {
+    migrations.len() as u32 + 1
+}
+
+// Brings a document up to the current version, returning whether anything had to change.  Files from before
+// versioning have no version and count as version 1; files from a newer build are rejected rather than misread.
+pub fn upgrade<T: Document>(document: &mut T, migrations: &[fn(&mut T)]) -> Result<bool, String> {
+    let current = current_version(migrations);
+    let version = match document.version() {
+        None => 1,
+        Some(Some(version)) if (1..=current as i64).contains(&version) => version as u32,
+        Some(Some(version)) if version > current as i64 => {
+            return Err(format!("{} {} is from a newer version of the app, which reads up to {}", VERSION_KEY, version, current))
+        }
+        Some(_) => return Err(format!("{} must be a whole number from 1 to {}", VERSION_KEY, current)),
+    };
+    for migration in &migrations[version as usize - 1..] {
+        migration(document);
+    }
+    document.set_version(current);
+    Ok(version != current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_marker(table: &mut toml::Table) {
+        table.insert("migrated".to_owned(), toml::Value::Boolean(true));
+    }
+
+    const MIGRATIONS: &[fn(&mut toml::Table)] = &[add_marker];
+
+    #[test]
+    fn unversioned_files_are_upgraded_from_version_1() {
+        let mut table: toml::Table = toml::from_str("name = \"old\"").unwrap();
+        assert_eq!(upgrade(&mut table, MIGRATIONS), Ok(true));
+        assert_eq!(table.get("migrated"), Some(&toml::Value::Boolean(true)));
+        assert_eq!(table.version(), Some(Some(2)));
+    }
+
+    #[test]
+    fn current_files_are_left_alone() {
+        let mut table: toml::Table = toml::from_str("format_version = 2").unwrap();
+        assert_eq!(upgrade(&mut table, MIGRATIONS), Ok(false));
+        assert!(table.get("migrated").is_none());
+    }
+
+    #[test]
+    fn newer_and_malformed_versions_are_rejected() {
+        let mut table: toml::Table = toml::from_str("format_version = 3").unwrap();
+        assert!(upgrade(&mut table, MIGRATIONS).unwrap_err().contains("newer version"));
+        assert!(table.get("migrated").is_none());
+
+        let mut table: toml::Table = toml::from_str("format_version = \"2\"").unwrap();
+        assert!(upgrade(&mut table, MIGRATIONS).is_err());
+    }
+}
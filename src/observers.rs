@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::stats::StateHistory;
+use crate::{OutputDir, SimulationEvent};
+
+// What observers see at the end of each simulated day
+pub struct DaySummary {
+    pub day: u32,
+    pub state_counts: [usize; 5], // Hosts (and crowds) in HostState::ALL order
+}
+
+// A pluggable statistics collector, exporter or plot. Register with `app.add_observer(...)`; every method is optional.
+pub trait Observer: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    // Once at startup, e.g. to open output files
+    fn start(&mut self, _output_dir: &OutputDir) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn on_day_end(&mut self, _summary: &DaySummary) {}
+
+    fn on_event(&mut self, _day: u32, _event: &SimulationEvent) {}
+
+    // Once the run report is written, at the end day or on exit
+    fn finalize(&mut self, _output_dir: &OutputDir) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    // Shown in the Observers window
+    #[cfg(feature = "gui")]
+    fn ui(&mut self, _ui: &mut egui::Ui) {}
+}
+
+#[derive(Resource, Default)]
+pub struct Observers {
+    observers: Vec<Box<dyn Observer>>,
+    days_seen: usize, // StateHistory samples already passed on
+    finalized: bool,
+}
+
+pub trait ObserverAppExt {
+    fn add_observer(&mut self, observer: impl Observer) -> &mut Self;
+}
+
+impl ObserverAppExt for App {
+    fn add_observer(&mut self, observer: impl Observer) -> &mut Self {
+        self.init_resource::<Observers>();
+        self.world.resource_mut::<Observers>().observers.push(Box::new(observer));
+        self
+    }
+}
+
+pub fn start_observers(mut observers: ResMut<Observers>, mut report: ResMut<RunReport>, output_dir: Res<OutputDir>) {
+    // A failed observer is dropped rather than stopping the run, which still exits with the failure
+    observers.observers.retain_mut(|observer| match observer.start(&output_dir) {
+        Ok(()) => true,
+        Err(err) => {
+            report.output_failed(observer.name(), err);
+            false
+        }
+    });
+}
+
+pub fn notify_observers(
+    mut observers: ResMut<Observers>,
+    mut events: EventReader<SimulationEvent>,
+    states: Res<StateHistory>,
+) {
+    let day = states.samples.last().map_or(0, |(day, _)| *day);
+    for event in events.read() {
+        for observer in observers.observers.iter_mut() {
+            observer.on_event(day, event);
+        }
+    }
+
+    // A reset clears the history; the next run's days follow on
+    if states.samples.len() < observers.days_seen {
+        observers.days_seen = 0;
+    }
+    let days_seen = observers.days_seen;
+    for (day, state_counts) in &states.samples[days_seen..] {
+        let summary = DaySummary {
+            day: *day,
+            state_counts: *state_counts,
+        };
+        for observer in observers.observers.iter_mut() {
+            observer.on_day_end(&summary);
+        }
+    }
+    observers.days_seen = states.samples.len();
+}
+
+pub fn finalize_observers(mut observers: ResMut<Observers>, mut report: ResMut<RunReport>, output_dir: Res<OutputDir>) {
+    if observers.finalized || !report.written() {
+        return;
+    }
+    observers.finalized = true;
+    for observer in observers.observers.iter_mut() {
+        if let Err(err) = observer.finalize(&output_dir) {
+            report.output_failed(observer.name(), err);
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn observers_ui(mut contexts: EguiContexts, mut observers: ResMut<Observers>, analytics: Res<AnalyticsWindow>) {
+    if observers.observers.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Observers")
+        .default_pos(egui::pos2(850.0, 700.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            for observer in observers.observers.iter_mut() {
+                let name = observer.name().to_owned();
+                egui::CollapsingHeader::new(name).default_open(true).show(ui, |ui| observer.ui(ui));
+            }
+        });
+}
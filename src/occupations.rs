@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::clock::DayTick;
+use crate::rng::Subsystem;
+use crate::scenario::OccupationConfig;
+use crate::{Calendar, Host, SimRng, SimulationTime};
+
+// Occupations from the scenario
+#[derive(Resource, Default)]
+pub struct Occupations(pub Vec<OccupationConfig>);
+
+// The host's occupation, and today's multiplier on its vector-borne infection rate from working
+#[derive(Component, Debug, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
+pub struct Occupation {
+    pub name: String,
+    index: usize,
+    pub exposure: f32,
+}
+
+pub fn assign_occupations(
+    mut commands: Commands,
+    host_query: Query<Entity, Added<Host>>,
+    occupations: Res<Occupations>,
+    sim_time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    if occupations.0.is_empty() {
+        return;
+    }
+
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort();
+    let weekday = calendar.weekday(sim_time.day);
+    let rng = &mut sim_rng.stream(Subsystem::Spawn);
+    for host in hosts {
+        let mut draw = rng.gen::<f32>();
+        let Some((index, config)) = occupations.0.iter().enumerate().find(|(_, config)| {
+            draw -= config.fraction;
+            draw < 0.0
+        }) else {
+            continue;
+        };
+        commands.entity(host).insert(Occupation {
+            name: config.name.clone(),
+            index,
+            exposure: config.exposure_on(sim_time.day, weekday),
+        });
+    }
+}
+
+pub fn update_occupational_exposure(
+    mut host_query: Query<&mut Occupation>,
+    occupations: Res<Occupations>,
+    sim_time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    mut ticks: EventReader<DayTick>,
+) {
+    if ticks.read().count() == 0 {
+        return;
+    }
+
+    let weekday = calendar.weekday(sim_time.day);
+    for mut occupation in host_query.iter_mut() {
+        if let Some(config) = occupations.0.get(occupation.index) {
+            occupation.exposure = config.exposure_on(sim_time.day, weekday);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::{Params, SimulationTime};
+
+// Compartmental counterpart of the agent-based model, ignoring coinfection and immunity.
+// Acute hosts are split by whether they will seek treatment, so the treated fraction matches prob_treatment.
+#[derive(Clone, Copy, Default)]
+struct Compartments {
+    s: f64,
+    e: f64,
+    a_treated: f64,
+    a_untreated: f64,
+    c: f64,
+    p: f64,
+}
+
+impl Compartments {
+    fn derivative(&self, params: &Params) -> Self {
+        let incidence = params.incidence_rate as f64;
+        let liver_rate = 1.0 / params.duration_liver as f64;
+        let acute_rate = 1.0 / params.duration_acute.mean() as f64;
+        let chronic_rate = 1.0 / params.duration_chronic.mean() as f64;
+        let treatment_rate = 1.0 / (params.treatment_delay.mean() as f64).max(0.1);
+        let prophylaxis_rate = 1.0 / (params.treatment_drug.duration_prophylaxis as f64).max(0.1);
+        let prob_acute = params.prob_acute as f64;
+        let prob_treatment = params.prob_treatment as f64;
+        let prob_ac = params.prob_ac as f64;
+
+        let infection = incidence * self.s;
+        let liver_exit = liver_rate * self.e;
+        let acute_exit = acute_rate * self.a_untreated;
+        let chronic_exit = chronic_rate * self.c;
+        let treatment = treatment_rate * self.a_treated;
+        let prophylaxis_exit = prophylaxis_rate * self.p;
+
+        Self {
+            s: -infection + (1.0 - prob_ac) * acute_exit + chronic_exit + prophylaxis_exit,
+            e: infection - liver_exit,
+            a_treated: prob_acute * prob_treatment * liver_exit - treatment,
+            a_untreated: prob_acute * (1.0 - prob_treatment) * liver_exit - acute_exit,
+            c: (1.0 - prob_acute) * liver_exit + prob_ac * acute_exit - chronic_exit,
+            p: treatment - prophylaxis_exit,
+        }
+    }
+
+    fn add_scaled(&self, other: &Self, h: f64) -> Self {
+        Self {
+            s: self.s + h * other.s,
+            e: self.e + h * other.e,
+            a_treated: self.a_treated + h * other.a_treated,
+            a_untreated: self.a_untreated + h * other.a_untreated,
+            c: self.c + h * other.c,
+            p: self.p + h * other.p,
+        }
+    }
+
+    // Fourth-order Runge-Kutta step
+    fn step(&self, params: &Params, h: f64) -> Self {
+        let k1 = self.derivative(params);
+        let k2 = self.add_scaled(&k1, h / 2.0).derivative(params);
+        let k3 = self.add_scaled(&k2, h / 2.0).derivative(params);
+        let k4 = self.add_scaled(&k3, h).derivative(params);
+        self.add_scaled(&k1, h / 6.0)
+            .add_scaled(&k2, h / 3.0)
+            .add_scaled(&k3, h / 3.0)
+            .add_scaled(&k4, h / 6.0)
+    }
+
+    // Fractions in HostState::ALL order
+    fn fractions(&self) -> [f64; 5] {
+        [self.s, self.e, self.a_treated + self.a_untreated, self.c, self.p]
+    }
+}
+
+#[derive(Resource)]
+pub struct OdeModel {
+    state: Compartments,
+    day: u32,
+    pub samples: Vec<(u32, [f64; 5])>, // Fractions of hosts in HostState::ALL order
+}
+
+impl Default for OdeModel {
+    fn default() -> Self {
+        // Matches setup, where every host starts with one liver-stage inoculation
+        let state = Compartments { e: 1.0, ..default() };
+        Self {
+            state,
+            day: 0,
+            samples: vec![(0, state.fractions())],
+        }
+    }
+}
+
+pub fn integrate_ode(mut ode: ResMut<OdeModel>, params: Res<Params>, sim_time: Res<SimulationTime>) {
+    const STEPS_PER_DAY: usize = 10;
+
+    while ode.day < sim_time.day {
+        for _ in 0..STEPS_PER_DAY {
+            ode.state = ode.state.step(&params, 1.0 / STEPS_PER_DAY as f64);
+        }
+        ode.day += 1;
+        let sample = (ode.day, ode.state.fractions());
+        ode.samples.push(sample);
+    }
+}
@@ -0,0 +1,430 @@
+use std::ops::RangeInclusive;
+
+use crate::Params;
+
+// What one number in Params means, for slider tooltips and for checking scenario values
+pub struct ParamInfo {
+    pub key: &'static str,   // As written under [params] in a scenario
+    pub label: &'static str, // Slider label
+    pub group: &'static str, // Collapsing section of the controls it appears in
+    pub units: &'static str,
+    pub help: &'static str,
+    pub typical: RangeInclusive<f32>, // For the help text
+    pub valid: RangeInclusive<f32>,   // Anything outside is rejected
+    pub step: f64, // Slider increment, or 0 for continuous
+    pub field: fn(&mut Params) -> &mut f32,
+}
+
+impl ParamInfo {
+    // Every valid value where that's a bounded range, e.g. a probability; otherwise from the lowest valid value up to
+    // the top of the typical range
+    pub fn slider_range(&self) -> RangeInclusive<f32> {
+        if self.valid.end().is_finite() {
+            self.valid.clone()
+        } else {
+            *self.valid.start()..=*self.typical.end()
+        }
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!("{}\n\nUnits: {}\nTypical range: {} to {}", self.help, self.units, self.typical.start(), self.typical.end())
+    }
+}
+
+const PROBABILITY: RangeInclusive<f32> = 0.0..=1.0;
+const NON_NEGATIVE: RangeInclusive<f32> = 0.0..=f32::INFINITY;
+
+// Every number in Params, by group in the order the controls show them; a new field only needs an entry here to get
+// a slider, a scenario key and range checks
+pub const PARAMS: &[ParamInfo] = &[
+    ParamInfo {
+        key: "incidence_rate",
+        label: "Incidence Rate",
+        group: "Transmission",
+        units: "infectious bites per host per day",
+        help: "Daily chance a host is bitten by an infectious mosquito, before local suitability, occupation and sex scale it.",
+        typical: 0.0..=0.2,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.incidence_rate,
+    },
+    ParamInfo {
+        key: "exposure_dispersion",
+        label: "Exposure Dispersion k (0 = off)",
+        group: "Transmission",
+        units: "gamma shape k",
+        help: "How unevenly bites fall on hosts each day. Small k concentrates them on a few hosts; 0 gives every host the same rate.",
+        typical: 0.0..=5.0,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.exposure_dispersion,
+    },
+    ParamInfo {
+        key: "duration_liver",
+        label: "Liver Stage Duration",
+        group: "Transmission",
+        units: "days",
+        help: "Days an inoculation spends in the liver before it reaches the blood.",
+        typical: 5.0..=14.0,
+        valid: NON_NEGATIVE,
+        step: 1.0,
+        field: |params| &mut params.duration_liver,
+    },
+    ParamInfo {
+        key: "prob_acute",
+        label: "Acute Probability",
+        group: "Disease",
+        units: "probability",
+        help: "Chance a blood-stage infection is acute rather than chronic, before comorbidity.",
+        typical: 0.3..=0.9,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.prob_acute,
+    },
+    ParamInfo {
+        key: "prob_ac",
+        label: "Acute to Chronic Probability",
+        group: "Disease",
+        units: "probability",
+        help: "Chance an acute infection that isn't cured goes on to a chronic one rather than clearing.",
+        typical: 0.0..=0.5,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.prob_ac,
+    },
+    ParamInfo {
+        key: "prob_treatment",
+        label: "Treatment Probability",
+        group: "Care seeking",
+        units: "probability",
+        help: "Chance an acute case seeks care and is offered treatment.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.prob_treatment,
+    },
+    ParamInfo {
+        key: "prob_treatment_chronic",
+        label: "Chronic Treatment Probability",
+        group: "Care seeking",
+        units: "probability",
+        help: "Chance a new chronic infection is found, e.g. by incidental testing, and treated.",
+        typical: 0.0..=0.3,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.prob_treatment_chronic,
+    },
+    ParamInfo {
+        key: "acceptance_treatment",
+        label: "Treatment Acceptance",
+        group: "Care seeking",
+        units: "probability",
+        help: "Chance a host takes treatment when offered it, at the clinic or after screening.",
+        typical: 0.5..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.acceptance_treatment,
+    },
+    ParamInfo {
+        key: "acceptance_mda",
+        label: "MDA Acceptance",
+        group: "Care seeking",
+        units: "probability",
+        help: "Chance a host takes a mass drug administration dose when offered it.",
+        typical: 0.5..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.acceptance_mda,
+    },
+    ParamInfo {
+        key: "acceptance_correlation",
+        label: "Acceptance Correlation",
+        group: "Care seeking",
+        units: "correlation",
+        help: "1 means the same hosts refuse every time they are offered an intervention; 0 means refusals are independent.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.acceptance_correlation,
+    },
+    ParamInfo {
+        key: "male_fraction",
+        label: "Male Fraction",
+        group: "Host factors",
+        units: "fraction",
+        help: "Chance a new host is male.",
+        typical: 0.4..=0.6,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.male_fraction,
+    },
+    ParamInfo {
+        key: "male_exposure",
+        label: "Male Exposure (relative)",
+        group: "Host factors",
+        units: "ratio to females",
+        help: "Vector-borne infection rate of males relative to females, e.g. above 1 where men sleep in the forest.",
+        typical: 0.0..=5.0,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.male_exposure,
+    },
+    ParamInfo {
+        key: "male_care_seeking",
+        label: "Male Care Seeking (relative)",
+        group: "Host factors",
+        units: "ratio to females",
+        help: "Chance an acute male case seeks treatment, relative to a female one.",
+        typical: 0.0..=2.0,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.male_care_seeking,
+    },
+    ParamInfo {
+        key: "comorbidity_odds_ratio",
+        label: "Comorbidity Odds Ratio (acute)",
+        group: "Host factors",
+        units: "odds ratio per unit of score",
+        help: "Multiplier on the odds that an infection is acute, per unit of the host's comorbidity score.",
+        typical: 1.0..=10.0,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.comorbidity_odds_ratio,
+    },
+    ParamInfo {
+        key: "immunity_protection",
+        label: "Immunity Protection",
+        group: "Immunity",
+        units: "fraction",
+        help: "Protection against a new inoculation of a genotype the host has already seen at blood stage.",
+        typical: 0.5..=0.95,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.immunity_protection,
+    },
+    ParamInfo {
+        key: "cross_protection",
+        label: "Cross Protection",
+        group: "Immunity",
+        units: "fraction",
+        help: "Share of the protection against a genotype the host has seen that also applies to genotypes it hasn't.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.cross_protection,
+    },
+    ParamInfo {
+        key: "boost_probability",
+        label: "Antibody Boost Probability (blocked)",
+        group: "Immunity",
+        units: "probability",
+        help: "Chance an exposure blocked by prophylaxis or immunity still boosts the host's antibodies.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.boost_probability,
+    },
+    ParamInfo {
+        key: "boost_size",
+        label: "Antibody Boost Size",
+        group: "Immunity",
+        units: "titre units",
+        help: "Antibody titre added by each boost.",
+        typical: 0.5..=2.0,
+        valid: NON_NEGATIVE,
+        step: 0.0,
+        field: |params| &mut params.boost_size,
+    },
+    ParamInfo {
+        key: "antibody_half_life",
+        label: "Antibody Half-Life (days)",
+        group: "Immunity",
+        units: "days",
+        help: "Days for the antibody titre to halve.",
+        typical: 10.0..=1000.0,
+        valid: 1.0..=f32::INFINITY,
+        step: 5.0,
+        field: |params| &mut params.antibody_half_life,
+    },
+    ParamInfo {
+        key: "duration_prophylaxis",
+        label: "Prophylaxis Duration",
+        group: "Drugs",
+        units: "days",
+        help: "How long a treatment dose keeps protecting against new inoculations.",
+        typical: 1.0..=30.0,
+        valid: NON_NEGATIVE,
+        step: 1.0,
+        field: |params| &mut params.treatment_drug.duration_prophylaxis,
+    },
+    ParamInfo {
+        key: "treatment_drug.cure_liver",
+        label: "Treatment Cure (Liver)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance a treatment dose clears a liver-stage inoculation.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.treatment_drug.cure_liver,
+    },
+    ParamInfo {
+        key: "treatment_drug.cure_acute",
+        label: "Treatment Cure (Acute)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance a treatment dose clears an acute infection.",
+        typical: 0.8..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.treatment_drug.cure_acute,
+    },
+    ParamInfo {
+        key: "treatment_drug.cure_chronic",
+        label: "Treatment Cure (Chronic)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance a treatment dose clears a chronic infection.",
+        typical: 0.8..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.treatment_drug.cure_chronic,
+    },
+    ParamInfo {
+        key: "mda_drug.duration_prophylaxis",
+        label: "MDA Prophylaxis Duration",
+        group: "Drugs",
+        units: "days",
+        help: "How long an MDA dose keeps protecting against new inoculations.",
+        typical: 1.0..=30.0,
+        valid: NON_NEGATIVE,
+        step: 1.0,
+        field: |params| &mut params.mda_drug.duration_prophylaxis,
+    },
+    ParamInfo {
+        key: "mda_drug.cure_liver",
+        label: "MDA Cure (Liver)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance an MDA dose clears a liver-stage inoculation.",
+        typical: 0.0..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.mda_drug.cure_liver,
+    },
+    ParamInfo {
+        key: "mda_drug.cure_acute",
+        label: "MDA Cure (Acute)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance an MDA dose clears an acute infection.",
+        typical: 0.8..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.mda_drug.cure_acute,
+    },
+    ParamInfo {
+        key: "mda_drug.cure_chronic",
+        label: "MDA Cure (Chronic)",
+        group: "Drugs",
+        units: "probability",
+        help: "Chance an MDA dose clears a chronic infection.",
+        typical: 0.8..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.mda_drug.cure_chronic,
+    },
+    ParamInfo {
+        key: "resistant_fraction",
+        label: "Resistant Fraction",
+        group: "Resistance",
+        units: "fraction",
+        help: "Share of new inoculations that carry drug resistance.",
+        typical: 0.0..=0.2,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.resistant_fraction,
+    },
+    ParamInfo {
+        key: "resistance_level",
+        label: "Resistance Level",
+        group: "Resistance",
+        units: "probability",
+        help: "Chance a drug fails against a resistant clone.",
+        typical: 0.5..=1.0,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.resistance_level,
+    },
+    ParamInfo {
+        key: "resistance_cost_duration",
+        label: "Resistance Cost (Duration)",
+        group: "Resistance",
+        units: "fraction",
+        help: "How much shorter resistant clones' blood-stage infections are than sensitive ones'.",
+        typical: 0.0..=0.9,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.resistance_cost_duration,
+    },
+    ParamInfo {
+        key: "resistance_cost_transmission",
+        label: "Resistance Cost (Transmission)",
+        group: "Resistance",
+        units: "fraction",
+        help: "How much less infectious resistant clones are than sensitive ones.",
+        typical: 0.0..=0.9,
+        valid: PROBABILITY,
+        step: 0.0,
+        field: |params| &mut params.resistance_cost_transmission,
+    },
+];
+
+pub fn find(key: &str) -> Option<&'static ParamInfo> {
+    PARAMS.iter().find(|info| info.key == key)
+}
+
+// Groups in the order of their first param
+pub fn groups() -> Vec<&'static str> {
+    let mut groups = Vec::new();
+    for info in PARAMS {
+        if !groups.contains(&info.group) {
+            groups.push(info.group);
+        }
+    }
+    groups
+}
+
+// Genotypes keep one bit per locus below the resistance bit
+const MAX_LOCI: u32 = 14;
+
+// Every value outside its valid range, e.g. a probability above 1
+pub fn validate(params: &Params) -> Result<(), Vec<String>> {
+    let mut params = params.clone();
+    let mut errors: Vec<String> = PARAMS
+        .iter()
+        .filter_map(|info| {
+            let value = *(info.field)(&mut params);
+            (!info.valid.contains(&value)).then(|| {
+                let (low, high) = (info.valid.start(), info.valid.end());
+                let range = if high.is_infinite() { format!("at least {}", low) } else { format!("{} to {}", low, high) };
+                format!("{} = {} must be {} ({})", info.key, value, range, info.units)
+            })
+        })
+        .collect();
+    if !(1..=MAX_LOCI).contains(&params.n_loci) {
+        errors.push(format!("n_loci = {} must be 1 to {}", params.n_loci, MAX_LOCI));
+    }
+    for (key, dist) in
+        [("duration_acute", params.duration_acute), ("duration_chronic", params.duration_chronic), ("treatment_delay", params.treatment_delay)]
+    {
+        if let Some(problem) = dist.problem() {
+            errors.push(format!("{} = {:?}: {}", key, dist, problem));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::window::WindowRef;
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{Legend, Line, LineStyle, Plot, PlotPoints, PlotUi, Polygon};
+use std::collections::BTreeSet;
+
+use crate::annotations::{plot_annotations, Annotations};
+use crate::blocked::BlockedExposures;
+use crate::comparison::RunComparison;
+use crate::ode::OdeModel;
+use crate::stats::{Aggregation, Diagnostic, GenotypeHistory, ResistanceHistory, StateHistory};
+use crate::{HostState, Params, Sex};
+
+#[derive(Resource)]
+pub struct PlotSettings {
+    pub show_ode: bool,
+    pub proportions: bool,  // Percent of hosts rather than counts
+    pub log_scale: bool,    // Log10 y-axis; zero values are left out
+    pub visible: [bool; 5], // Per state, indexed like HostState::ALL
+    pub stacked: bool,      // Stacked-area view of the visible states
+    pub prevalence: bool,   // True and detected prevalence over the states
+    pub by_sex: bool,       // Dotted female and male host lines under each state
+}
+
+impl Default for PlotSettings {
+    fn default() -> Self {
+        Self {
+            show_ode: true,
+            proportions: false,
+            log_scale: false,
+            visible: [true; 5],
+            stacked: false,
+            prevalence: false,
+            by_sex: false,
+        }
+    }
+}
+
+impl PlotSettings {
+    // Maps a plotted value onto the y-axis, or None if it can't be shown on a log axis
+    fn y(&self, value: f64) -> Option<f64> {
+        if !self.log_scale {
+            Some(value)
+        } else if value > 0.0 {
+            Some(value.log10())
+        } else {
+            None
+        }
+    }
+
+    fn value(&self, counts: &[f64; 5], index: usize) -> f64 {
+        if self.proportions {
+            100.0 * counts[index] / counts.iter().sum::<f64>().max(1.0)
+        } else {
+            counts[index]
+        }
+    }
+}
+
+// Visible states as bands stacked in HostState::ALL order, drawn one trapezoid per period so each piece is convex
+fn stacked_area(plot_ui: &mut PlotUi, states: &[(u32, [f64; 5])], settings: &PlotSettings) {
+    let mut lower = vec![0.0; states.len()];
+    for (index, state) in HostState::ALL.iter().enumerate() {
+        if !settings.visible[index] {
+            continue;
+        }
+        let color = egui_color(state.color());
+        let upper: Vec<f64> = states
+            .iter()
+            .zip(&lower)
+            .map(|((_, counts), base)| base + settings.value(counts, index))
+            .collect();
+
+        for i in 1..states.len() {
+            let (x0, x1) = (states[i - 1].0 as f64, states[i].0 as f64);
+            let corners = vec![[x0, lower[i - 1]], [x1, lower[i]], [x1, upper[i]], [x0, upper[i - 1]]];
+            plot_ui.polygon(
+                Polygon::new(corners)
+                    .fill_color(color)
+                    .stroke(egui::Stroke::new(0.5, color))
+                    .name(format!("{:?}", state)),
+            );
+        }
+        lower = upper;
+    }
+}
+
+// True and detected prevalence lines, with the undetected gap shaded between them
+fn prevalence_lines(plot_ui: &mut PlotUi, prevalence: &[(u32, [f64; 3])], settings: &PlotSettings) {
+    let scale = |value: f64, total: f64| if settings.proportions { 100.0 * value / total.max(1.0) } else { value };
+    let line = |index: usize| -> Vec<Option<[f64; 2]>> {
+        prevalence
+            .iter()
+            .map(|(day, values)| Some([*day as f64, settings.y(scale(values[index], values[2]))?]))
+            .collect()
+    };
+    let (infected, detected) = (line(0), line(1));
+
+    let gap = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 40);
+    for i in 1..prevalence.len() {
+        let corners = [detected[i - 1], detected[i], infected[i], infected[i - 1]];
+        if let Some(corners) = corners.into_iter().collect::<Option<Vec<_>>>() {
+            plot_ui.polygon(Polygon::new(corners).fill_color(gap).stroke(egui::Stroke::NONE).name("Undetected"));
+        }
+    }
+    let points = |line: Vec<Option<[f64; 2]>>| -> PlotPoints { line.into_iter().flatten().collect() };
+    plot_ui.line(Line::new(points(infected)).color(egui::Color32::WHITE).width(2.0).name("True prevalence"));
+    plot_ui.line(
+        Line::new(points(detected))
+            .color(egui::Color32::WHITE)
+            .width(2.0)
+            .style(LineStyle::dashed_dense())
+            .name("Detected prevalence"),
+    );
+}
+
+// Optional second OS window for analytics, so the agent view can be shown on a projector alone
+#[derive(Resource)]
+pub struct AnalyticsWindow {
+    open_on_startup: bool,
+    window: Option<Entity>,
+    camera: Option<Entity>,
+}
+
+impl AnalyticsWindow {
+    pub fn new(open_on_startup: bool) -> Self {
+        Self {
+            open_on_startup,
+            window: None,
+            camera: None,
+        }
+    }
+}
+
+fn spawn_analytics_window(commands: &mut Commands, analytics: &mut AnalyticsWindow) {
+    let window = commands
+        .spawn(Window {
+            title: "Coinfection Analytics".to_owned(),
+            resolution: (900.0, 900.0).into(),
+            ..default()
+        })
+        .id();
+    let camera = commands
+        .spawn(Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                ..default()
+            },
+            ..default()
+        })
+        .id();
+    analytics.window = Some(window);
+    analytics.camera = Some(camera);
+}
+
+// F2 opens or closes the analytics window; closing it by hand returns plots to the main window
+pub fn toggle_analytics_window(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<(), With<Window>>,
+    mut analytics: ResMut<AnalyticsWindow>,
+    mut opened: Local<bool>,
+) {
+    if analytics.window.is_some_and(|window| windows.get(window).is_err()) {
+        if let Some(camera) = analytics.camera.take() {
+            commands.entity(camera).despawn();
+        }
+        analytics.window = None;
+    }
+
+    let open_now = !*opened && analytics.open_on_startup;
+    *opened = true;
+
+    if keys.just_pressed(KeyCode::F2) || open_now {
+        if let Some(window) = analytics.window.take() {
+            commands.entity(window).despawn();
+            if let Some(camera) = analytics.camera.take() {
+                commands.entity(camera).despawn();
+            }
+        } else {
+            spawn_analytics_window(&mut commands, &mut analytics);
+        }
+    }
+}
+
+// Context for analytics panels: the analytics window when open, otherwise the primary window
+pub fn analytics_ctx<'a>(contexts: &'a mut EguiContexts, analytics: &AnalyticsWindow) -> &'a mut egui::Context {
+    match analytics.window {
+        Some(window) if contexts.try_ctx_for_window_mut(window).is_some() => contexts.ctx_for_window_mut(window),
+        _ => contexts.ctx_mut(),
+    }
+}
+
+fn egui_color(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.as_rgba_u8();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn host_state_plot_ui(
+    mut contexts: EguiContexts,
+    history: Res<StateHistory>,
+    ode: Res<OdeModel>,
+    annotations: Res<Annotations>,
+    mut settings: ResMut<PlotSettings>,
+    analytics: Res<AnalyticsWindow>,
+    mut aggregation: ResMut<Aggregation>,
+    mut diagnostic: ResMut<Diagnostic>,
+    mut comparison: ResMut<RunComparison>,
+    blocked: Res<BlockedExposures>,
+) {
+    egui::Window::new("Host States")
+        .default_pos(egui::pos2(10.0, 650.0))
+        .default_size(egui::vec2(400.0, 200.0))
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.show_ode, "Show ODE (dashed)");
+                ui.checkbox(&mut settings.proportions, "Percent");
+                ui.checkbox(&mut settings.stacked, "Stacked");
+                ui.add_enabled(!settings.stacked, egui::Checkbox::new(&mut settings.log_scale, "Log scale"));
+
+                // Applies to all plots and exports
+                egui::ComboBox::from_id_source("aggregation")
+                    .selected_text(format!("{:?}", *aggregation))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut *aggregation, Aggregation::Daily, "Daily");
+                        ui.selectable_value(&mut *aggregation, Aggregation::Weekly, "Weekly");
+                        ui.selectable_value(&mut *aggregation, Aggregation::Monthly, "Monthly");
+                    });
+            });
+            ui.horizontal(|ui| {
+                for (index, state) in HostState::ALL.iter().enumerate() {
+                    ui.checkbox(&mut settings.visible[index], format!("{:?}", state));
+                }
+                ui.add_enabled(!settings.stacked, egui::Checkbox::new(&mut settings.prevalence, "True vs detected"));
+                ui.add_enabled(!settings.stacked, egui::Checkbox::new(&mut settings.by_sex, "By sex"));
+            });
+            if settings.prevalence && !settings.stacked {
+                // The gap between the lines is what a survey with this test would miss
+                ui.collapsing("Diagnostic", |ui| {
+                    let diagnostic = &mut diagnostic.0;
+                    ui.add(egui::Slider::new(&mut diagnostic.sensitivity_liver, 0.0..=1.0).text("Sensitivity, liver stage"));
+                    ui.add(egui::Slider::new(&mut diagnostic.sensitivity_acute, 0.0..=1.0).text("Sensitivity, acute"));
+                    ui.add(egui::Slider::new(&mut diagnostic.sensitivity_chronic, 0.0..=1.0).text("Sensitivity, chronic"));
+                    ui.add(egui::Slider::new(&mut diagnostic.specificity, 0.9..=1.0).text("Specificity"));
+                });
+            }
+            ui.collapsing(format!("Compare runs ({})", comparison.runs.len()), |ui| comparison.controls_ui(ui));
+            ui.label(format!("Exposures blocked: {} by prophylaxis, {} by immunity", blocked.by_prophylaxis, blocked.by_immunity));
+
+            let host_count = history.samples.last().map_or(0, |(_, counts)| counts.iter().sum::<usize>()) as f64;
+            let states = aggregation.states(&history);
+            let by_sex = settings.by_sex.then(|| aggregation.states_by_sex(&history));
+            let prevalence = settings.prevalence.then(|| aggregation.prevalence(&history, &diagnostic));
+            let ode_fractions = aggregation.series(ode.samples.iter().copied());
+            let saved_runs: Vec<_> = comparison.runs.iter().map(|run| (&run.name, aggregation.series(run.states.iter().copied()))).collect();
+
+            // ODE fractions are scaled to the current population when plotting counts
+            let ode_scale = if settings.proportions { 100.0 } else { host_count };
+            let log_scale = settings.log_scale && !settings.stacked;
+
+            Plot::new("host_states")
+                .legend(Legend::default())
+                .include_y(if log_scale { -1.0 } else { 0.0 })
+                .y_axis_label(if settings.proportions { "% of hosts" } else { "Hosts" })
+                .y_axis_formatter(move |mark, _, _| {
+                    if log_scale {
+                        format!("{}", 10f64.powf(mark.value))
+                    } else {
+                        format!("{}", mark.value)
+                    }
+                })
+                .show(ui, |plot_ui| {
+                    plot_annotations(plot_ui, &annotations);
+                    if settings.stacked {
+                        stacked_area(plot_ui, &states, &settings);
+                        return;
+                    }
+
+                    for (index, state) in HostState::ALL.iter().enumerate() {
+                        if !settings.visible[index] {
+                            continue;
+                        }
+                        let color = egui_color(state.color());
+
+                        let points: PlotPoints = states
+                            .iter()
+                            .filter_map(|(day, counts)| Some([*day as f64, settings.y(settings.value(counts, index))?]))
+                            .collect();
+                        plot_ui.line(Line::new(points).color(color).name(format!("{:?}", state)));
+
+                        // Percentages are of each sex's own hosts
+                        if let Some(by_sex) = &by_sex {
+                            for (offset, sex) in Sex::ALL.iter().enumerate() {
+                                let points: PlotPoints = by_sex
+                                    .iter()
+                                    .filter_map(|(day, counts)| {
+                                        let counts: [f64; 5] = counts[5 * offset..5 * offset + 5].try_into().ok()?;
+                                        Some([*day as f64, settings.y(settings.value(&counts, index))?])
+                                    })
+                                    .collect();
+                                let style = if offset == 0 { LineStyle::dotted_dense() } else { LineStyle::dotted_loose() };
+                                plot_ui.line(Line::new(points).color(color).style(style).name(format!("{:?} {}", state, sex.label())));
+                            }
+                        }
+
+                        if settings.show_ode {
+                            let points: PlotPoints = ode_fractions
+                                .iter()
+                                .filter_map(|(day, fractions)| Some([*day as f64, settings.y(fractions[index] * ode_scale)?]))
+                                .collect();
+                            plot_ui.line(
+                                Line::new(points)
+                                    .color(color)
+                                    .style(LineStyle::dashed_loose())
+                                    .name(format!("{:?}", state)),
+                            );
+                        }
+                    }
+                    // Earlier runs in the state colors, each with its own dash length
+                    for (run_index, (name, states)) in saved_runs.iter().enumerate() {
+                        for (index, state) in HostState::ALL.iter().enumerate().filter(|(index, _)| settings.visible[*index]) {
+                            let points: PlotPoints = states
+                                .iter()
+                                .filter_map(|(day, counts)| Some([*day as f64, settings.y(settings.value(counts, index))?]))
+                                .collect();
+                            plot_ui.line(
+                                Line::new(points)
+                                    .color(egui_color(state.color()).gamma_multiply(0.6))
+                                    .style(LineStyle::Dashed {
+                                        length: 6.0 + 4.0 * run_index as f32,
+                                    })
+                                    .name(format!("{}: {:?}", name, state)),
+                            );
+                        }
+                    }
+                    if let Some(prevalence) = &prevalence {
+                        prevalence_lines(plot_ui, prevalence, &settings);
+                    }
+                });
+        });
+}
+
+pub fn genotype_frequency_plot_ui(
+    mut contexts: EguiContexts,
+    history: Res<GenotypeHistory>,
+    resistance: Res<ResistanceHistory>,
+    annotations: Res<Annotations>,
+    params: Res<Params>,
+    analytics: Res<AnalyticsWindow>,
+    aggregation: Res<Aggregation>,
+) {
+    egui::Window::new("Genotype Frequencies")
+        .default_pos(egui::pos2(10.0, 400.0))
+        .default_size(egui::vec2(400.0, 200.0))
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            let samples = aggregation.genotypes(&history);
+            let genotypes: BTreeSet<_> = samples.iter().flat_map(|(_, freqs)| freqs.keys().copied()).collect();
+            let resistant = aggregation.series(resistance.samples.iter().map(|(day, fraction)| (*day, [*fraction])));
+
+            // Resistance readout, shown once resistant clones are in play
+            let show_resistance = params.resistant_fraction > 0.0 || genotypes.iter().any(|g| g.is_resistant());
+            if show_resistance {
+                let current = resistance.samples.last().map_or(0.0, |(_, fraction)| *fraction);
+                ui.label(format!("Resistant clones: {:.0}%", current * 100.0));
+            }
+
+            Plot::new("genotype_frequencies")
+                .legend(Legend::default())
+                .include_y(0.0)
+                .include_y(1.0)
+                .show(ui, |plot_ui| {
+                    plot_annotations(plot_ui, &annotations);
+                    for genotype in genotypes {
+                        let points: PlotPoints = samples
+                            .iter()
+                            .map(|(day, freqs)| [*day as f64, *freqs.get(&genotype).unwrap_or(&0.0) as f64])
+                            .collect();
+                        plot_ui.line(Line::new(points).name(genotype.label(params.n_loci)));
+                    }
+
+                    if show_resistance {
+                        let points: PlotPoints = resistant.iter().map(|(day, [fraction])| [*day as f64, *fraction]).collect();
+                        plot_ui.line(Line::new(points).color(egui::Color32::WHITE).width(3.0).name("Resistant (all)"));
+                    }
+                });
+        });
+}
@@ -0,0 +1,166 @@
+use rand::Rng;
+use std::f64::consts::{LN_2, SQRT_2};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// With --portable-math, the simulation's logs, exponentials and random draws avoid the platform's libm, whose
+// last-bit rounding differs between operating systems and CPUs. These use only +, -, *, / and sqrt in f64, which
+// IEEE 754 rounds exactly everywhere, with fixed term counts so every platform takes the same steps.
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    PORTABLE.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    PORTABLE.load(Ordering::Relaxed)
+}
+
+fn ln(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x.is_infinite() {
+        return x;
+    }
+    // x = m * 2^e with m in [sqrt(1/2), sqrt(2)), scaling subnormals up first
+    let (x, bias) = if x < f64::MIN_POSITIVE { (x * (1u64 << 54) as f64, 54) } else { (x, 0) };
+    let bits = x.to_bits();
+    let mut e = ((bits >> 52) & 0x7ff) as i64 - 1023 - bias;
+    let mut m = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | 0x3ff0_0000_0000_0000);
+    if m > SQRT_2 {
+        m /= 2.0;
+        e += 1;
+    }
+    // ln(m) = 2 atanh(s), with |s| < 0.172 so 12 odd terms reach full precision
+    let s = (m - 1.0) / (m + 1.0);
+    let s2 = s * s;
+    let mut series = 0.0;
+    for k in (0..12).rev() {
+        series = series * s2 + 1.0 / (2 * k + 1) as f64;
+    }
+    e as f64 * LN_2 + 2.0 * s * series
+}
+
+fn exp(x: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    if x > 709.8 {
+        return f64::INFINITY;
+    }
+    if x < -745.2 {
+        return 0.0;
+    }
+    // e^x = 2^k e^r with |r| <= ln(2)/2, where 20 Taylor terms reach full precision
+    let k = (x / LN_2).round();
+    let r = x - k * LN_2;
+    let mut series = 1.0;
+    for n in (1..=20).rev() {
+        series = 1.0 + series * r / n as f64;
+    }
+    // 2^k in two halves, so neither leaves the normal range
+    let pow2 = |k: i64| f64::from_bits(((k + 1023) as u64) << 52);
+    let k = k as i64;
+    series * pow2(k / 2) * pow2(k - k / 2)
+}
+
+// ln, exp and powf that switch to the portable versions under --portable-math, and are std's otherwise
+pub trait PortableFloat: Copy {
+    fn portable_ln(self) -> Self;
+    fn portable_exp(self) -> Self;
+    fn portable_powf(self, exponent: Self) -> Self;
+}
+
+impl PortableFloat for f64 {
+    fn portable_ln(self) -> f64 {
+        if enabled() { ln(self) } else { self.ln() }
+    }
+
+    fn portable_exp(self) -> f64 {
+        if enabled() { exp(self) } else { self.exp() }
+    }
+
+    fn portable_powf(self, exponent: f64) -> f64 {
+        if !enabled() {
+            self.powf(exponent)
+        } else if exponent == 0.0 {
+            1.0
+        } else if self == 0.0 {
+            if exponent > 0.0 { 0.0 } else { f64::INFINITY }
+        } else {
+            exp(exponent * ln(self))
+        }
+    }
+}
+
+impl PortableFloat for f32 {
+    fn portable_ln(self) -> f32 {
+        if enabled() { ln(self as f64) as f32 } else { self.ln() }
+    }
+
+    fn portable_exp(self) -> f32 {
+        if enabled() { exp(self as f64) as f32 } else { self.exp() }
+    }
+
+    fn portable_powf(self, exponent: f32) -> f32 {
+        if enabled() { (self as f64).portable_powf(exponent as f64) as f32 } else { self.powf(exponent) }
+    }
+}
+
+// Random draws built on the functions above, for use in place of rand_distr's when portable math is on. They
+// consume the random stream differently, so a portable run doesn't reproduce a default run with the same seed.
+
+// Uniform on (0, 1], safe to take the log of
+fn open_unit(rng: &mut (impl Rng + ?Sized)) -> f64 {
+    1.0 - rng.gen::<f64>()
+}
+
+pub fn exponential(rng: &mut (impl Rng + ?Sized), mean: f64) -> f64 {
+    -mean * ln(open_unit(rng))
+}
+
+// Marsaglia's polar method, keeping one of each pair
+pub fn standard_normal(rng: &mut (impl Rng + ?Sized)) -> f64 {
+    loop {
+        let x = 2.0 * rng.gen::<f64>() - 1.0;
+        let y = 2.0 * rng.gen::<f64>() - 1.0;
+        let s = x * x + y * y;
+        if s > 0.0 && s < 1.0 {
+            return x * (-2.0 * ln(s) / s).sqrt();
+        }
+    }
+}
+
+// Marsaglia and Tsang's method, boosted for shapes below 1
+pub fn gamma(rng: &mut (impl Rng + ?Sized), shape: f64, scale: f64) -> f64 {
+    if shape < 1.0 {
+        let boost = exp(ln(open_unit(rng)) / shape);
+        return gamma(rng, shape + 1.0, scale) * boost;
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let z = standard_normal(rng);
+        let v = 1.0 + c * z;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u = open_unit(rng);
+        if u < 1.0 - 0.0331 * z * z * z * z || ln(u) < 0.5 * z * z + d * (1.0 - v + ln(v)) {
+            return d * v * scale;
+        }
+    }
+}
+
+pub fn log_normal(rng: &mut (impl Rng + ?Sized), mu: f64, sigma: f64) -> f64 {
+    exp(mu + sigma * standard_normal(rng))
+}
+
+// One Bernoulli draw per trial; slower than rand_distr's for large n, but needs no transcendental functions at all
+pub fn binomial(rng: &mut (impl Rng + ?Sized), n: u32, probability: f64) -> u32 {
+    (0..n).filter(|_| rng.gen::<f64>() < probability).count() as u32
+}
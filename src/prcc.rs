@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+#[cfg(feature = "gui")]
+use egui_plot::{Bar, BarChart, Plot};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::sensitivity::OUTCOMES;
+use crate::OutputDir;
+
+// The runs in a sensitivity.csv: sampled param values and outcomes, one row per run
+pub struct SensitivityTable {
+    pub name: String,
+    pub params: Vec<String>,
+    pub outcomes: Vec<String>,
+    inputs: Vec<Vec<f64>>,   // One column per param
+    outputs: Vec<Vec<f64>>,  // One column per outcome
+}
+
+impl SensitivityTable {
+    // Params are the columns between `seed` and the first outcome; an empty elimination day (never eliminated)
+    // ranks after every day
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut lines = contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header: Vec<&str> = lines.next().map_or(Vec::new(), |(_, line)| line.split(',').map(str::trim).collect());
+        let first_param = header.iter().position(|field| *field == "seed").ok_or_else(|| format!("{}: no seed column", path.display()))? + 1;
+        let first_outcome = header
+            .iter()
+            .position(|field| OUTCOMES.contains(field))
+            .ok_or_else(|| format!("{}: no outcome columns", path.display()))?;
+        let params: Vec<String> = header[first_param..first_outcome].iter().map(|field| field.to_string()).collect();
+        let outcomes: Vec<String> = header[first_outcome..].iter().map(|field| field.to_string()).collect();
+        if params.is_empty() {
+            return Err(format!("{}: no param columns", path.display()));
+        }
+
+        let (mut inputs, mut outputs) = (vec![Vec::new(); params.len()], vec![Vec::new(); outcomes.len()]);
+        for (line_number, line) in lines {
+            let error = |message: String| format!("{}: line {}: {}", path.display(), line_number + 1, message);
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != header.len() {
+                return Err(error(format!("{} columns, expected {}", fields.len(), header.len())));
+            }
+            let parse = |field: &str| match field {
+                "" => Ok(f64::INFINITY),
+                field => field.parse::<f64>().map_err(|err| error(err.to_string())),
+            };
+            for (column, field) in inputs.iter_mut().zip(&fields[first_param..first_outcome]) {
+                column.push(parse(field)?);
+            }
+            for (column, field) in outputs.iter_mut().zip(&fields[first_outcome..]) {
+                column.push(parse(field)?);
+            }
+        }
+        if inputs[0].len() <= params.len() + 1 {
+            return Err(format!("{}: {} runs, too few for {} params", path.display(), inputs[0].len(), params.len()));
+        }
+
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        Ok(Self {
+            name,
+            params,
+            outcomes,
+            inputs,
+            outputs,
+        })
+    }
+
+    pub fn runs(&self) -> usize {
+        self.inputs[0].len()
+    }
+
+    // PRCC of each param with the outcome, None where it is undefined (e.g. an outcome that never varies)
+    pub fn prcc(&self, outcome: usize) -> Vec<Option<f64>> {
+        prcc(&self.inputs, &self.outputs[outcome])
+    }
+
+    pub fn outcome_index(&self, name: &str) -> Option<usize> {
+        self.outcomes.iter().position(|outcome| outcome == name)
+    }
+
+    // outcome,param,prcc for the given outcomes, blank where undefined
+    pub fn write_prcc(&self, path: &Path, outcomes: &[usize]) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "outcome,param,prcc")?;
+        for &index in outcomes {
+            for (param, value) in self.params.iter().zip(self.prcc(index)) {
+                writeln!(writer, "{},{},{}", self.outcomes[index], param, value.map_or(String::new(), |value| format!("{:.4}", value)))?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+// Ranks from 1, ties sharing the mean of their ranks
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &index in &order[start..end] {
+            ranks[index] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+// What's left of `target` after a least-squares fit on the predictors and an intercept, by Gaussian elimination on the
+// normal equations; None if the predictors are collinear
+fn residuals(predictors: &[&[f64]], target: &[f64]) -> Option<Vec<f64>> {
+    let n = target.len();
+    let design: Vec<Vec<f64>> = (0..n).map(|row| std::iter::once(1.0).chain(predictors.iter().map(|column| column[row])).collect()).collect();
+    let k = predictors.len() + 1;
+    let mut system: Vec<Vec<f64>> = (0..k)
+        .map(|i| {
+            let mut equation: Vec<f64> = (0..k).map(|j| design.iter().map(|row| row[i] * row[j]).sum()).collect();
+            equation.push(design.iter().zip(target).map(|(row, y)| row[i] * y).sum());
+            equation
+        })
+        .collect();
+    for column in 0..k {
+        let pivot = (column..k).max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+        if system[pivot][column].abs() < 1e-9 {
+            return None;
+        }
+        system.swap(column, pivot);
+        let pivot_row = system[column].clone();
+        for (row, equation) in system.iter_mut().enumerate() {
+            if row != column {
+                let factor = equation[column] / pivot_row[column];
+                for (value, pivot_value) in equation.iter_mut().zip(&pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let coefficients: Vec<f64> = (0..k).map(|i| system[i][k] / system[i][i]).collect();
+    Some(design.iter().zip(target).map(|(row, y)| y - row.iter().zip(&coefficients).map(|(x, b)| x * b).sum::<f64>()).collect())
+}
+
+fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+    let covariance: f64 = a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum();
+    let var_a: f64 = a.iter().map(|x| (x - mean_a).powi(2)).sum();
+    let var_b: f64 = b.iter().map(|y| (y - mean_b).powi(2)).sum();
+    (var_a > 1e-12 && var_b > 1e-12).then(|| covariance / (var_a * var_b).sqrt())
+}
+
+// Partial rank correlation coefficients: each param's rank correlation with the outcome once the (rank-linear)
+// effects of the other params are removed from both
+pub fn prcc(inputs: &[Vec<f64>], output: &[f64]) -> Vec<Option<f64>> {
+    let ranked: Vec<Vec<f64>> = inputs.iter().map(|column| ranks(column)).collect();
+    let output = ranks(output);
+    (0..ranked.len())
+        .map(|param| {
+            let others: Vec<&[f64]> = ranked.iter().enumerate().filter(|&(other, _)| other != param).map(|(_, column)| column.as_slice()).collect();
+            let param_residuals = residuals(&others, &ranked[param])?;
+            let output_residuals = residuals(&others, &output)?;
+            correlation(&param_residuals, &output_residuals)
+        })
+        .collect()
+}
+
+// A sensitivity.csv loaded in the Sensitivity window, with the outcome whose PRCCs are charted
+#[derive(Resource, Default)]
+pub struct SensitivityResults {
+    path: String,
+    table: Option<SensitivityTable>,
+    outcome: usize,
+    message: Option<String>, // Load error or where the CSV went
+}
+
+#[cfg(feature = "gui")]
+pub fn sensitivity_ui(mut contexts: EguiContexts, mut results: ResMut<SensitivityResults>, output_dir: Res<OutputDir>) {
+    egui::Window::new("Sensitivity")
+        .default_pos(egui::pos2(850.0, 300.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut results.path).hint_text("output/sensitivity.csv"));
+                if ui.add_enabled(!results.path.trim().is_empty(), egui::Button::new("Load")).clicked() {
+                    match SensitivityTable::load(&PathBuf::from(results.path.trim())) {
+                        Ok(table) => {
+                            results.table = Some(table);
+                            results.outcome = 0;
+                            results.message = None;
+                        }
+                        Err(err) => results.message = Some(err),
+                    }
+                }
+            });
+            if let Some(message) = &results.message {
+                ui.label(message);
+            }
+
+            let SensitivityResults { table, outcome, message, .. } = results.as_mut();
+            let Some(table) = table else {
+                return;
+            };
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("sensitivity_outcome").selected_text(&table.outcomes[*outcome]).show_ui(ui, |ui| {
+                    for (index, name) in table.outcomes.iter().enumerate() {
+                        ui.selectable_value(outcome, index, name);
+                    }
+                });
+                if ui.button("Export CSV").clicked() {
+                    let path = output_dir.0.join("sensitivity_prcc.csv");
+                    let result = fs::create_dir_all(&output_dir.0).and_then(|_| table.write_prcc(&path, &(0..table.outcomes.len()).collect::<Vec<_>>()));
+                    *message = Some(match result {
+                        Ok(()) => format!("Wrote {}", path.display()),
+                        Err(err) => format!("Failed to write {}: {}", path.display(), err),
+                    });
+                }
+            });
+            ui.label(format!("PRCC with {} in {} ({} runs)", table.outcomes[*outcome], table.name, table.runs()));
+
+            // Horizontal bars, one per param from the top, blue for a positive effect and red for a negative one
+            let values = table.prcc(*outcome);
+            let bars: Vec<Bar> = values
+                .iter()
+                .enumerate()
+                .filter_map(|(index, value)| {
+                    let value = (*value)?;
+                    let color = if value >= 0.0 { egui::Color32::LIGHT_BLUE } else { egui::Color32::LIGHT_RED };
+                    Some(Bar::new(-(index as f64), value).name(&table.params[index]).fill(color).width(0.7))
+                })
+                .collect();
+            let params = table.params.clone();
+            Plot::new("sensitivity_prcc")
+                .include_x(-1.0)
+                .include_x(1.0)
+                .height(30.0 + 25.0 * params.len() as f32)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .y_axis_width(4)
+                .y_axis_formatter(move |mark, _, _| {
+                    let index = -mark.value;
+                    if index >= 0.0 && index.fract() == 0.0 {
+                        params.get(index as usize).cloned().unwrap_or_default()
+                    } else {
+                        String::new()
+                    }
+                })
+                .show(ui, |plot_ui| plot_ui.bar_chart(BarChart::new(bars).horizontal()));
+            for (param, value) in table.params.iter().zip(&values) {
+                if value.is_none() {
+                    ui.label(format!("{}: undefined (no variation left to correlate)", param));
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tied_values_share_their_mean_rank() {
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 2.0, 3.0]), vec![4.0, 1.0, 4.0, 2.0, 4.0]);
+        assert_eq!(ranks(&[]), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn collinear_predictors_have_no_residuals() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let doubled = [2.0, 4.0, 6.0, 8.0];
+        assert!(residuals(&[&x, &doubled], &[1.0, 0.0, 1.0, 0.0]).is_none());
+        // A constant predictor is collinear with the intercept
+        assert!(residuals(&[&[5.0; 4]], &x).is_none());
+    }
+
+    #[test]
+    fn prcc_is_undefined_for_duplicated_inputs() {
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = vec![2.0, 5.0, 1.0, 4.0, 3.0];
+        let output = [1.0, 3.0, 2.0, 5.0, 4.0];
+        assert_eq!(prcc(&[a.clone(), a, b], &output), vec![None, None, None]);
+    }
+
+    #[test]
+    fn prcc_finds_a_monotone_effect() {
+        let a = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let b = vec![3.0, 1.0, 6.0, 2.0, 5.0, 4.0];
+        let output: Vec<f64> = a.iter().map(|x: &f64| x.powi(3)).collect();
+        let coefficients = prcc(&[a, b], &output);
+        assert!((coefficients[0].unwrap() - 1.0).abs() < 1e-9);
+    }
+}
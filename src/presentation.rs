@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode};
+use bevy_egui::EguiSettings;
+
+use crate::Host;
+
+const PRESENTATION_SCALE: f32 = 1.5; // Text, panels and hosts, for the back of a lecture hall
+
+// Borderless fullscreen with enlarged UI and hosts and the debugging panels hidden, for projectors
+#[derive(Resource, Default)]
+pub struct Presentation {
+    previous: Option<(WindowMode, f32, f32)>, // Window mode, egui scale and UI scale to restore on exit
+}
+
+impl Presentation {
+    pub fn is_active(&self) -> bool {
+        self.previous.is_some()
+    }
+}
+
+// Run condition for panels only meant for model development
+pub fn not_presenting(presentation: Res<Presentation>) -> bool {
+    !presentation.is_active()
+}
+
+// F11 enters or leaves presentation mode
+pub fn toggle_presentation(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut egui_settings: ResMut<EguiSettings>,
+    mut ui_scale: ResMut<UiScale>,
+    mut presentation: ResMut<Presentation>,
+) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+    let Ok(mut window) = window_query.get_single_mut() else {
+        return;
+    };
+
+    match presentation.previous.take() {
+        Some((mode, egui_scale, scale)) => {
+            window.mode = mode;
+            egui_settings.scale_factor = egui_scale;
+            ui_scale.0 = scale;
+        }
+        None => {
+            presentation.previous = Some((window.mode, egui_settings.scale_factor, ui_scale.0));
+            window.mode = WindowMode::BorderlessFullscreen;
+            egui_settings.scale_factor *= PRESENTATION_SCALE;
+            ui_scale.0 *= PRESENTATION_SCALE;
+        }
+    }
+}
+
+// Hosts and their inoculation stacks are drawn larger while presenting
+pub fn scale_hosts(presentation: Res<Presentation>, mut host_query: Query<&mut Transform, With<Host>>) {
+    let scale = Vec3::splat(if presentation.is_active() { PRESENTATION_SCALE } else { 1.0 });
+    for mut transform in host_query.iter_mut() {
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+#[cfg(feature = "export")]
+use base64::Engine;
+#[cfg(feature = "export")]
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+#[cfg(feature = "export")]
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::SystemTime;
+
+#[cfg(feature = "gui")]
+use crate::annotations::Annotations;
+use crate::blocked::BlockedExposures;
+use crate::autosave::write_atomic;
+use crate::clock::Throughput;
+use crate::infectiousness::InfectiousReservoir;
+use crate::ode::OdeModel;
+use crate::portable_math;
+use crate::rng::Subsystem;
+use crate::state_hash::StateHashes;
+use crate::stats::{Aggregation, GenotypeHistory, StateHistory};
+use crate::strata::RiskStrata;
+use crate::vectors::VectorPopulation;
+use crate::{HostState, InfectionState, Inoculation, OutputDir, Params, RunConfig, Sex, SimRng, SimulationTime};
+
+// Exit codes; 2 is for a scenario, snapshot or other input that fails to load
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_OUTPUT_FAILED: i32 = 1; // An output file could not be written
+pub const EXIT_INTERRUPTED: i32 = 3;   // Closed before reaching --end-day
+
+// Set once the run status is written, for main to exit with after the app returns
+pub static EXIT_CODE: AtomicI32 = AtomicI32::new(EXIT_SUCCESS);
+
+#[derive(Resource)]
+pub struct RunReport {
+    written: bool,
+    stop_reason: &'static str,
+    errors: Vec<String>,
+    status_written: bool,
+    started: SystemTime, // Files in the output directory modified before this are from earlier runs
+}
+
+impl Default for RunReport {
+    fn default() -> Self {
+        Self {
+            written: false,
+            stop_reason: "",
+            errors: Vec::new(),
+            status_written: false,
+            started: SystemTime::now(),
+        }
+    }
+}
+
+impl RunReport {
+    pub fn written(&self) -> bool {
+        self.written
+    }
+
+    // Fails the run; an output that keeps failing, e.g. every day on a full disk, is listed once
+    pub fn output_failed(&mut self, what: &str, err: impl std::fmt::Display) {
+        let prefix = format!("{}: ", what);
+        if self.errors.iter().any(|error| error.starts_with(&prefix)) {
+            return;
+        }
+        warn!("Failed to write {}: {}", what, err);
+        self.errors.push(format!("{}{}", prefix, err));
+    }
+}
+
+// run_status.json, also printed to stdout, for workflow tools to check how a run ended
+#[derive(Serialize)]
+struct RunStatus<'a> {
+    success: bool,
+    exit_code: i32,
+    stop_reason: &'a str, // "end_day", or "closed" when the window was closed first
+    days_simulated: u32,
+    end_day: Option<u32>,
+    seed: u64,
+    portable_math: bool,
+    state_hash: Option<String>, // Of the last day simulated, as in state_hash.csv
+    wall_seconds: f32,
+    output_dir: &'a PathBuf,
+    outputs: Vec<String>, // Files in the output directory written by this run
+    errors: &'a [String],
+}
+
+#[cfg(feature = "export")]
+const CHART_WIDTH: u32 = 640;
+#[cfg(feature = "export")]
+const CHART_HEIGHT: u32 = 240;
+#[cfg(feature = "export")]
+const CHART_MARGIN: u32 = 10;
+
+// Rasterizes a simple line chart; axes labels and legend are left to the surrounding HTML
+#[cfg(feature = "export")]
+fn line_chart_png(series: &[(Vec<[f64; 2]>, [u8; 3])]) -> Vec<u8> {
+    let mut image = RgbImage::from_pixel(CHART_WIDTH, CHART_HEIGHT, Rgb([255, 255, 255]));
+
+    let points = series.iter().flat_map(|(points, _)| points.iter());
+    let (x_max, y_max) = points.fold((1.0f64, 1.0f64), |(x, y), p| (x.max(p[0]), y.max(p[1])));
+    let to_pixel = |p: [f64; 2]| {
+        let x = CHART_MARGIN as f64 + p[0] / x_max * (CHART_WIDTH - 2 * CHART_MARGIN) as f64;
+        let y = (CHART_HEIGHT - CHART_MARGIN) as f64 - p[1] / y_max * (CHART_HEIGHT - 2 * CHART_MARGIN) as f64;
+        (x, y)
+    };
+
+    // Axes
+    for x in CHART_MARGIN..CHART_WIDTH - CHART_MARGIN {
+        image.put_pixel(x, CHART_HEIGHT - CHART_MARGIN, Rgb([0, 0, 0]));
+    }
+    for y in CHART_MARGIN..CHART_HEIGHT - CHART_MARGIN {
+        image.put_pixel(CHART_MARGIN, y, Rgb([0, 0, 0]));
+    }
+
+    for (points, color) in series {
+        for pair in points.windows(2) {
+            let (x0, y0) = to_pixel(pair[0]);
+            let (x1, y1) = to_pixel(pair[1]);
+            let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                if x >= 0.0 && y >= 0.0 && (x as u32) < CHART_WIDTH && (y as u32) < CHART_HEIGHT {
+                    image.put_pixel(x as u32, y as u32, Rgb(*color));
+                }
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .expect("encoding an in-memory PNG");
+    bytes
+}
+
+#[cfg(feature = "export")]
+fn embedded_chart(series: &[(Vec<[f64; 2]>, [u8; 3])]) -> String {
+    format!(
+        "<img src=\"data:image/png;base64,{}\">",
+        base64::engine::general_purpose::STANDARD.encode(line_chart_png(series))
+    )
+}
+
+#[cfg(not(feature = "export"))]
+fn embedded_chart(_series: &[(Vec<[f64; 2]>, [u8; 3])]) -> String {
+    "<p><i>Charts are left out of builds without the export feature</i></p>".to_owned()
+}
+
+#[cfg(feature = "gui")]
+pub fn rgb(color: Color) -> [u8; 3] {
+    let [r, g, b, _] = color.as_rgba_u8();
+    [r, g, b]
+}
+
+// A distinct color per genotype for the frequency chart
+#[cfg(feature = "gui")]
+fn genotype_color(index: usize) -> [u8; 3] {
+    let hue = (index as f32 * 137.5) % 360.0;
+    rgb(Color::hsl(hue, 0.7, 0.45))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[cfg(feature = "gui")]
+fn write_report(
+    output_dir: &OutputDir,
+    params: &Params,
+    sim_rng: &SimRng,
+    sim_time: &SimulationTime,
+    state_history: &StateHistory,
+    blocked: &BlockedExposures,
+    genotype_history: &GenotypeHistory,
+    ode: &OdeModel,
+    reservoir: &InfectiousReservoir,
+    vectors: &VectorPopulation,
+    strata: &RiskStrata,
+    annotations: &Annotations,
+    inoculations: &[&Inoculation],
+    aggregation: Aggregation,
+) -> std::io::Result<()> {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Coinfection run report</title>\n");
+    html.push_str("<style>body{font-family:sans-serif;max-width:720px;margin:auto}td,th{padding:2px 10px;text-align:right}</style>\n");
+    html.push_str("</head><body>\n<h1>Coinfection run report</h1>\n");
+    html.push_str(&format!("<p>Seed: <code>{}</code><br>Days simulated: {}</p>\n", sim_rng.seed, sim_time.day));
+
+    if !annotations.notes.is_empty() {
+        html.push_str("<h2>Annotations</h2>\n<ul>\n");
+        for (day, note) in &annotations.notes {
+            let note = note.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+            html.push_str(&format!("<li>Day {}: {}</li>\n", day, note));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("<h2>Random number draws</h2>\n<table>\n<tr><th>Subsystem</th><th>Draws</th></tr>\n");
+    for subsystem in Subsystem::ALL {
+        html.push_str(&format!("<tr><td>{:?}</td><td>{}</td></tr>\n", subsystem, sim_rng.total_draws[subsystem.index()]));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Parameters</h2>\n");
+    html.push_str(&format!("<pre>{:#?}</pre>\n", params));
+
+    html.push_str("<h2>Final statistics</h2>\n<table>\n<tr><th>State</th><th>Hosts</th><th>ODE expectation</th><th>Female</th><th>Male</th></tr>\n");
+    let final_counts = state_history.samples.last().map_or([0; 5], |(_, counts)| *counts);
+    let final_by_sex = state_history.by_sex.last().map_or([[0; 5]; 2], |(_, by_sex)| *by_sex);
+    let host_count: usize = final_counts.iter().sum();
+    let final_fractions = ode.samples.last().map_or([0.0; 5], |(_, fractions)| *fractions);
+    for (index, state) in HostState::ALL.iter().enumerate() {
+        html.push_str(&format!(
+            "<tr><td>{:?}</td><td>{}</td><td>{:.1}</td><td>{}</td><td>{}</td></tr>\n",
+            state,
+            final_counts[index],
+            final_fractions[index] * host_count as f64,
+            final_by_sex[0][index],
+            final_by_sex[1][index]
+        ));
+    }
+    html.push_str("</table>\n");
+
+    let blood_stage: Vec<_> = inoculations.iter().filter(|inoc| inoc.state != InfectionState::E).collect();
+    let genotypes: BTreeSet<_> = blood_stage.iter().map(|inoc| inoc.genotype).collect();
+    let infected_hosts = final_counts[2] + final_counts[3]; // Acute and chronic hosts carry blood-stage clones
+    let mean_moi = if infected_hosts > 0 { blood_stage.len() as f32 / infected_hosts as f32 } else { 0.0 };
+    let resistant = blood_stage.iter().filter(|inoc| inoc.genotype.is_resistant()).count();
+    let resistant_fraction = if blood_stage.is_empty() { 0.0 } else { resistant as f32 / blood_stage.len() as f32 };
+    html.push_str(&format!(
+        "<p>Blood-stage inoculations: {}<br>Mean MOI among infected hosts: {:.2}<br>Circulating genotypes: {}<br>Resistant clones: {:.1}%<br>Mean host infectiousness: {:.3}</p>\n",
+        blood_stage.len(),
+        mean_moi,
+        genotypes.len(),
+        resistant_fraction * 100.0,
+        reservoir.mean
+    ));
+    html.push_str(&format!(
+        "<p>Exposures blocked at the liver stage: {} by prophylaxis, {} by immunity</p>\n",
+        blocked.by_prophylaxis, blocked.by_immunity
+    ));
+
+    if !vectors.samples.is_empty() {
+        // Entomological inoculation rate over the whole run, scaled to a year
+        let mean_eir = vectors.samples.iter().map(|sample| sample.eir).sum::<f32>() / vectors.samples.len() as f32;
+        let mean_sporozoite_rate = vectors.samples.iter().map(|sample| sample.sporozoite_rate).sum::<f32>() / vectors.samples.len() as f32;
+        html.push_str(&format!(
+            "<p>Annual EIR: {:.1} infectious bites per host<br>Mean sporozoite rate: {:.2}%</p>\n",
+            365.0 * mean_eir,
+            100.0 * mean_sporozoite_rate
+        ));
+    }
+
+    html.push_str("<h2>Risk strata</h2>\n<table>\n<tr><th>Stratum</th><th>Hosts</th><th>Infections / host-year</th><th>Prevalence</th><th>Coverage</th><th>Acceptance</th></tr>\n");
+    for summary in strata.summaries() {
+        html.push_str(&format!(
+            "<tr><td>{} {}</td><td>{}</td><td>{:.2}</td><td>{:.1}%</td><td>{:.1}%</td><td>{:.1}%</td></tr>\n",
+            summary.dimension,
+            summary.band,
+            summary.hosts,
+            summary.incidence,
+            100.0 * summary.prevalence,
+            100.0 * summary.coverage,
+            100.0 * summary.acceptance
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str(&format!("<h2>Host states over time ({:?})</h2>\n<p>", aggregation));
+    let states = aggregation.states(state_history);
+    let mut series = Vec::new();
+    for (index, state) in HostState::ALL.iter().enumerate() {
+        let [r, g, b] = rgb(state.color());
+        let points = states.iter().map(|(day, counts)| [*day as f64, counts[index]]).collect();
+        series.push((points, [r, g, b]));
+        html.push_str(&format!("<span style=\"color:#{:02x}{:02x}{:02x}\">&#9632; {:?}</span> ", r, g, b, state));
+    }
+    html.push_str("</p>\n");
+    html.push_str(&embedded_chart(&series));
+
+    // Share of each sex's hosts carrying blood-stage parasites
+    html.push_str(&format!("\n<h2>Acute or chronic by sex ({:?})</h2>\n<p>", aggregation));
+    let by_sex = aggregation.states_by_sex(state_history);
+    let mut series = Vec::new();
+    for (offset, (sex, [r, g, b])) in Sex::ALL.iter().zip([[200, 60, 140], [50, 110, 200]]).enumerate() {
+        let points = by_sex
+            .iter()
+            .map(|(day, counts)| {
+                let counts = &counts[5 * offset..5 * offset + 5];
+                let hosts: f64 = counts.iter().sum();
+                [*day as f64, if hosts > 0.0 { (counts[2] + counts[3]) / hosts } else { 0.0 }]
+            })
+            .collect();
+        series.push((points, [r, g, b]));
+        html.push_str(&format!("<span style=\"color:#{:02x}{:02x}{:02x}\">&#9632; {}</span> ", r, g, b, sex.label()));
+    }
+    html.push_str("</p>\n");
+    html.push_str(&embedded_chart(&series));
+
+    html.push_str(&format!("\n<h2>Genotype frequencies over time ({:?})</h2>\n", aggregation));
+    let genotype_samples = aggregation.genotypes(genotype_history);
+    let all_genotypes: BTreeSet<_> = genotype_samples.iter().flat_map(|(_, freqs)| freqs.keys().copied()).collect();
+    let series: Vec<_> = all_genotypes
+        .iter()
+        .enumerate()
+        .map(|(index, genotype)| {
+            let points = genotype_samples
+                .iter()
+                .map(|(day, freqs)| [*day as f64, *freqs.get(genotype).unwrap_or(&0.0) as f64])
+                .collect();
+            (points, genotype_color(index))
+        })
+        .collect();
+    html.push_str(&embedded_chart(&series));
+    html.push_str("\n</body></html>\n");
+
+    fs::create_dir_all(&output_dir.0)?;
+    write_atomic(&output_dir.0.join("report.html"), html)
+}
+
+// Mean host counts per aggregation period, then the hosts of each sex (crowds have no sex)
+fn write_state_csv(output_dir: &OutputDir, state_history: &StateHistory, aggregation: Aggregation) -> std::io::Result<()> {
+    let mut csv = String::from("day,S,E,A,C,P");
+    for sex in Sex::ALL {
+        for state in HostState::ALL {
+            csv.push_str(&format!(",{}_{:?}", sex.label(), state));
+        }
+    }
+    csv.push('\n');
+    for ((day, counts), (_, by_sex)) in aggregation.states(state_history).iter().zip(aggregation.states_by_sex(state_history)) {
+        let values: Vec<String> = counts.iter().chain(&by_sex).map(|count| format!("{:.2}", count)).collect();
+        csv.push_str(&format!("{},{}\n", day, values.join(",")));
+    }
+
+    fs::create_dir_all(&output_dir.0)?;
+    write_atomic(&output_dir.0.join("host_states.csv"), csv)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[cfg(feature = "gui")]
+pub fn finish_run(
+    inoc_query: Query<&Inoculation>,
+    output_dir: Res<OutputDir>,
+    params: Res<Params>,
+    sim_rng: Res<SimRng>,
+    sim_time: Res<SimulationTime>,
+    run_config: Res<RunConfig>,
+    (state_history, blocked): (Res<StateHistory>, Res<BlockedExposures>),
+    genotype_history: Res<GenotypeHistory>,
+    ode: Res<OdeModel>,
+    reservoir: Res<InfectiousReservoir>,
+    vectors: Res<VectorPopulation>,
+    strata: Res<RiskStrata>,
+    annotations: Res<Annotations>,
+    aggregation: Res<Aggregation>,
+    mut report: ResMut<RunReport>,
+    mut exit_events: ParamSet<(EventReader<AppExit>, EventWriter<AppExit>)>,
+) {
+    let reached_end = run_config.end_day.is_some_and(|end_day| sim_time.day >= end_day);
+    let exiting = exit_events.p0().read().next().is_some();
+    if report.written || !(reached_end || exiting) {
+        return;
+    }
+
+    let inoculations: Vec<&Inoculation> = inoc_query.iter().collect();
+    match write_report(&output_dir, &params, &sim_rng, &sim_time, &state_history, &blocked, &genotype_history, &ode, &reservoir, &vectors, &strata, &annotations, &inoculations, *aggregation) {
+        Ok(()) => info!("Wrote run report to {}", output_dir.0.join("report.html").display()),
+        Err(err) => report.output_failed("run report", err),
+    }
+    if let Err(err) = write_state_csv(&output_dir, &state_history, *aggregation) {
+        report.output_failed("host state export", err);
+    }
+    if let Err(err) = strata.write_csv(&output_dir) {
+        report.output_failed("risk strata", err);
+    }
+    if let Err(err) = annotations.write_csv(&output_dir) {
+        report.output_failed("annotations", err);
+    }
+    report.written = true;
+    report.stop_reason = if reached_end { "end_day" } else { "closed" };
+
+    if reached_end && !exiting {
+        exit_events.p1().send(AppExit);
+    }
+}
+
+// Runs once the report and observers are done, so the output list is complete
+pub fn write_run_status(
+    mut report: ResMut<RunReport>,
+    output_dir: Res<OutputDir>,
+    sim_rng: Res<SimRng>,
+    sim_time: Res<SimulationTime>,
+    run_config: Res<RunConfig>,
+    throughput: Res<Throughput>,
+    state_hashes: Res<StateHashes>,
+) {
+    if !report.written || report.status_written {
+        return;
+    }
+    report.status_written = true;
+
+    let exit_code = if !report.errors.is_empty() {
+        EXIT_OUTPUT_FAILED
+    } else if run_config.end_day.is_some() && report.stop_reason != "end_day" {
+        EXIT_INTERRUPTED
+    } else {
+        EXIT_SUCCESS
+    };
+    let this_run = |entry: &fs::DirEntry| {
+        entry.metadata().and_then(|metadata| metadata.modified()).is_ok_and(|modified| modified >= report.started)
+    };
+    let mut outputs: Vec<String> = fs::read_dir(&output_dir.0)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok().filter(this_run)?.file_name().into_string().ok()).collect()
+        })
+        .unwrap_or_default();
+    outputs.retain(|name| !name.ends_with(".tmp") && name != "run_status.json");
+    outputs.sort();
+    let status = RunStatus {
+        success: exit_code == EXIT_SUCCESS,
+        exit_code,
+        stop_reason: report.stop_reason,
+        days_simulated: sim_time.day,
+        end_day: run_config.end_day,
+        seed: sim_rng.seed,
+        portable_math: portable_math::enabled(),
+        state_hash: state_hashes.latest.map(|(_, hash)| format!("{:016x}", hash)),
+        wall_seconds: throughput.elapsed_seconds(),
+        output_dir: &output_dir.0,
+        outputs,
+        errors: &report.errors,
+    };
+
+    let json = serde_json::to_string(&status).expect("run status serializes");
+    println!("{}", json);
+    let exit_code = match write_atomic(&output_dir.0.join("run_status.json"), &json) {
+        Ok(()) => exit_code,
+        Err(err) => {
+            warn!("Failed to write run status: {}", err);
+            EXIT_OUTPUT_FAILED
+        }
+    };
+    EXIT_CODE.store(exit_code, Ordering::Relaxed);
+}
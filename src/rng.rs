@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::RngCore;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::report::RunReport;
+use crate::{OutputDir, SimRng, SimulationTime};
+
+// Parts of the simulation that draw from SimRng, for the draw audit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    Spawn,       // Initial host population
+    Infection,   // New inoculations and their genotypes
+    Progression, // Inoculation state transitions
+    Treatment,
+    Mda,
+    CaseDetection,
+    Genotyping,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 7] = [
+        Subsystem::Spawn,
+        Subsystem::Infection,
+        Subsystem::Progression,
+        Subsystem::Treatment,
+        Subsystem::Mda,
+        Subsystem::CaseDetection,
+        Subsystem::Genotyping,
+    ];
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+// Borrowed view of the simulation RNG that counts the words drawn through it
+pub struct CountingRng<'a> {
+    pub(crate) rng: &'a mut StdRng,
+    pub(crate) daily: &'a mut u64,
+    pub(crate) total: &'a mut u64,
+}
+
+impl CountingRng<'_> {
+    fn count(&mut self) {
+        *self.daily += 1;
+        *self.total += 1;
+    }
+}
+
+impl RngCore for CountingRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        self.count();
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.count();
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.count();
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.count();
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[derive(Resource)]
+pub struct RngAuditOutput(BufWriter<File>);
+
+pub fn setup_rng_audit(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("rng_draws.csv"))?);
+        writeln!(writer, "day,subsystem,draws")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(RngAuditOutput(writer)),
+        Err(err) => report.output_failed("RNG audit", err),
+    }
+}
+
+// Writes each day's draw counts once the simulation has moved past it; draws in the frame a day
+// ticks over may land on either side of the boundary
+pub fn record_rng_usage(
+    sim_time: Res<SimulationTime>,
+    mut sim_rng: ResMut<SimRng>,
+    output: Option<ResMut<RngAuditOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    if sim_rng.audit_day == sim_time.day {
+        return;
+    }
+
+    let day = sim_rng.audit_day;
+    let draws = std::mem::take(&mut sim_rng.daily_draws);
+    sim_rng.audit_day = sim_time.day;
+
+    let Some(mut output) = output else {
+        return;
+    };
+    let mut result = Ok(());
+    for subsystem in Subsystem::ALL {
+        let count = draws[subsystem.index()];
+        if count > 0 {
+            result = result.and_then(|_| writeln!(output.0, "{},{:?},{}", day, subsystem, count));
+        }
+    }
+    if let Err(err) = result.and_then(|_| output.0.flush()) {
+        report.output_failed("RNG audit", err);
+    }
+}
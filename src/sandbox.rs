@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::budget::EntityBudget;
+use crate::cleared::{clear_inoculation, ClearCause};
+use crate::event_log::EventLog;
+use crate::ids::{HostId, Ids};
+use crate::scheduled::ScheduledActions;
+use crate::{spawn_inoculation, Genotype, Host, Inoculation, Params, Regimen, SimRng, SimulationTime};
+
+#[derive(Clone, Copy)]
+enum SandboxAction {
+    Infect,
+    ClearAll,
+    Mda,
+}
+
+// Whole-population actions from the Sandbox window, for trying things out on a live run
+#[derive(Resource)]
+pub struct Sandbox {
+    infect_count: u32,
+    mda_coverage: f32,
+    pub transmission_off: bool,
+    requested: Option<SandboxAction>,
+    draws: Option<StdRng>, // Host picks and genotypes, apart from the run's streams
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            infect_count: 5,
+            mda_coverage: 0.8,
+            transmission_off: false,
+            requested: None,
+            draws: None,
+        }
+    }
+}
+
+// Run condition for new infections, which the Sandbox window can switch off
+pub fn transmission_on(sandbox: Res<Sandbox>) -> bool {
+    !sandbox.transmission_off
+}
+
+pub fn sandbox_ui(mut contexts: EguiContexts, mut sandbox: ResMut<Sandbox>, sim_time: Res<SimulationTime>, mut event_log: ResMut<EventLog>) {
+    egui::Window::new("Sandbox")
+        .default_pos(egui::pos2(250.0, 350.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Infect").clicked() {
+                    sandbox.requested = Some(SandboxAction::Infect);
+                }
+                ui.add(egui::DragValue::new(&mut sandbox.infect_count).clamp_range(1..=1000).suffix(" random hosts"));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("MDA round").clicked() {
+                    sandbox.requested = Some(SandboxAction::Mda);
+                }
+                ui.add(egui::Slider::new(&mut sandbox.mda_coverage, 0.0..=1.0).text("coverage"));
+            });
+            if ui.button("Clear all infections").clicked() {
+                sandbox.requested = Some(SandboxAction::ClearAll);
+            }
+            if ui.checkbox(&mut sandbox.transmission_off, "Transmission off").changed() {
+                let state = if sandbox.transmission_off { "off" } else { "back on" };
+                event_log.log(sim_time.day, format!("Transmission switched {} in the sandbox", state));
+            }
+        });
+}
+
+// Carries out the requested action, noting it in the Event Log like the host menu's actions
+#[allow(clippy::too_many_arguments)]
+pub fn apply_sandbox_action(
+    mut commands: Commands,
+    mut sandbox: ResMut<Sandbox>,
+    mut host_query: Query<(Entity, &HostId, &mut ScheduledActions, Option<&Children>), With<Host>>,
+    inoc_query: Query<(Entity, &Inoculation, &Parent)>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    sim_rng: Res<SimRng>,
+    mut ids: ResMut<Ids>,
+    budget: Res<EntityBudget>,
+    mut event_log: ResMut<EventLog>,
+) {
+    let Some(action) = sandbox.requested.take() else {
+        return;
+    };
+    let day = sim_time.day;
+    let count = sandbox.infect_count as usize;
+    let coverage = sandbox.mda_coverage;
+    let rng = sandbox.draws.get_or_insert_with(|| StdRng::seed_from_u64(sim_rng.seed ^ 0x5a4d));
+
+    match action {
+        SandboxAction::Infect => {
+            let mut hosts: Vec<_> = host_query.iter().map(|(entity, id, _, children)| (*id, entity, children)).collect();
+            hosts.sort_by_key(|(id, ..)| *id);
+            let mut total = inoc_query.iter().count();
+            let mut infected = 0;
+            for (_, entity, children) in hosts.into_iter().choose_multiple(rng, count) {
+                if !budget.allows(children.map_or(0, |c| c.len()), total) {
+                    continue;
+                }
+                let genotype = Genotype::random(params.n_loci, params.resistant_fraction, rng);
+                spawn_inoculation(&mut commands, entity, children, genotype, &params, day, &mut ids);
+                total += 1;
+                infected += 1;
+            }
+            event_log.log(day, format!("Sandbox infected {} random hosts", infected));
+        }
+        SandboxAction::ClearAll => {
+            for (entity, inoc, parent) in inoc_query.iter() {
+                clear_inoculation(&mut commands, parent.get(), entity, inoc, day, ClearCause::Manual);
+            }
+            event_log.log(day, format!("Sandbox cleared all {} inoculations", inoc_query.iter().count()));
+        }
+        SandboxAction::Mda => {
+            let mut hosts: Vec<_> = host_query.iter_mut().collect();
+            hosts.sort_by_key(|(_, id, ..)| **id);
+            let mut reached = 0;
+            for (_, _, mut scheduled, _) in hosts {
+                if rng.gen::<f32>() < coverage {
+                    scheduled.request_treatment(day, Regimen::Mda);
+                    reached += 1;
+                }
+            }
+            event_log.log(day, format!("Sandbox MDA round reached {} hosts", reached));
+        }
+    }
+}
@@ -0,0 +1,908 @@
+use chrono::NaiveDate;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::distributions::DurationDist;
+use crate::migration;
+use crate::param_docs;
+use crate::{Drug, InfectionState, Params};
+
+fn set<T: Copy>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
+
+// Partial Drug, e.g. `treatment_drug = { cure_chronic = 0.5, duration_prophylaxis = 0 }`
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrugOverrides {
+    cure_liver: Option<f32>,
+    cure_acute: Option<f32>,
+    cure_chronic: Option<f32>,
+    duration_prophylaxis: Option<f32>,
+}
+
+impl DrugOverrides {
+    fn apply(&self, drug: &mut Drug) {
+        set(&mut drug.cure_liver, self.cure_liver);
+        set(&mut drug.cure_acute, self.cure_acute);
+        set(&mut drug.cure_chronic, self.cure_chronic);
+        set(&mut drug.duration_prophylaxis, self.duration_prophylaxis);
+    }
+}
+
+// Limits on treating the same host, e.g. `[treatment_policy]` with `max_courses = 6` or `switch_after_failures = 2`
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreatmentPolicyConfig {
+    pub max_courses: Option<u32>,           // Lifetime courses before toxicity rules out more
+    pub switch_after_failures: Option<u32>, // Failed courses before a host is given the second-line drug
+    second_line_drug: DrugOverrides,        // Changes from a fully effective drug
+}
+
+impl TreatmentPolicyConfig {
+    pub fn second_line_drug(&self) -> Drug {
+        let mut drug = Drug::default();
+        self.second_line_drug.apply(&mut drug);
+        drug
+    }
+}
+
+// Either [low, high] of a uniform, or a distribution such as `{ family = "gamma", mean = 25.0, cv = 0.5 }`
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum DurationOverride {
+    Range([f32; 2]),
+    Distribution(DurationDist),
+}
+
+// Partial Params: only the values present in a scenario file are overridden.  Plain numbers are any key in the
+// param registry, e.g. `incidence_rate = 0.05` or the shorthand `duration_prophylaxis = 14`
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct ParamOverrides {
+    treatment_drug: Option<DrugOverrides>,
+    mda_drug: Option<DrugOverrides>,
+    duration_acute: Option<DurationOverride>,
+    duration_chronic: Option<DurationOverride>,
+    treatment_delay: Option<DurationOverride>,
+    n_loci: Option<u32>,
+    #[serde(flatten)]
+    values: BTreeMap<String, f32>,
+}
+
+impl ParamOverrides {
+    pub fn apply(&self, params: &mut Params) {
+        fn set_range(target: &mut DurationDist, value: Option<DurationOverride>) {
+            match value {
+                Some(DurationOverride::Range([low, high])) => *target = DurationDist::uniform(low, high),
+                Some(DurationOverride::Distribution(dist)) => *target = dist,
+                None => {}
+            }
+        }
+
+        // The numbers first, so a drug table's own duration_prophylaxis wins over the shorthand
+        for (key, value) in &self.values {
+            if let Some(info) = param_docs::find(key) {
+                *(info.field)(params) = *value;
+            }
+        }
+        if let Some(drug) = &self.treatment_drug {
+            drug.apply(&mut params.treatment_drug);
+        }
+        if let Some(drug) = &self.mda_drug {
+            drug.apply(&mut params.mda_drug);
+        }
+        set_range(&mut params.duration_acute, self.duration_acute);
+        set_range(&mut params.duration_chronic, self.duration_chronic);
+        set_range(&mut params.treatment_delay, self.treatment_delay);
+        set(&mut params.n_loci, self.n_loci);
+    }
+
+    pub fn from_values(values: &[(&str, f32)]) -> Self {
+        Self {
+            values: values.iter().map(|&(key, value)| (key.to_owned(), value)).collect(),
+            ..Default::default()
+        }
+    }
+
+    // Keys that aren't in the param registry, usually typos
+    fn check_keys(&self) -> Result<(), String> {
+        match self.values.keys().find(|key| param_docs::find(key).is_none()) {
+            Some(key) => Err(format!("unknown param `{}`", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub params: ParamOverrides,
+    pub mda: Option<MdaConfig>, // Replaces the scenario's MDA schedule
+    pub snapshot: Option<PathBuf>, // Replaces the scenario's warm-start snapshot
+}
+
+// Mass drug administration rounds, treating a random fraction of all hosts regardless of infection
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MdaConfig {
+    pub start_day: u32,
+    pub interval_days: u32,
+    pub rounds: u32,
+    pub coverage: f32, // Fraction of hosts treated each round
+}
+
+impl Default for MdaConfig {
+    fn default() -> Self {
+        Self {
+            start_day: 30,
+            interval_days: 30,
+            rounds: 3,
+            coverage: 0.8,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StockOutRule {
+    Fail,  // The request is dropped
+    Delay, // The host is offered treatment again after `retry_days`
+}
+
+// Drug courses held for treatment, MDA and screening, replenished by regular deliveries
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrugStockConfig {
+    pub initial: u32,                     // Courses in stock on day 0
+    pub capacity: u32,                    // Most courses the store can hold
+    pub resupply_days: u32,               // Days between deliveries
+    pub resupply_amount: u32,             // Courses per delivery
+    pub missed_deliveries: Vec<[u32; 2]>, // [first_day, last_day] spans with no deliveries
+    pub on_stock_out: StockOutRule,
+    pub retry_days: u32,
+}
+
+impl Default for DrugStockConfig {
+    fn default() -> Self {
+        Self {
+            initial: 100,
+            capacity: 200,
+            resupply_days: 30,
+            resupply_amount: 50,
+            missed_deliveries: Vec::new(),
+            on_stock_out: StockOutRule::Delay,
+            retry_days: 7,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct KioskConfig {
+    pub idle_minutes: f32, // Reset after this long without input
+    pub cycle_presets: bool, // Move to the next preset on each reset
+}
+
+impl Default for KioskConfig {
+    fn default() -> Self {
+        Self {
+            idle_minutes: 5.0,
+            cycle_presets: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    pub seconds_per_day: f32, // Wall-clock seconds per simulated day at 1x speed
+    pub start_date: Option<NaiveDate>, // Show dates instead of day numbers, e.g. "2025-03-01"
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            seconds_per_day: 1.0,
+            start_date: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AcdTrigger {
+    Scheduled, // Rounds on a fixed calendar
+    Reactive,  // A round after every `case_threshold` clinical cases
+}
+
+// Active case detection: screen a fraction of hosts with a diagnostic and treat the positives
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AcdConfig {
+    pub trigger: AcdTrigger,
+    pub start_day: u32,
+    pub interval_days: u32,
+    pub rounds: u32,
+    pub case_threshold: u32,
+    pub coverage: f32, // Fraction of hosts screened each round
+    pub sensitivity_liver: f32, // Chance the diagnostic detects an inoculation, by its state
+    pub sensitivity_acute: f32,
+    pub sensitivity_chronic: f32,
+}
+
+impl Default for AcdConfig {
+    fn default() -> Self {
+        Self {
+            trigger: AcdTrigger::Scheduled,
+            start_day: 30,
+            interval_days: 30,
+            rounds: 6,
+            case_threshold: 3,
+            coverage: 0.5,
+            sensitivity_liver: 0.0,
+            sensitivity_acute: 0.95,
+            sensitivity_chronic: 0.5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum TrialIntervention {
+    // A course of the MDA drug at enrollment, repeated every `repeat_days` if set
+    Chemoprevention { repeat_days: Option<u32> },
+    // A leaky vaccine at enrollment that blocks each infectious exposure with probability `efficacy`
+    Vaccine { efficacy: f32 },
+}
+
+// Virtual follow-up study: enroll a random cohort and count incident infections, optionally against an intervention
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrialConfig {
+    pub enroll_day: u32,
+    pub size: usize, // Hosts enrolled, or all hosts if fewer
+    pub follow_up_days: u32,
+    pub intervention: Option<TrialIntervention>, // Given to a randomized half of the cohort
+}
+
+impl Default for TrialConfig {
+    fn default() -> Self {
+        Self {
+            enroll_day: 30,
+            size: 20,
+            follow_up_days: 180,
+            intervention: None,
+        }
+    }
+}
+
+// Test-negative case-control study among hosts seeking care for fever, tested with the [diagnostic]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestNegativeConfig {
+    pub fever_rate: f32,      // Fevers from other causes that bring a host to care, per host per day
+    pub mda_window_days: u32,  // An MDA course taken this recently counts as intervention exposure
+}
+
+impl Default for TestNegativeConfig {
+    fn default() -> Self {
+        Self {
+            fever_rate: 0.005,
+            mda_window_days: 30,
+        }
+    }
+}
+
+// Per-host comorbidity score, raising the odds of acute disease by the comorbidity_odds_ratio param per unit;
+// host locations with a comorbidity column or property set the score of the host at each point instead
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ComorbidityConfig {
+    pub mean_score: f32, // Of the exponential the other scores are drawn from
+}
+
+impl Default for ComorbidityConfig {
+    fn default() -> Self {
+        Self { mean_score: 0.5 }
+    }
+}
+
+// Test used for reported prevalence, e.g. `[diagnostic]` with `sensitivity_chronic = 0.3` for microscopy
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticConfig {
+    pub sensitivity_liver: f32, // Chance a host in each state tests positive
+    pub sensitivity_acute: f32,
+    pub sensitivity_chronic: f32,
+    pub specificity: f32, // Chance an uninfected host tests negative
+}
+
+impl Default for DiagnosticConfig {
+    fn default() -> Self {
+        Self {
+            sensitivity_liver: 0.0,
+            sensitivity_acute: 0.95,
+            sensitivity_chronic: 0.5,
+            specificity: 1.0,
+        }
+    }
+}
+
+impl DiagnosticConfig {
+    pub fn sensitivity(&self, state: InfectionState) -> f32 {
+        match state {
+            InfectionState::E => self.sensitivity_liver,
+            InfectionState::A => self.sensitivity_acute,
+            InfectionState::C => self.sensitivity_chronic,
+        }
+    }
+
+    // Tests one host: positive if any inoculation is detected, or falsely positive otherwise
+    pub fn test(&self, states: impl IntoIterator<Item = InfectionState>, rng: &mut impl Rng) -> bool {
+        let detected = states.into_iter().any(|state| rng.gen::<f32>() < self.sensitivity(state));
+        detected || rng.gen::<f32>() >= self.specificity
+    }
+
+    // Expected positives among hosts counted in HostState::ALL order; hosts on prophylaxis count as uninfected
+    pub fn detected(&self, counts: &[f64; 5]) -> f64 {
+        let [s, e, a, c, p] = *counts;
+        let sensitivities = [self.sensitivity_liver, self.sensitivity_acute, self.sensitivity_chronic];
+        let infected: f64 = [e, a, c].iter().zip(sensitivities).map(|(count, sensitivity)| count * sensitivity as f64).sum();
+        infected + (s + p) * (1.0 - self.specificity as f64)
+    }
+}
+
+// Mosquito population with logistic births up to a rainfall-driven carrying capacity. Vectors are infected by
+// biting infectious hosts and become infectious once they survive the temperature-dependent extrinsic incubation period.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VectorConfig {
+    pub rainfall: Vec<f32>,      // mm per period, repeated once the series runs out
+    pub temperature: Vec<f32>,   // Mean °C per period, repeated likewise
+    pub period_days: u32,        // Days covered by each rainfall and temperature value
+    pub birth_rate: f32,         // Per vector per day, at low density
+    pub death_rate: f32,         // Per vector per day
+    pub capacity_dry: f32,       // Carrying capacity per host from permanent breeding sites
+    pub capacity_per_mm: f32,    // Carrying capacity per host added by each mm of rain
+    pub biting_rate: f32,        // Bites on hosts per vector per day
+    pub eip_degree_days: f32,    // Degree-days above eip_min_temperature to complete sporogony
+    pub eip_min_temperature: f32, // °C below which parasites don't develop in the vector
+}
+
+impl Default for VectorConfig {
+    fn default() -> Self {
+        Self {
+            // A single rainy season mid-year, by month
+            rainfall: vec![5.0, 5.0, 10.0, 40.0, 120.0, 200.0, 250.0, 220.0, 150.0, 60.0, 15.0, 5.0],
+            temperature: vec![27.0],
+            period_days: 30,
+            birth_rate: 0.3,
+            death_rate: 0.1,
+            capacity_dry: 10.0,
+            capacity_per_mm: 1.0,
+            biting_rate: 0.3,
+            eip_degree_days: 111.0,
+            eip_min_temperature: 16.0,
+        }
+    }
+}
+
+impl VectorConfig {
+    fn on_day(&self, series: &[f32], day: u32) -> f32 {
+        if series.is_empty() {
+            return 0.0;
+        }
+        series[(day / self.period_days.max(1)) as usize % series.len()]
+    }
+
+    pub fn rainfall_on(&self, day: u32) -> f32 {
+        self.on_day(&self.rainfall, day)
+    }
+
+    pub fn temperature_on(&self, day: u32) -> f32 {
+        self.on_day(&self.temperature, day)
+    }
+
+    pub fn capacity(&self, rainfall: f32) -> f32 {
+        self.capacity_dry + self.capacity_per_mm * rainfall
+    }
+
+    // Stable population size for a fixed carrying capacity
+    pub fn equilibrium(&self, capacity: f32) -> f32 {
+        (capacity * (1.0 - self.death_rate / self.birth_rate)).max(0.0)
+    }
+
+    // Fraction of the extrinsic incubation period completed per day
+    pub fn sporogony_rate(&self, temperature: f32) -> f32 {
+        (temperature - self.eip_min_temperature).max(0.0) / self.eip_degree_days
+    }
+}
+
+// Transmission suitability over the host area, from a CSV grid of numbers or a grayscale image
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    pub raster: PathBuf,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VenueKind {
+    School,
+    Work,
+    #[default]
+    Community,
+}
+
+impl VenueKind {
+    // Mixing intensity Monday to Sunday
+    fn weekly(&self) -> [f32; 7] {
+        match self {
+            VenueKind::School => [1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0],
+            VenueKind::Work => [1.0, 1.0, 1.0, 1.0, 1.0, 0.3, 0.0],
+            VenueKind::Community => [0.3, 0.3, 0.3, 0.3, 0.3, 1.0, 1.0],
+        }
+    }
+}
+
+// A place where members mix and infect each other, e.g.
+//
+//   [[venues]]
+//   name = "Primary school"
+//   kind = "school"
+//   membership = 0.4
+//   closures = [[60, 90]]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VenueConfig {
+    pub name: String,
+    pub kind: VenueKind,
+    pub membership: f32,          // Fraction of hosts who belong
+    pub weekly: Option<[f32; 7]>, // Mixing intensity Monday to Sunday, defaulting by kind
+    pub contact_rate: f32,        // Daily infection chance of a member when every member is fully infectious
+    pub closures: Vec<[u32; 2]>,  // First and last day of each closure
+}
+
+impl Default for VenueConfig {
+    fn default() -> Self {
+        Self {
+            name: "Venue".to_owned(),
+            kind: VenueKind::Community,
+            membership: 0.5,
+            weekly: None,
+            contact_rate: 0.05,
+            closures: Vec::new(),
+        }
+    }
+}
+
+impl VenueConfig {
+    pub fn weekly(&self) -> [f32; 7] {
+        self.weekly.unwrap_or_else(|| self.kind.weekly())
+    }
+}
+
+// Hosts whose work changes their exposure to vectors, e.g. forest workers who are only exposed on trips:
+//
+//   [[occupations]]
+//   name = "Forest worker"
+//   fraction = 0.1
+//   exposure = 5.0
+//   weekly = [1, 1, 1, 1, 1, 0, 0]
+//   trips = { every_days = 28, length_days = 7 }
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct OccupationConfig {
+    pub name: String,
+    pub fraction: f32,          // Of hosts with this occupation; what the occupations leave over have none
+    pub exposure: f32,          // Multiplier on the vector-borne infection rate while at work
+    pub weekly: [f32; 7],       // Share of each day Monday to Sunday spent at work
+    pub trips: Option<TripSchedule>,
+}
+
+impl Default for OccupationConfig {
+    fn default() -> Self {
+        Self {
+            name: "Occupation".to_owned(),
+            fraction: 0.1,
+            exposure: 2.0,
+            weekly: [1.0; 7],
+            trips: None,
+        }
+    }
+}
+
+// Work only happens during trips of length_days, one starting every every_days from day 0
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TripSchedule {
+    pub every_days: u32,
+    pub length_days: u32,
+}
+
+impl OccupationConfig {
+    // Multiplier on the vector-borne infection rate on this day
+    pub fn exposure_on(&self, day: u32, weekday: usize) -> f32 {
+        let on_trip = self.trips.is_none_or(|trips| day % trips.every_days.max(1) < trips.length_days);
+        if !on_trip {
+            return 1.0;
+        }
+        1.0 + (self.exposure - 1.0) * self.weekly[weekday]
+    }
+}
+
+// Host-to-host transmission by age group, e.g.
+//
+//   [age_mixing]
+//   matrix = "assets/age_mixing.csv"
+//   contact_rate = 0.02
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AgeMixingConfig {
+    pub matrix: PathBuf, // Daily contacts between age groups, POLYMOD style
+    #[serde(default = "default_contact_rate")]
+    pub contact_rate: f32, // Infection chance per daily contact with a fully infectious host
+}
+
+fn default_contact_rate() -> f32 {
+    0.02
+}
+
+// Surveillance alarm, e.g. `[[alerts]]` with `rule = "prevalence"` and `threshold = 0.3`
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "rule", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AlertRule {
+    IncidenceDoubling {
+        #[serde(default = "default_min_cases")]
+        min_cases: u32, // Treated cases needed in the week, so 1 -> 2 doesn't count
+    },
+    FirstResistant, // A treated host still carries a resistant clone
+    Prevalence {
+        threshold: f32, // Fraction of hosts acute or chronic
+    },
+}
+
+fn default_min_cases() -> u32 {
+    3
+}
+
+// Scenario file, e.g.
+//
+//   format_version = 1  # Written by merge-scenarios; files without one are read as version 1
+//   host_locations = "assets/village.geojson"
+//   term_calendar = "assets/school_terms.csv"
+//   snapshot = "assets/snapshots/endemic.json"
+//
+//   [params]
+//   incidence_rate = 0.15
+//   duration_acute = [10.0, 40.0]
+//
+//   [[presets]]
+//   name = "High transmission"
+//   params = { incidence_rate = 0.2 }
+//
+//   [mda]
+//   start_day = 60
+//   rounds = 4
+//
+//   [acd]
+//   trigger = "reactive"
+//   case_threshold = 2
+//
+//   [treatment_policy]
+//   switch_after_failures = 2
+//   second_line_drug = { duration_prophylaxis = 28 }
+//
+//   [diagnostic]
+//   sensitivity_chronic = 0.3
+//
+//   [trial]
+//   enroll_day = 60
+//   intervention = { kind = "vaccine", efficacy = 0.6 }
+//
+//   [test_negative]
+//   fever_rate = 0.01
+//
+//   [comorbidity]
+//   mean_score = 0.3
+//
+//   [drug_stock]
+//   initial = 40
+//   missed_deliveries = [[90, 180]]
+//
+//   [vectors]
+//   rainfall = [0.0, 0.0, 50.0, 200.0]
+//   temperature = [22.0, 25.0, 28.0, 26.0]
+//   period_days = 90
+//
+//   [environment]
+//   raster = "assets/suitability.csv"
+//
+//   [[alerts]]
+//   rule = "incidence_doubling"
+//   min_cases = 5
+//
+//   [clock]
+//   seconds_per_day = 0.5
+//   start_date = "2025-01-01"
+//
+//   [kiosk]
+//   idle_minutes = 3
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub format_version: u32,
+    pub host_locations: Option<PathBuf>, // GeoJSON or CSV points, one host each
+    pub term_calendar: Option<PathBuf>,  // School terms and holidays for venue mixing
+    pub snapshot: Option<PathBuf>,       // Hosts to start from, written by --write-snapshot
+    pub params: ParamOverrides,
+    pub clock: ClockConfig,
+    pub presets: Vec<Preset>,
+    pub kiosk: Option<KioskConfig>,
+    pub mda: Option<MdaConfig>,
+    pub acd: Option<AcdConfig>,
+    pub treatment_policy: TreatmentPolicyConfig,
+    pub drug_stock: Option<DrugStockConfig>,
+    pub diagnostic: DiagnosticConfig,
+    pub trial: Option<TrialConfig>,
+    pub test_negative: Option<TestNegativeConfig>,
+    pub comorbidity: Option<ComorbidityConfig>,
+    pub vectors: Option<VectorConfig>,
+    pub environment: Option<EnvironmentConfig>,
+    pub venues: Vec<VenueConfig>,
+    pub occupations: Vec<OccupationConfig>,
+    pub age_mixing: Option<AgeMixingConfig>,
+    pub alerts: Vec<AlertRule>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Self::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    // Files in the current format are parsed as written, so errors keep their line numbers
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut table: toml::Table = contents.parse().map_err(|err: toml::de::Error| err.to_string())?;
+        let scenario: Self = if migration::upgrade(&mut table, SCENARIO_MIGRATIONS)? {
+            table.try_into().map_err(|err: toml::de::Error| err.to_string())?
+        } else {
+            toml::from_str(contents).map_err(|err| err.to_string())?
+        };
+        scenario.params.check_keys()?;
+        for preset in &scenario.presets {
+            preset.params.check_keys().map_err(|err| format!("preset {}: {}", preset.name, err))?;
+        }
+        if let Some(occupation) = scenario.occupations.iter().find(|occupation| !(0.0..=1.0).contains(&occupation.fraction)) {
+            return Err(format!("occupation {}: fraction = {} must be 0 to 1", occupation.name, occupation.fraction));
+        }
+        // Hosts are drawn into the occupations in order, so any share past 1 would silently go to nobody
+        let total: f32 = scenario.occupations.iter().map(|occupation| occupation.fraction).sum();
+        if total > 1.0 + 1e-4 {
+            return Err(format!("occupation fractions sum to {}, more than 1", total));
+        }
+        Ok(scenario)
+    }
+
+    // Alerts from the file, or weekly incidence doubling and the first resistant case if it defines none
+    pub fn alerts_or_builtin(&self) -> Vec<AlertRule> {
+        if !self.alerts.is_empty() {
+            return self.alerts.clone();
+        }
+        vec![
+            AlertRule::IncidenceDoubling {
+                min_cases: default_min_cases(),
+            },
+            AlertRule::FirstResistant,
+        ]
+    }
+
+    // Presets from the file, or the built-in ones if it defines none
+    pub fn presets_or_builtin(&self) -> Vec<Preset> {
+        if !self.presets.is_empty() {
+            return self.presets.clone();
+        }
+
+        let preset = |name: &str, params: ParamOverrides| Preset {
+            name: name.to_owned(),
+            params,
+            mda: None,
+            snapshot: None,
+        };
+        vec![
+            preset("Baseline", ParamOverrides::default()),
+            preset(
+                "High transmission",
+                ParamOverrides::from_values(&[("incidence_rate", 0.2)]),
+            ),
+            preset(
+                "Poor access to treatment",
+                ParamOverrides::from_values(&[("prob_treatment", 0.1)]),
+            ),
+            // Repeated MDA plus frequent treatment selects for the rare resistant clones
+            Preset {
+                mda: Some(MdaConfig {
+                    start_day: 60,
+                    interval_days: 30,
+                    rounds: 6,
+                    coverage: 0.8,
+                }),
+                ..preset(
+                    "Drug pressure",
+                    ParamOverrides::from_values(&[("prob_treatment", 0.6), ("resistant_fraction", 0.05), ("resistance_cost_duration", 0.3)]),
+                )
+            },
+        ]
+    }
+}
+
+// Migrations for scenario files written in an older format; none yet, as the first versioned format is the original
+const SCENARIO_MIGRATIONS: &[fn(&mut toml::Table)] = &[];
+
+// A scenario file as plain TOML in the current format, after checking that it loads as a scenario
+fn load_table(path: &Path) -> Result<toml::Table, String> {
+    Scenario::load(path)?;
+    let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut table: toml::Table = contents.parse().map_err(|err| format!("{}: {}", path.display(), err))?;
+    migration::upgrade(&mut table, SCENARIO_MIGRATIONS).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(table)
+}
+
+type Difference = (String, Option<toml::Value>, Option<toml::Value>);
+
+// Every key whose value differs, as a dotted path with the value on each side (None where it is missing).  Tables are
+// compared key by key, a missing table as an empty one, and arrays of the same length element by element, e.g.
+// `venues[1].contact_rate`
+fn differences(path: &str, a: Option<&toml::Value>, b: Option<&toml::Value>, found: &mut Vec<Difference>) {
+    let empty = toml::Value::Table(toml::Table::new());
+    let (a, b) = match (a, b) {
+        (None, Some(toml::Value::Table(_))) => (Some(&empty), b),
+        (Some(toml::Value::Table(_)), None) => (a, Some(&empty)),
+        _ => (a, b),
+    };
+    match (a, b) {
+        (Some(toml::Value::Table(a)), Some(toml::Value::Table(b))) => {
+            let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                differences(&child, a.get(key), b.get(key), found);
+            }
+        }
+        (Some(toml::Value::Array(a)), Some(toml::Value::Array(b))) if a.len() == b.len() => {
+            for (index, (a, b)) in a.iter().zip(b).enumerate() {
+                differences(&format!("{}[{}]", path, index), Some(a), Some(b), found);
+            }
+        }
+        (a, b) if a != b => found.push((path.to_owned(), a.cloned(), b.cloned())),
+        _ => {}
+    }
+}
+
+// A missing value, with the default it falls back to for a registry param
+fn describe_missing(path: &str) -> String {
+    match path.strip_prefix("params.").and_then(param_docs::find) {
+        Some(info) => format!("(default {})", (info.field)(&mut Params::default())),
+        None => "(unset)".to_owned(),
+    }
+}
+
+// Prints the keys that differ between two scenarios with their old and new values; the exit code is 0 if they are
+// the same, 1 if they differ and 2 if either can't be loaded
+pub fn diff_scenarios(a_path: &Path, b_path: &Path) -> i32 {
+    let (a, b) = match (load_table(a_path), load_table(b_path)) {
+        (Ok(a), Ok(b)) => (toml::Value::Table(a), toml::Value::Table(b)),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("Failed to load scenario: {}", err);
+            return 2;
+        }
+    };
+    println!("a: {}", a_path.display());
+    println!("b: {}", b_path.display());
+
+    let mut found = Vec::new();
+    differences("", Some(&a), Some(&b), &mut found);
+    if found.is_empty() {
+        println!("Identical");
+        return 0;
+    }
+    for (path, old, new) in &found {
+        let old = old.as_ref().map_or_else(|| describe_missing(path), toml::Value::to_string);
+        let new = new.as_ref().map_or_else(|| describe_missing(path), toml::Value::to_string);
+        println!("{}: {} -> {}", path, old, new);
+    }
+    println!("{} keys differ", found.len());
+    1
+}
+
+// Layers `overrides` onto `base`: tables merge key by key, anything else (including arrays such as [[venues]]) is
+// replaced whole
+fn merge_table(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge_table(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// Merges each override file in turn onto the base scenario and prints the result, or writes it to `output`; the
+// exit code is 0 on success and 2 if a file can't be loaded or the merged scenario is invalid
+pub fn merge_scenarios(base_path: &Path, override_paths: &[PathBuf], output: Option<&Path>) -> i32 {
+    let paths: Vec<&Path> = std::iter::once(base_path).chain(override_paths.iter().map(PathBuf::as_path)).collect();
+    let merged = paths.iter().try_fold(toml::Table::new(), |mut merged, path| {
+        merge_table(&mut merged, load_table(path)?);
+        Ok::<_, String>(merged)
+    });
+    let merged = match merged {
+        Ok(merged) => merged,
+        Err(err) => {
+            eprintln!("Failed to load scenario: {}", err);
+            return 2;
+        }
+    };
+
+    let sources: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+    let contents = match toml::to_string(&merged) {
+        Ok(body) => format!("# Merged from {}\n\n{}", sources.join(", "), body),
+        Err(err) => {
+            eprintln!("Failed to write merged scenario: {}", err);
+            return 2;
+        }
+    };
+    if let Err(err) = Scenario::parse(&contents) {
+        eprintln!("Merged scenario is invalid: {}", err);
+        return 2;
+    }
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(path, contents) {
+                eprintln!("Failed to write {}: {}", path.display(), err);
+                return 2;
+            }
+        }
+        None => print!("{}", contents),
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_layers_tables_and_replaces_arrays() {
+        let mut base = table(
+            "seed = 1\n[params]\nn_hosts = 100\nprob_treatment = 0.5\n[[venues]]\nname = \"market\"\n[[venues]]\nname = \"school\"",
+        );
+        merge_table(&mut base, table("[params]\nn_hosts = 200\n[[venues]]\nname = \"church\""));
+        assert_eq!(
+            base,
+            table("seed = 1\n[params]\nn_hosts = 200\nprob_treatment = 0.5\n[[venues]]\nname = \"church\"")
+        );
+    }
+
+    #[test]
+    fn differences_are_dotted_paths() {
+        let a = toml::Value::Table(table("[params]\nn_hosts = 100\n[[venues]]\ncontact_rate = 1.0"));
+        let b = toml::Value::Table(table("[params]\nn_hosts = 100\n[[venues]]\ncontact_rate = 2.0\n[diagnostic]\nspecificity = 0.99"));
+        let mut found = Vec::new();
+        differences("", Some(&a), Some(&b), &mut found);
+        let paths: Vec<&str> = found.iter().map(|(path, _, _)| path.as_str()).collect();
+        assert_eq!(paths, ["diagnostic.specificity", "venues[0].contact_rate"]);
+        assert_eq!(found[0].1, None);
+    }
+}
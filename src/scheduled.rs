@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::Regimen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+pub enum HostAction {
+    Treat(Regimen),  // Offer a course of the regimen's drug
+    EndProphylaxis,
+}
+
+// An action due at the start of a simulation day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+pub struct ScheduledAt(pub u32, pub HostAction);
+
+// A host's pending timed actions, carried out by process_hosts once they fall due
+#[derive(Component, Default, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
+pub struct ScheduledActions(Vec<ScheduledAt>);
+
+impl ScheduledActions {
+    pub fn schedule(&mut self, day: u32, action: HostAction) {
+        self.0.push(ScheduledAt(day, action));
+    }
+
+    pub fn cancel(&mut self, matches: impl Fn(&HostAction) -> bool) {
+        self.0.retain(|ScheduledAt(_, action)| !matches(action));
+    }
+
+    // Schedules treatment, keeping an earlier pending request
+    pub fn request_treatment(&mut self, day: u32, regimen: Regimen) {
+        let is_treatment = |action: &HostAction| matches!(action, HostAction::Treat(_));
+        if self.0.iter().any(|ScheduledAt(pending, action)| is_treatment(action) && *pending <= day) {
+            return;
+        }
+        self.cancel(is_treatment);
+        self.schedule(day, HostAction::Treat(regimen));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScheduledAt> {
+        self.0.iter()
+    }
+
+    pub fn is_due(&self, day: u32, matches: impl Fn(&HostAction) -> bool) -> bool {
+        self.0.iter().any(|ScheduledAt(due, action)| *due <= day && matches(action))
+    }
+
+    // Removes and returns the earliest action due by `day`, in the order scheduled among equals
+    pub fn next_due(&mut self, day: u32) -> Option<HostAction> {
+        let index = (0..self.0.len()).filter(|&index| self.0[index].0 <= day).min_by_key(|&index| self.0[index].0)?;
+        Some(self.0.remove(index).1)
+    }
+}
@@ -0,0 +1,241 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::budget::EntityBudget;
+use crate::headless::HeadlessRun;
+use crate::infectiousness::InfectiousnessCurve;
+use crate::param_docs::{self, ParamInfo};
+use crate::prcc::SensitivityTable;
+use crate::report;
+use crate::scenario::Scenario;
+use crate::stats::StateHistory;
+use crate::{Cli, Inoculation, Params, Regimen, SimulationEvent};
+
+// Outcome columns of sensitivity.csv, in order
+pub const OUTCOMES: [&str; 5] = ["mean_prevalence", "final_prevalence", "infections", "treatments", "elimination_day"];
+
+// Which params to vary and over what ranges, read from the design file given to the sensitivity subcommand
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct Design {
+    samples: u32,
+    replicates: u32, // Runs per sample, each with its own seed
+    days: u32,
+    ranges: BTreeMap<String, [f32; 2]>, // Param registry key to [low, high]
+    outcomes: Vec<String>, // Summarized with PRCCs at the end; all of them if empty
+}
+
+impl Default for Design {
+    fn default() -> Self {
+        Self {
+            samples: 50,
+            replicates: 1,
+            days: 365,
+            ranges: BTreeMap::new(),
+            outcomes: Vec::new(),
+        }
+    }
+}
+
+impl Design {
+    fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let design: Self = toml::from_str(&contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if design.samples == 0 || design.replicates == 0 {
+            return Err("samples and replicates must be at least 1".to_owned());
+        }
+        if design.ranges.is_empty() {
+            return Err("no [ranges] to sample".to_owned());
+        }
+        if let Some(outcome) = design.outcomes.iter().find(|outcome| !OUTCOMES.contains(&outcome.as_str())) {
+            return Err(format!("unknown outcome `{}` (expected one of {})", outcome, OUTCOMES.join(", ")));
+        }
+        Ok(design)
+    }
+
+    // Each range with its registry entry, checked against the param's valid range
+    fn params(&self) -> Result<Vec<(&'static ParamInfo, f32, f32)>, String> {
+        self.ranges
+            .iter()
+            .map(|(key, &[low, high])| {
+                let info = param_docs::find(key).ok_or_else(|| format!("unknown param `{}`", key))?;
+                if low > high || !info.valid.contains(&low) || !info.valid.contains(&high) {
+                    return Err(format!("{} = [{}, {}] must be an increasing range within {:?}", key, low, high, info.valid));
+                }
+                Ok((info, low, high))
+            })
+            .collect()
+    }
+}
+
+// One value per sample in each column: the range is cut into as many equal strata as samples, each stratum is used
+// exactly once, and the strata are shuffled independently for every param
+pub fn latin_hypercube(ranges: &[(f32, f32)], samples: u32, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+    let columns: Vec<Vec<f32>> = ranges
+        .iter()
+        .map(|&(low, high)| {
+            let mut strata: Vec<u32> = (0..samples).collect();
+            strata.shuffle(rng);
+            strata
+                .into_iter()
+                .map(|stratum| low + (high - low) * (stratum as f32 + rng.gen::<f32>()) / samples as f32)
+                .collect()
+        })
+        .collect();
+    (0..samples as usize).map(|sample| columns.iter().map(|column| column[sample]).collect()).collect()
+}
+
+// Outcomes of one run, the columns after the params in sensitivity.csv
+struct Outcomes {
+    mean_prevalence: f64,  // Acute or chronic hosts, as a fraction, averaged over the days
+    final_prevalence: f64, // On the last day
+    infections: u32,       // New inoculations
+    treatments: u32,       // Clinical treatment courses taken
+    elimination_day: Option<u32>, // First day with no inoculations left
+}
+
+// One run from a fresh day-0 cohort
+fn run_sample(params: &Params, scenario: &Scenario, cli: &Cli, curve: &InfectiousnessCurve, days: u32, seed: u64) -> Outcomes {
+    let budget = EntityBudget {
+        max_per_host: cli.max_inoculations_per_host,
+        max_total: cli.max_inoculations,
+    };
+    let mut run = HeadlessRun::new(params, scenario, budget, curve, cli.hosts, seed);
+    let (mut infections, mut treatments, mut elimination_day) = (0, 0, None);
+    for _ in 0..days {
+        for event in run.step() {
+            match event {
+                SimulationEvent::Infection => infections += 1,
+                SimulationEvent::Treatment(Regimen::Treatment) => treatments += 1,
+                _ => {}
+            }
+        }
+        if elimination_day.is_none() && run.world.query::<&Inoculation>().iter(&run.world).next().is_none() {
+            elimination_day = Some(run.day());
+        }
+    }
+
+    let hosts = cli.hosts.max(1) as f64;
+    let prevalence: Vec<f64> = run
+        .world
+        .resource::<StateHistory>()
+        .samples
+        .iter()
+        .map(|(_, counts)| (counts[2] + counts[3]) as f64 / hosts) // Acute and chronic, in HostState::ALL order
+        .collect();
+    Outcomes {
+        mean_prevalence: prevalence.iter().sum::<f64>() / prevalence.len().max(1) as f64,
+        final_prevalence: prevalence.last().copied().unwrap_or(0.0),
+        infections,
+        treatments,
+        elimination_day,
+    }
+}
+
+// Runs every sample of the design over the scenario's params and writes sensitivity.csv, one row per run, for Sobol or
+// other analyses elsewhere, then a first-pass PRCC summary; the exit code is 0 on success, 1 if an output can't be
+// written and 2 if the design or scenario can't be used
+pub fn run_sensitivity(design_path: &Path, cli: &Cli) -> i32 {
+    let setup = Design::load(design_path).and_then(|design| {
+        let ranges = design.params()?;
+        let scenario = match &cli.scenario {
+            Some(path) => Scenario::load(path)?,
+            None => Scenario::default(),
+        };
+        Ok((design, ranges, scenario))
+    });
+    let (design, ranges, scenario) = match setup {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("Failed to set up sensitivity analysis: {}", err);
+            return 2;
+        }
+    };
+    let mut base_params = Params::default();
+    scenario.params.apply(&mut base_params);
+    if let Err(errors) = param_docs::validate(&base_params) {
+        eprintln!("Invalid params:\n  {}", errors.join("\n  "));
+        return 2;
+    }
+    let curve = InfectiousnessCurve::load(Path::new("assets/infectiousness.csv")).unwrap_or_default();
+
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let bounds: Vec<(f32, f32)> = ranges.iter().map(|&(_, low, high)| (low, high)).collect();
+    let samples = latin_hypercube(&bounds, design.samples, &mut StdRng::seed_from_u64(seed ^ 0x1a5));
+
+    let path = cli.output_dir.join("sensitivity.csv");
+    let result = fs::create_dir_all(&cli.output_dir).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let keys: Vec<&str> = ranges.iter().map(|(info, ..)| info.key).collect();
+        writeln!(writer, "sample,replicate,seed,{},mean_prevalence,final_prevalence,infections,treatments,elimination_day", keys.join(","))?;
+        for (sample, values) in samples.iter().enumerate() {
+            let mut params = base_params.clone();
+            for ((info, ..), value) in ranges.iter().zip(values) {
+                *(info.field)(&mut params) = *value;
+            }
+            for replicate in 0..design.replicates {
+                let run_seed = seed ^ ((sample as u64) << 32) ^ replicate as u64;
+                let outcomes = run_sample(&params, &scenario, cli, &curve, design.days, run_seed);
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                writeln!(
+                    writer,
+                    "{},{},{},{},{:.6},{:.6},{},{},{}",
+                    sample,
+                    replicate,
+                    run_seed,
+                    values.join(","),
+                    outcomes.mean_prevalence,
+                    outcomes.final_prevalence,
+                    outcomes.infections,
+                    outcomes.treatments,
+                    outcomes.elimination_day.map_or(String::new(), |day| day.to_string())
+                )?;
+            }
+            println!("sample {} of {} done", sample + 1, design.samples);
+        }
+        writer.flush()
+    });
+    if let Err(err) = result {
+        eprintln!("Failed to write {}: {}", path.display(), err);
+        return report::EXIT_OUTPUT_FAILED;
+    }
+    println!("Wrote {} runs (seed {}) to {}", design.samples * design.replicates, seed, path.display());
+    summarize(&path, &design, &cli.output_dir)
+}
+
+// Prints the PRCC of every param with each chosen outcome and writes them to sensitivity_prcc.csv
+fn summarize(path: &Path, design: &Design, output_dir: &Path) -> i32 {
+    let table = match SensitivityTable::load(path) {
+        Ok(table) => table,
+        Err(err) => {
+            eprintln!("No PRCC summary: {}", err);
+            return 0;
+        }
+    };
+    let chosen: Vec<usize> = if design.outcomes.is_empty() {
+        (0..table.outcomes.len()).collect()
+    } else {
+        design.outcomes.iter().filter_map(|outcome| table.outcome_index(outcome)).collect()
+    };
+    let width = table.params.iter().map(String::len).max().unwrap_or(0);
+    for &outcome in &chosen {
+        println!("PRCC with {}:", table.outcomes[outcome]);
+        for (param, value) in table.params.iter().zip(table.prcc(outcome)) {
+            println!("  {:width$}  {}", param, value.map_or("undefined".to_owned(), |value| format!("{:+.3}", value)));
+        }
+    }
+    let prcc_path = output_dir.join("sensitivity_prcc.csv");
+    match table.write_prcc(&prcc_path, &chosen) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Failed to write {}: {}", prcc_path.display(), err);
+            report::EXIT_OUTPUT_FAILED
+        }
+    }
+}
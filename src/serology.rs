@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::clock::DayTick;
+use crate::ids::HostId;
+use crate::report::RunReport;
+use crate::{Host, OutputDir, Params, Sex, SimRng};
+
+const AGE_GROUPS: [(&str, f32); 4] = [("<5", 5.0), ("5-14", 15.0), ("15-29", 30.0), ("30+", f32::INFINITY)];
+const THRESHOLDS: [usize; 3] = [1, 2, 4]; // Antigenic types seen
+const SEROPOSITIVE_TITRE: f32 = 0.5; // Half a fresh boost
+
+// Monthly cross-sectional serosurvey of a random sample of hosts
+#[derive(Resource)]
+pub struct SerologySurvey {
+    pub interval_days: u32,
+    pub sample_size: usize,
+}
+
+impl Default for SerologySurvey {
+    fn default() -> Self {
+        Self {
+            interval_days: 30,
+            sample_size: 100,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SerologyOutput(BufWriter<File>);
+
+pub fn setup_serology_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("serology.csv"))?);
+        let thresholds: Vec<String> = THRESHOLDS.iter().map(|threshold| format!("at_least_{}", threshold)).collect();
+        writeln!(writer, "day,age_group,sex,sampled,{},seropositive", thresholds.join(","))?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(SerologyOutput(writer)),
+        Err(err) => report.output_failed("serology", err),
+    }
+}
+
+pub fn run_serosurvey(
+    host_query: Query<(&HostId, &Host)>,
+    survey: Res<SerologySurvey>,
+    params: Res<Params>,
+    sim_rng: Res<SimRng>,
+    mut ticks: EventReader<DayTick>,
+    output: Option<ResMut<SerologyOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(mut output) = output else {
+        return;
+    };
+    let Some(day) = ticks.read().last().map(|tick| tick.day) else {
+        return;
+    };
+    if day == 0 || !day.is_multiple_of(survey.interval_days) {
+        return;
+    }
+
+    // Seeded from the day rather than drawn, so surveying doesn't change the run
+    let mut hosts: Vec<(&HostId, &Host)> = host_query.iter().collect();
+    hosts.sort_by_key(|(id, _)| id.0);
+    let mut rng = StdRng::seed_from_u64(sim_rng.seed ^ 0x5e40 ^ ((day as u64) << 32));
+    let sampled: Vec<&Host> = hosts.choose_multiple(&mut rng, survey.sample_size).map(|(_, host)| *host).collect();
+
+    // Each age group overall, then by sex
+    let mut result = Ok(());
+    let mut lower = 0.0;
+    for (label, upper) in AGE_GROUPS {
+        for sex in [None, Some(Sex::Female), Some(Sex::Male)] {
+            let group: Vec<&&Host> = sampled
+                .iter()
+                .filter(|host| (lower..upper).contains(&host.age_years(day)) && sex.is_none_or(|sex| host.sex == sex))
+                .collect();
+            let breadths: Vec<usize> = group.iter().map(|host| host.immune_memory.len()).collect();
+            let seropositive = group.iter().filter(|host| host.antibody_titre(day, &params) >= SEROPOSITIVE_TITRE).count();
+
+            let fractions: Vec<String> = THRESHOLDS
+                .iter()
+                .map(|&threshold| {
+                    let positive = breadths.iter().filter(|&&breadth| breadth >= threshold).count();
+                    format!("{:.3}", if breadths.is_empty() { 0.0 } else { positive as f32 / breadths.len() as f32 })
+                })
+                .collect();
+            let seroprevalence = if group.is_empty() { 0.0 } else { seropositive as f32 / group.len() as f32 };
+            let sex = sex.map_or("all", |sex| sex.label());
+            result = result.and_then(|_| {
+                writeln!(output.0, "{},{},{},{},{},{:.3}", day, label, sex, group.len(), fractions.join(","), seroprevalence)
+            });
+        }
+        lower = upper;
+    }
+    if let Err(err) = result.and_then(|_| output.0.flush()) {
+        report.output_failed("serology", format!("day {}: {}", day, err));
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::autosave::write_atomic;
+use crate::gis::HostLocations;
+use crate::ids::HostId;
+use crate::migration;
+use crate::report::RunReport;
+use crate::scenario::Preset;
+use crate::scheduled::{HostAction, ScheduledActions, ScheduledAt};
+use crate::{Genotype, Host, HostState, InfectionState, Inoculation, Params, RunConfig, Sex, SimulationTime};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InoculationSnapshot {
+    pub state: InfectionState,
+    pub remaining_days: f32, // Left of the current state when the snapshot was taken
+    pub genotype: Genotype,
+    pub infected_days_ago: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HostSnapshot {
+    pub age_days: f32,
+    pub propensity: f32,
+    pub on_prophylaxis: bool,
+    pub immune_memory: Vec<Genotype>,
+    pub antibody_titre: f32,
+    pub comorbidity: f32,
+    pub sex: Option<Sex>, // None in snapshots from before hosts had a sex
+    pub pending: Vec<(u32, HostAction)>, // Days after the snapshot each scheduled action falls due
+    pub inoculations: Vec<InoculationSnapshot>,
+}
+
+impl HostSnapshot {
+    pub fn capture(host: &Host, actions: &ScheduledActions, inoculations: Vec<&Inoculation>, day: u32, params: &Params) -> Self {
+        Self {
+            age_days: day as f32 - host.birth_day,
+            propensity: host.propensity,
+            on_prophylaxis: host.on_prophylaxis,
+            immune_memory: host.immune_memory.iter().copied().collect(),
+            antibody_titre: host.antibody_titre(day, params),
+            comorbidity: host.comorbidity,
+            sex: Some(host.sex),
+            pending: actions.iter().map(|&ScheduledAt(due, action)| (due.saturating_sub(day), action)).collect(),
+            inoculations: inoculations
+                .into_iter()
+                .map(|inoc| InoculationSnapshot {
+                    state: inoc.state,
+                    remaining_days: (inoc.delay_days - (day as f32 - inoc.start_day as f32)).max(0.0),
+                    genotype: inoc.genotype,
+                    infected_days_ago: day.saturating_sub(inoc.infected_day),
+                })
+                .collect(),
+        }
+    }
+
+    // The host as of `day`; inoculations start over in their current state with what was left of it
+    pub fn restore(&self, day: u32) -> (Host, ScheduledActions, Vec<Inoculation>) {
+        let host = Host {
+            on_prophylaxis: self.on_prophylaxis,
+            propensity: self.propensity,
+            birth_day: day as f32 - self.age_days,
+            immune_memory: self.immune_memory.iter().copied().collect(),
+            antibody: self.antibody_titre,
+            antibody_day: day,
+            comorbidity: self.comorbidity,
+            sex: self.sex.unwrap_or_default(),
+            ..default()
+        };
+        let mut actions = ScheduledActions::default();
+        for &(in_days, action) in &self.pending {
+            actions.schedule(day + in_days, action);
+        }
+        let inoculations = self
+            .inoculations
+            .iter()
+            .map(|inoc| Inoculation {
+                state: inoc.state,
+                start_day: day,
+                delay_days: inoc.remaining_days,
+                genotype: inoc.genotype,
+                infected_day: day.saturating_sub(inoc.infected_days_ago),
+            })
+            .collect();
+        (host, actions, inoculations)
+    }
+
+    fn state(&self) -> HostState {
+        let has = |state: InfectionState| self.inoculations.iter().any(|inoc| inoc.state == state);
+        if self.on_prophylaxis {
+            HostState::P
+        } else if has(InfectionState::A) {
+            HostState::A
+        } else if has(InfectionState::C) {
+            HostState::C
+        } else if has(InfectionState::E) {
+            HostState::E
+        } else {
+            HostState::S
+        }
+    }
+
+    // Every field that differs from `other`, compared exactly so that float drift shows up too
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let mut differences = Vec::new();
+        let mut compare = |what: &str, a: String, b: String| {
+            if a != b {
+                differences.push(format!("{} {} vs {}", what, a, b));
+            }
+        };
+        compare("state", format!("{:?}", self.state()), format!("{:?}", other.state()));
+        compare("inoculations", self.inoculations.len().to_string(), other.inoculations.len().to_string());
+        if self.inoculations.len() == other.inoculations.len() {
+            for (index, (a, b)) in self.inoculations.iter().zip(&other.inoculations).enumerate() {
+                let describe = |inoc: &InoculationSnapshot| {
+                    format!("{:?} {:?} {:?} days left, infected {} days before", inoc.state, inoc.genotype, inoc.remaining_days, inoc.infected_days_ago)
+                };
+                compare(&format!("inoculation {}", index), describe(a), describe(b));
+            }
+        }
+        compare("age", format!("{:?}", self.age_days), format!("{:?}", other.age_days));
+        compare("propensity", format!("{:?}", self.propensity), format!("{:?}", other.propensity));
+        compare("immune memory", format!("{:?}", self.immune_memory), format!("{:?}", other.immune_memory));
+        compare("antibody titre", format!("{:?}", self.antibody_titre), format!("{:?}", other.antibody_titre));
+        compare("comorbidity", format!("{:?}", self.comorbidity), format!("{:?}", other.comorbidity));
+        compare("sex", format!("{:?}", self.sex), format!("{:?}", other.sex));
+        compare("pending actions", format!("{:?}", self.pending), format!("{:?}", other.pending));
+        differences
+    }
+}
+
+// The host population on one day of a run, e.g. at endemic equilibrium, for later runs to start from instead of
+// the synchronized day-0 cohort
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Snapshot {
+    pub format_version: u32,
+    pub day: u32, // Day of the run it was taken on
+    pub hosts: Vec<HostSnapshot>,
+}
+
+type SnapshotDocument = serde_json::Map<String, serde_json::Value>;
+
+// Version 1 snapshots could leave out the fields added to hosts and inoculations after snapshots were introduced
+fn fill_later_fields(snapshot: &mut SnapshotDocument) {
+    let hosts = snapshot.get_mut("hosts").and_then(serde_json::Value::as_array_mut).into_iter().flatten();
+    for host in hosts.filter_map(serde_json::Value::as_object_mut) {
+        for (key, default) in [("antibody_titre", 0.into()), ("comorbidity", 0.into()), ("sex", serde_json::Value::Null)] {
+            host.entry(key).or_insert(default);
+        }
+        let inoculations = host.get_mut("inoculations").and_then(serde_json::Value::as_array_mut).into_iter().flatten();
+        for inoculation in inoculations.filter_map(serde_json::Value::as_object_mut) {
+            inoculation.entry("infected_days_ago").or_insert(0.into());
+        }
+    }
+}
+
+const SNAPSHOT_MIGRATIONS: &[fn(&mut SnapshotDocument)] = &[fill_later_fields];
+pub const SNAPSHOT_VERSION: u32 = migration::current_version(SNAPSHOT_MIGRATIONS);
+
+impl Snapshot {
+    // With host locations, the snapshot must have one host per point
+    pub fn load(path: &Path, locations: Option<&HostLocations>) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let snapshot = Self::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if snapshot.hosts.is_empty() {
+            return Err(format!("{}: no hosts", path.display()));
+        }
+        if let Some(locations) = locations.filter(|locations| locations.host_count() as usize != snapshot.hosts.len()) {
+            return Err(format!(
+                "{}: {} hosts, but the host locations have {} points",
+                path.display(),
+                snapshot.hosts.len(),
+                locations.host_count()
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    // Files in the current format are parsed as written, so errors keep their line numbers
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut document: SnapshotDocument = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+        if migration::upgrade(&mut document, SNAPSHOT_MIGRATIONS)? {
+            serde_json::from_value(document.into()).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str(contents).map_err(|err| err.to_string())
+        }
+    }
+}
+
+// Prints how two snapshots differ, host by host, for tracking down runs that should match but don't; the exit code
+// is 0 if they are identical, 1 if they differ and 2 if either can't be read
+pub fn diff_snapshots(a_path: &Path, b_path: &Path) -> i32 {
+    let (a, b) = match (Snapshot::load(a_path, None), Snapshot::load(b_path, None)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("Failed to load snapshot: {}", err);
+            return 2;
+        }
+    };
+    println!("a: {} (day {}, {} hosts)", a_path.display(), a.day, a.hosts.len());
+    println!("b: {} (day {}, {} hosts)", b_path.display(), b.day, b.hosts.len());
+
+    let mut identical = a.day == b.day && a.hosts.len() == b.hosts.len();
+    let (mut differing, mut in_state, mut in_count) = (0, 0, 0);
+    for (index, (host_a, host_b)) in a.hosts.iter().zip(&b.hosts).enumerate() {
+        let differences = host_a.differences(host_b);
+        if differences.is_empty() {
+            continue;
+        }
+        differing += 1;
+        in_state += (host_a.state() != host_b.state()) as usize;
+        in_count += (host_a.inoculations.len() != host_b.inoculations.len()) as usize;
+        for difference in differences {
+            println!("host {}: {}", index, difference);
+        }
+    }
+    let shared = a.hosts.len().min(b.hosts.len());
+    for (name, snapshot) in [("a", &a), ("b", &b)] {
+        for index in shared..snapshot.hosts.len() {
+            println!("host {}: only in {}", index, name);
+        }
+    }
+
+    identical &= differing == 0;
+    if identical {
+        println!("Identical");
+        return 0;
+    }
+    println!(
+        "{} of {} hosts differ ({} in state, {} in inoculation count)",
+        differing, shared, in_state, in_count
+    );
+    1
+}
+
+// Snapshot new runs start from, if any
+#[derive(Resource, Default)]
+pub struct WarmStart(pub Option<Snapshot>);
+
+// A preset's own snapshot if it names one, else the scenario's
+pub fn preset_snapshot(preset: Option<&Preset>, base: &Option<Snapshot>, locations: Option<&HostLocations>) -> Option<Snapshot> {
+    let Some(path) = preset.and_then(|preset| preset.snapshot.as_ref()) else {
+        return base.clone();
+    };
+    Snapshot::load(path, locations).map_or_else(
+        |err| {
+            warn!("Failed to load preset snapshot, keeping the scenario's start: {}", err);
+            base.clone()
+        },
+        Some,
+    )
+}
+
+// Where to write a snapshot once the run reaches its end day
+#[derive(Resource)]
+pub struct SnapshotOutput {
+    path: PathBuf,
+    written: bool,
+}
+
+impl SnapshotOutput {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, written: false }
+    }
+}
+
+pub fn write_snapshot(
+    host_query: Query<(&HostId, &Host, &ScheduledActions, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    run_config: Res<RunConfig>,
+    mut output: ResMut<SnapshotOutput>,
+    mut report: ResMut<RunReport>,
+) {
+    let reached_end = run_config.end_day.is_some_and(|end_day| sim_time.day >= end_day);
+    if output.written || !reached_end {
+        return;
+    }
+    output.written = true;
+
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort_by_key(|(id, ..)| **id);
+    let snapshot = Snapshot {
+        format_version: SNAPSHOT_VERSION,
+        day: sim_time.day,
+        hosts: hosts
+            .into_iter()
+            .map(|(_, host, actions, children)| {
+                let inoculations = children.map_or(Vec::new(), |children| inoc_query.iter_many(children).collect());
+                HostSnapshot::capture(host, actions, inoculations, sim_time.day, &params)
+            })
+            .collect(),
+    };
+
+    let result = serde_json::to_string_pretty(&snapshot).map_err(std::io::Error::from).and_then(|json| {
+        if let Some(parent) = output.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&output.path, json)
+    });
+    match result {
+        Ok(()) => info!("Wrote snapshot of {} hosts to {}", snapshot.hosts.len(), output.path.display()),
+        Err(err) => report.output_failed("snapshot", err),
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use std::collections::VecDeque;
+
+use crate::clock::DayTick;
+use crate::ids::{HostId, PATCH_SIZE};
+use crate::venues::{Memberships, Venue};
+use crate::{Host, Inoculation};
+
+const SPARKLINE_DAYS: usize = 30;
+
+struct Group {
+    name: String,
+    members: Vec<Entity>,
+    daily: VecDeque<u32>, // New inoculations per day, oldest first and today last
+}
+
+// Recent incidence in each venue, or in each patch of neighbouring hosts
+#[derive(Resource, Default)]
+pub struct GroupIncidence {
+    groups: Vec<Group>,
+}
+
+#[derive(Resource, Default)]
+pub struct SparklinePanel {
+    pub shown: bool,
+    hovered: Option<usize>, // Group whose hosts are outlined in the host view
+}
+
+fn group_members(host_query: &Query<(Entity, &HostId, Option<&Memberships>)>, venue_query: &Query<(Entity, &Venue)>) -> Vec<(String, Vec<Entity>)> {
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort_by_key(|(_, id, _)| **id);
+
+    if !venue_query.is_empty() {
+        let mut venues: Vec<_> = venue_query.iter().collect();
+        venues.sort_by_key(|(entity, _)| *entity);
+        return venues
+            .into_iter()
+            .map(|(entity, venue)| {
+                let members = hosts
+                    .iter()
+                    .filter(|(_, _, memberships)| memberships.is_some_and(|memberships| memberships.0.contains(&entity)))
+                    .map(|(host, _, _)| *host)
+                    .collect();
+                (venue.config.name.clone(), members)
+            })
+            .collect();
+    }
+    hosts
+        .chunks(PATCH_SIZE as usize)
+        .map(|patch| {
+            let (first, last) = (patch[0].1 .0, patch[patch.len() - 1].1 .0);
+            (format!("Hosts {}-{}", first, last), patch.iter().map(|(entity, _, _)| *entity).collect())
+        })
+        .collect()
+}
+
+pub fn record_group_incidence(
+    host_query: Query<(Entity, &HostId, Option<&Memberships>)>,
+    venue_query: Query<(Entity, &Venue)>,
+    new_inocs: Query<&Parent, Added<Inoculation>>,
+    mut incidence: ResMut<GroupIncidence>,
+    mut ticks: EventReader<DayTick>,
+) {
+    // Regrouped daily, as hosts join venues a frame after they spawn
+    if ticks.read().count() > 0 {
+        let previous = std::mem::take(&mut incidence.groups);
+        incidence.groups = group_members(&host_query, &venue_query)
+            .into_iter()
+            .map(|(name, members)| {
+                let mut daily = previous.iter().find(|group| group.name == name).map_or_else(VecDeque::new, |group| group.daily.clone());
+                daily.push_back(0);
+                if daily.len() > SPARKLINE_DAYS {
+                    daily.pop_front();
+                }
+                Group { name, members, daily }
+            })
+            .collect();
+    }
+
+    for parent in new_inocs.iter() {
+        for group in incidence.groups.iter_mut().filter(|group| group.members.contains(&parent.get())) {
+            if let Some(today) = group.daily.back_mut() {
+                *today += 1;
+            }
+        }
+    }
+}
+
+// Daily counts scaled to the busiest day, with today at the right edge
+fn sparkline(ui: &mut egui::Ui, daily: &VecDeque<u32>) -> egui::Response {
+    let (response, painter) = ui.allocate_painter(egui::vec2(120.0, 20.0), egui::Sense::hover());
+    let rect = response.rect;
+    let max = daily.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let offset = SPARKLINE_DAYS - daily.len();
+    let points: Vec<egui::Pos2> = daily
+        .iter()
+        .enumerate()
+        .map(|(index, &count)| {
+            let x = rect.left() + rect.width() * (offset + index) as f32 / (SPARKLINE_DAYS - 1) as f32;
+            egui::pos2(x, rect.bottom() - rect.height() * count as f32 / max)
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 90, 90))));
+    response
+}
+
+pub fn sparklines_ui(mut contexts: EguiContexts, incidence: Res<GroupIncidence>, mut panel: ResMut<SparklinePanel>) {
+    if !panel.shown {
+        return;
+    }
+
+    let mut hovered = None;
+    egui::SidePanel::right("group_sparklines").resizable(false).show(contexts.ctx_mut(), |ui| {
+        ui.heading("Groups");
+        ui.label(format!("New infections over the last {} days", SPARKLINE_DAYS));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("sparklines").striped(true).show(ui, |ui| {
+                for (index, group) in incidence.groups.iter().enumerate() {
+                    let name = ui.label(&group.name);
+                    let line = sparkline(ui, &group.daily);
+                    let total = ui.label(group.daily.iter().sum::<u32>().to_string());
+                    if name.hovered() || line.hovered() || total.hovered() {
+                        hovered = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+    panel.hovered = hovered;
+}
+
+// Outlines the hosts of the group under the pointer in the Groups panel
+pub fn outline_hovered_group(
+    mut gizmos: Gizmos,
+    panel: Res<SparklinePanel>,
+    incidence: Res<GroupIncidence>,
+    host_query: Query<&Transform, With<Host>>,
+) {
+    let Some(group) = panel.hovered.filter(|_| panel.shown).and_then(|index| incidence.groups.get(index)) else {
+        return;
+    };
+    for transform in group.members.iter().filter_map(|&host| host_query.get(host).ok()) {
+        gizmos.rect_2d(transform.translation.truncate(), 0.0, Vec2::new(60.0, 15.0), Color::WHITE);
+    }
+}
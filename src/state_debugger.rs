@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::ids::{HostId, InoculationId};
+use crate::{Host, InfectionState, Inoculation, MainCamera, Params, SimulationTime};
+
+const INOC_HALF_SIZE: f32 = 15.0;
+
+// Inoculation shown in the state-machine debugger
+#[derive(Resource, Default)]
+pub struct InspectedInoculation(pub Option<Entity>);
+
+// Left click on an inoculation square selects it, Escape clears the selection
+pub fn select_inoculation(
+    mut contexts: EguiContexts,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    inoc_query: Query<(Entity, &GlobalTransform), With<Inoculation>>,
+    mut inspected: ResMut<InspectedInoculation>,
+) {
+    if keys.just_pressed(KeyCode::Escape) || inspected.0.is_some_and(|entity| inoc_query.get(entity).is_err()) {
+        inspected.0 = None;
+    }
+
+    if !mouse.just_pressed(MouseButton::Left) || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let Some(cursor) = window_query.get_single().ok().and_then(|window| window.cursor_position()) else {
+        return;
+    };
+    let Some(point) = camera_query
+        .get_single()
+        .ok()
+        .and_then(|(camera, camera_transform)| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+
+    let hit = inoc_query
+        .iter()
+        .find(|(_, transform)| (transform.translation().truncate() - point).abs().max_element() <= INOC_HALF_SIZE);
+    if let Some((entity, _)) = hit {
+        inspected.0 = Some(entity);
+    }
+}
+
+pub fn outline_inspected(mut gizmos: Gizmos, inspected: Res<InspectedInoculation>, inoc_query: Query<&GlobalTransform, With<Inoculation>>) {
+    if let Some(transform) = inspected.0.and_then(|entity| inoc_query.get(entity).ok()) {
+        gizmos.rect_2d(transform.translation().truncate(), 0.0, Vec2::splat(2.0 * INOC_HALF_SIZE + 6.0), Color::WHITE);
+    }
+}
+
+#[derive(PartialEq)]
+enum Node {
+    State(InfectionState),
+    Blocked,
+    Cleared,
+}
+
+// Chances of the next transitions out of the current state, as process_inoculations would draw them today
+fn transitions(inoc: &Inoculation, host: &Host, params: &Params) -> Vec<(Node, Node, f32)> {
+    match inoc.state {
+        InfectionState::E => {
+            let escapes_drug = if inoc.genotype.is_resistant() { params.resistance_level } else { 0.0 };
+            let prophylaxis = if host.on_prophylaxis { 1.0 - escapes_drug } else { 0.0 };
+            let blocked = 1.0 - (1.0 - prophylaxis) * (1.0 - host.protection_against(inoc.genotype, params));
+            let prob_acute = host.prob_acute(params);
+            vec![
+                (Node::State(InfectionState::E), Node::Blocked, blocked),
+                (Node::State(InfectionState::E), Node::State(InfectionState::A), (1.0 - blocked) * prob_acute),
+                (Node::State(InfectionState::E), Node::State(InfectionState::C), (1.0 - blocked) * (1.0 - prob_acute)),
+            ]
+        }
+        InfectionState::A => vec![
+            (Node::State(InfectionState::A), Node::State(InfectionState::C), params.prob_ac),
+            (Node::State(InfectionState::A), Node::Cleared, 1.0 - params.prob_ac),
+        ],
+        InfectionState::C => vec![(Node::State(InfectionState::C), Node::Cleared, 1.0)],
+    }
+}
+
+fn node_position(node: &Node, rect: egui::Rect) -> egui::Pos2 {
+    let (x, y) = match node {
+        Node::State(InfectionState::E) => (0.12, 0.5),
+        Node::State(InfectionState::A) => (0.45, 0.2),
+        Node::State(InfectionState::C) => (0.45, 0.8),
+        Node::Blocked => (0.12, 0.85),
+        Node::Cleared => (0.85, 0.5),
+    };
+    rect.lerp_inside(egui::vec2(x, y))
+}
+
+fn node_label(node: &Node) -> &'static str {
+    match node {
+        Node::State(InfectionState::E) => "Liver (E)",
+        Node::State(InfectionState::A) => "Acute (A)",
+        Node::State(InfectionState::C) => "Chronic (C)",
+        Node::Blocked => "Blocked",
+        Node::Cleared => "Cleared",
+    }
+}
+
+// The E -> A/C -> cleared diagram with the current state highlighted and today's transition chances on the arrows
+fn draw_state_diagram(ui: &mut egui::Ui, current: InfectionState, transitions: &[(Node, Node, f32)]) {
+    let (response, painter) = ui.allocate_painter(egui::vec2(320.0, 150.0), egui::Sense::hover());
+    let rect = response.rect;
+    let text_color = ui.visuals().text_color();
+    let edges = [
+        (Node::State(InfectionState::E), Node::Blocked),
+        (Node::State(InfectionState::E), Node::State(InfectionState::A)),
+        (Node::State(InfectionState::E), Node::State(InfectionState::C)),
+        (Node::State(InfectionState::A), Node::State(InfectionState::C)),
+        (Node::State(InfectionState::A), Node::Cleared),
+        (Node::State(InfectionState::C), Node::Cleared),
+    ];
+    for (from, to) in &edges {
+        let (start, end) = (node_position(from, rect), node_position(to, rect));
+        let probability = transitions
+            .iter()
+            .find(|(f, t, _)| f == from && t == to)
+            .map(|(_, _, p)| *p);
+        let stroke = match probability {
+            Some(_) => egui::Stroke::new(2.0, egui::Color32::YELLOW),
+            None => egui::Stroke::new(1.0, egui::Color32::GRAY),
+        };
+        // Stop short of the node boxes
+        let direction = (end - start).normalized();
+        painter.arrow(start + direction * 30.0, end - start - direction * 60.0, stroke);
+        if let Some(probability) = probability {
+            let middle = start + (end - start) / 2.0;
+            painter.text(middle, egui::Align2::CENTER_BOTTOM, format!("{:.2}", probability), egui::FontId::proportional(12.0), text_color);
+        }
+    }
+
+    for node in [
+        Node::State(InfectionState::E),
+        Node::State(InfectionState::A),
+        Node::State(InfectionState::C),
+        Node::Blocked,
+        Node::Cleared,
+    ] {
+        let center = node_position(&node, rect);
+        let highlighted = matches!(node, Node::State(state) if state == current);
+        let fill = if highlighted { egui::Color32::from_rgb(200, 60, 60) } else { ui.visuals().extreme_bg_color };
+        painter.rect(egui::Rect::from_center_size(center, egui::vec2(70.0, 22.0)), 4.0, fill, egui::Stroke::new(1.0, text_color));
+        painter.text(center, egui::Align2::CENTER_CENTER, node_label(&node), egui::FontId::proportional(12.0), text_color);
+    }
+}
+
+pub fn state_debugger_ui(
+    mut contexts: EguiContexts,
+    mut inspected: ResMut<InspectedInoculation>,
+    inoc_query: Query<(&Inoculation, &InoculationId, &Parent)>,
+    host_query: Query<(&Host, &HostId)>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+) {
+    let Some((inoc, inoc_id, (host, host_id))) = inspected
+        .0
+        .and_then(|entity| inoc_query.get(entity).ok())
+        .and_then(|(inoc, inoc_id, parent)| Some((inoc, inoc_id, host_query.get(parent.get()).ok()?)))
+    else {
+        return;
+    };
+
+    let mut clear = false;
+    egui::Window::new("Inoculation State")
+        .default_pos(egui::pos2(450.0, 250.0))
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Inoculation {} in host {}, genotype {}", inoc_id.0, host_id.0, inoc.genotype.label(params.n_loci)));
+                clear = ui.button("Clear").clicked();
+            });
+            let elapsed = sim_time.day as f32 - inoc.start_day as f32;
+            ui.label(format!(
+                "In {:?} for {:.0} of {:.1} days ({:.1} remaining)",
+                inoc.state,
+                elapsed,
+                inoc.delay_days,
+                (inoc.delay_days - elapsed).max(0.0)
+            ));
+            draw_state_diagram(ui, inoc.state, &transitions(inoc, host, &params));
+            if inoc.state == InfectionState::E && host.on_prophylaxis {
+                ui.label("Host is on prophylaxis");
+            }
+        });
+
+    if clear {
+        inspected.0 = None;
+    }
+}
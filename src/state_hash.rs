@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::clock::DayTick;
+use crate::ids::HostId;
+use crate::report::RunReport;
+use crate::{Host, Inoculation, OutputDir};
+
+// FNV-1a over explicitly laid out little-endian fields, so a hash means the same on every platform and toolchain
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    fn f32(&mut self, value: f32) -> &mut Self {
+        self.u32(value.to_bits())
+    }
+}
+
+fn inoculation_hash(inoc: &Inoculation) -> u64 {
+    let mut hash = Fnv::new();
+    hash.u32(inoc.state as u32).u32(inoc.start_day).f32(inoc.delay_days).u32(inoc.genotype.0 as u32).u32(inoc.infected_day);
+    hash.0
+}
+
+// A host's own fields plus its inoculations, which are summed so their order among the children doesn't matter
+fn host_hash(id: HostId, host: &Host, inoculations: u64) -> u64 {
+    let mut hash = Fnv::new();
+    hash.u32(id.0).u32(host.on_prophylaxis as u32).f32(host.propensity).f32(host.birth_day);
+    for genotype in &host.immune_memory {
+        hash.u32(genotype.0 as u32);
+    }
+    hash.f32(host.antibody).u32(host.antibody_day).f32(host.comorbidity).u32(host.sex as u32);
+    hash.write(&inoculations.to_le_bytes());
+    hash.0
+}
+
+// Hash of the simulation state once each day's updates are done, for spotting where two runs that should match diverge
+#[derive(Resource, Default)]
+pub struct StateHashes {
+    pub latest: Option<(u32, u64)>,
+}
+
+#[derive(Resource)]
+pub struct StateHashOutput(BufWriter<File>);
+
+pub fn setup_state_hash_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("state_hash.csv"))?);
+        writeln!(writer, "day,hash")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(StateHashOutput(writer)),
+        Err(err) => report.output_failed("state hash", err),
+    }
+}
+
+// Order-independent: per-host hashes are summed, so neither query order nor entity ids change the result; the
+// transient daily exposure draw is left out
+pub fn record_state_hash(
+    host_query: Query<(&HostId, &Host, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    mut hashes: ResMut<StateHashes>,
+    mut ticks: EventReader<DayTick>,
+    output: Option<ResMut<StateHashOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(day) = ticks.read().last().map(|tick| tick.day) else {
+        return;
+    };
+
+    let hash = host_query.iter().fold(0u64, |sum, (&id, host, children)| {
+        let inoculations = inoc_query
+            .iter_many(children.into_iter().flatten())
+            .fold(0u64, |sum, inoc| sum.wrapping_add(inoculation_hash(inoc)));
+        sum.wrapping_add(host_hash(id, host, inoculations))
+    });
+    hashes.latest = Some((day, hash));
+
+    if let Some(mut output) = output {
+        if let Err(err) = writeln!(output.0, "{},{:016x}", day, hash).and_then(|_| output.0.flush()) {
+            report.output_failed("state hash", err);
+        }
+    }
+}
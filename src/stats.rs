@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::crowds::Crowd;
+use crate::scenario::DiagnosticConfig;
+use crate::{Genotype, Host, HostState, InfectionState, Inoculation, SimulationTime};
+
+// Time resolution for statistics shown in plots and written to exports
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Aggregation {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly, // 30-day periods
+}
+
+impl Aggregation {
+    pub fn period_days(&self) -> u32 {
+        match self {
+            Aggregation::Daily => 1,
+            Aggregation::Weekly => 7,
+            Aggregation::Monthly => 30,
+        }
+    }
+
+    // Mean of each series over consecutive periods, labelled by the first day of the period
+    pub fn series<const N: usize>(&self, samples: impl IntoIterator<Item = (u32, [f64; N])>) -> Vec<(u32, [f64; N])> {
+        let period = self.period_days();
+        let mut periods: Vec<(u32, [f64; N], usize)> = Vec::new();
+
+        for (day, values) in samples {
+            let start = day - day % period;
+            match periods.last_mut() {
+                Some((last_start, sums, count)) if *last_start == start => {
+                    for (sum, value) in sums.iter_mut().zip(values) {
+                        *sum += value;
+                    }
+                    *count += 1;
+                }
+                _ => periods.push((start, values, 1)),
+            }
+        }
+
+        periods
+            .into_iter()
+            .map(|(start, sums, count)| (start, sums.map(|sum| sum / count as f64)))
+            .collect()
+    }
+
+    pub fn states(&self, history: &StateHistory) -> Vec<(u32, [f64; 5])> {
+        self.series(history.samples.iter().map(|(day, counts)| (*day, counts.map(|count| count as f64))))
+    }
+
+    // Female states followed by male states
+    pub fn states_by_sex(&self, history: &StateHistory) -> Vec<(u32, [f64; 10])> {
+        self.series(history.by_sex.iter().map(|(day, [female, male])| {
+            let mut values = [0.0; 10];
+            for (value, count) in values.iter_mut().zip(female.iter().chain(male)) {
+                *value = *count as f64;
+            }
+            (*day, values)
+        }))
+    }
+
+    // True (liver or blood stage) and detected infections, with the population size
+    pub fn prevalence(&self, history: &StateHistory, diagnostic: &Diagnostic) -> Vec<(u32, [f64; 3])> {
+        self.series(history.samples.iter().map(|(day, counts)| {
+            let counts = counts.map(|count| count as f64);
+            (*day, [counts[1] + counts[2] + counts[3], diagnostic.0.detected(&counts), counts.iter().sum()])
+        }))
+    }
+
+    pub fn genotypes(&self, history: &GenotypeHistory) -> Vec<(u32, BTreeMap<Genotype, f32>)> {
+        let period = self.period_days();
+        let mut periods: Vec<(u32, BTreeMap<Genotype, f32>, usize)> = Vec::new();
+
+        for (day, frequencies) in &history.samples {
+            let start = day - day % period;
+            if periods.last().is_none_or(|(last_start, _, _)| *last_start != start) {
+                periods.push((start, BTreeMap::new(), 0));
+            }
+            if let Some((_, sums, count)) = periods.last_mut() {
+                for (genotype, frequency) in frequencies {
+                    *sums.entry(*genotype).or_default() += frequency;
+                }
+                *count += 1;
+            }
+        }
+
+        periods
+            .into_iter()
+            .map(|(start, mut sums, count)| {
+                sums.values_mut().for_each(|sum| *sum /= count as f32);
+                (start, sums)
+            })
+            .collect()
+    }
+}
+
+// Test behind the detected prevalence line, adjustable in the Host States window
+#[derive(Resource, Default)]
+pub struct Diagnostic(pub DiagnosticConfig);
+
+// Daily frequency of each genotype among blood-stage inoculations
+#[derive(Resource, Default)]
+pub struct GenotypeHistory {
+    pub samples: Vec<(u32, BTreeMap<Genotype, f32>)>,
+}
+
+pub fn record_genotype_frequencies(
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut history: ResMut<GenotypeHistory>,
+) {
+    if history.samples.last().is_some_and(|(day, _)| *day == sim_time.day) {
+        return;
+    }
+
+    let mut counts: BTreeMap<Genotype, f32> = BTreeMap::new();
+    for inoc in inoc_query.iter().filter(|inoc| inoc.state != InfectionState::E) {
+        *counts.entry(inoc.genotype).or_default() += 1.0;
+    }
+
+    let total: f32 = counts.values().sum();
+    for count in counts.values_mut() {
+        *count /= total;
+    }
+    history.samples.push((sim_time.day, counts));
+}
+
+// Daily fraction of blood-stage inoculations carrying drug resistance
+#[derive(Resource, Default)]
+pub struct ResistanceHistory {
+    pub samples: Vec<(u32, f64)>,
+}
+
+pub fn record_resistance(
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut history: ResMut<ResistanceHistory>,
+) {
+    if history.samples.last().is_some_and(|(day, _)| *day == sim_time.day) {
+        return;
+    }
+
+    let blood_stage: Vec<_> = inoc_query.iter().filter(|inoc| inoc.state != InfectionState::E).collect();
+    let resistant = blood_stage.iter().filter(|inoc| inoc.genotype.is_resistant()).count();
+    let fraction = if blood_stage.is_empty() { 0.0 } else { resistant as f64 / blood_stage.len() as f64 };
+    history.samples.push((sim_time.day, fraction));
+}
+
+// Daily count of hosts in each HostState, indexed in HostState::ALL order
+#[derive(Resource, Default)]
+pub struct StateHistory {
+    pub samples: Vec<(u32, [usize; 5])>,
+    pub by_sex: Vec<(u32, [[usize; 5]; 2])>, // Hosts only, indexed like Sex::ALL; crowds have no sex
+}
+
+pub fn record_host_states(
+    host_query: Query<(&Host, Option<&Children>, Option<&Crowd>)>,
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut history: ResMut<StateHistory>,
+) {
+    if history.samples.last().is_some_and(|(day, _)| *day == sim_time.day) {
+        return;
+    }
+
+    let mut counts = [0; 5];
+    let mut by_sex = [[0; 5]; 2];
+    for (host, children, crowd) in host_query.iter() {
+        let state = host.state(children, &inoc_query);
+        if let Some(index) = HostState::ALL.iter().position(|s| *s == state) {
+            counts[index] += 1;
+            by_sex[host.sex as usize][index] += 1;
+        }
+        // The people behind the host count alongside it
+        if let Some(crowd) = crowd {
+            for (count, crowd_count) in counts.iter_mut().zip(crowd.state_counts()) {
+                *count += crowd_count;
+            }
+        }
+    }
+    history.samples.push((sim_time.day, counts));
+    history.by_sex.push((sim_time.day, by_sex));
+}
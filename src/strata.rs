@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::fs;
+
+use crate::autosave::write_atomic;
+use crate::clock::DayTick;
+use crate::environment::Suitability;
+use crate::occupations::Occupation;
+use crate::{Host, HostState, Inoculation, OutputDir, Regimen, SimulationTime};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Stratum {
+    Age(&'static str),
+    Exposure(&'static str),
+    Sex(&'static str),
+    Occupation(String), // "none" for hosts without one
+}
+
+impl Stratum {
+    const ALL: [Stratum; 8] = [
+        Stratum::Age("<5"),
+        Stratum::Age("5-14"),
+        Stratum::Age("15+"),
+        Stratum::Exposure("Low"),
+        Stratum::Exposure("Medium"),
+        Stratum::Exposure("High"),
+        Stratum::Sex("female"),
+        Stratum::Sex("male"),
+    ];
+
+    fn age(years: f32) -> Self {
+        Stratum::Age(match years {
+            years if years < 5.0 => "<5",
+            years if years < 15.0 => "5-14",
+            _ => "15+",
+        })
+    }
+
+    // By the host's local suitability multiplier
+    fn exposure(multiplier: f32) -> Self {
+        Stratum::Exposure(match multiplier {
+            multiplier if multiplier < 0.75 => "Low",
+            multiplier if multiplier <= 1.33 => "Medium",
+            _ => "High",
+        })
+    }
+
+    fn labels(&self) -> (&'static str, &str) {
+        match self {
+            Stratum::Age(band) => ("Age", band),
+            Stratum::Exposure(band) => ("Exposure", band),
+            Stratum::Sex(band) => ("Sex", band),
+            Stratum::Occupation(name) => ("Occupation", name),
+        }
+    }
+}
+
+// Follow-up of one host, classified by its age when first seen
+struct HostRecord {
+    age: Stratum,
+    exposure: Stratum,
+    sex: Stratum,
+    occupation: Stratum,
+    days: u32,
+    infected_days: u32, // Acute or chronic
+    infections: u32,
+    offers: u32,
+    accepted: u32,
+    last_offer: Option<(u32, Regimen, bool)>,
+}
+
+pub struct StratumSummary {
+    pub dimension: &'static str,
+    pub band: String,
+    pub hosts: usize,
+    pub incidence: f32,  // New inoculations per host-year
+    pub prevalence: f32, // Fraction of host-days acute or chronic
+    pub coverage: f32,   // Fraction of hosts who took up at least one intervention
+    pub acceptance: f32, // Fraction of offers taken up
+}
+
+#[derive(Resource, Default)]
+pub struct RiskStrata {
+    hosts: HashMap<Entity, HostRecord>,
+}
+
+impl RiskStrata {
+    pub fn summaries(&self) -> Vec<StratumSummary> {
+        // Occupations only once the scenario gives some hosts one
+        let none = Stratum::Occupation("none".to_owned());
+        let mut occupations: Vec<&Stratum> = self.hosts.values().map(|r| &r.occupation).collect();
+        occupations.sort();
+        occupations.dedup();
+        if occupations == [&none] {
+            occupations.clear();
+        }
+
+        Stratum::ALL
+            .iter()
+            .chain(occupations)
+            .map(|stratum| {
+                let records: Vec<_> = self
+                    .hosts
+                    .values()
+                    .filter(|r| r.age == *stratum || r.exposure == *stratum || r.sex == *stratum || r.occupation == *stratum)
+                    .collect();
+                let sum = |value: fn(&HostRecord) -> u32| records.iter().map(|r| value(r)).sum::<u32>() as f32;
+                let ratio = |numerator: f32, denominator: f32| if denominator > 0.0 { numerator / denominator } else { 0.0 };
+                let (dimension, band) = stratum.labels();
+                StratumSummary {
+                    dimension,
+                    band: band.to_owned(),
+                    hosts: records.len(),
+                    incidence: ratio(sum(|r| r.infections), sum(|r| r.days) / 365.0),
+                    prevalence: ratio(sum(|r| r.infected_days), sum(|r| r.days)),
+                    coverage: ratio(records.iter().filter(|r| r.accepted > 0).count() as f32, records.len() as f32),
+                    acceptance: ratio(sum(|r| r.accepted), sum(|r| r.offers)),
+                }
+            })
+            .collect()
+    }
+
+    pub fn write_csv(&self, output_dir: &OutputDir) -> std::io::Result<()> {
+        let mut csv = String::from("dimension,stratum,hosts,incidence_per_year,prevalence,coverage,acceptance\n");
+        for summary in self.summaries() {
+            csv.push_str(&format!(
+                "{},{},{},{:.3},{:.3},{:.3},{:.3}\n",
+                summary.dimension, summary.band, summary.hosts, summary.incidence, summary.prevalence, summary.coverage, summary.acceptance
+            ));
+        }
+        fs::create_dir_all(&output_dir.0)?;
+        write_atomic(&output_dir.0.join("risk_strata.csv"), csv)
+    }
+}
+
+pub fn record_risk_strata(
+    host_query: Query<(Entity, &Host, Option<&Children>, Option<&Suitability>)>,
+    occupation_query: Query<&Occupation>,
+    new_inocs: Query<&Parent, Added<Inoculation>>,
+    inoc_query: Query<&Inoculation>,
+    sim_time: Res<SimulationTime>,
+    mut strata: ResMut<RiskStrata>,
+    mut ticks: EventReader<DayTick>,
+) {
+    let new_day = ticks.read().count() > 0;
+
+    for (entity, host, children, suitability) in host_query.iter() {
+        let record = strata.hosts.entry(entity).or_insert_with(|| HostRecord {
+            age: Stratum::age(host.age_years(sim_time.day)),
+            exposure: Stratum::exposure(1.0),
+            sex: Stratum::Sex(host.sex.label()),
+            occupation: Stratum::Occupation("none".to_owned()),
+            days: 0,
+            infected_days: 0,
+            infections: 0,
+            offers: 0,
+            accepted: 0,
+            last_offer: None,
+        });
+        // Suitability and occupation arrive a frame after the host
+        record.exposure = Stratum::exposure(suitability.map_or(1.0, |suitability| suitability.0));
+        if let Some(occupation) = occupation_query.get(entity).ok().filter(|_| matches!(&record.occupation, Stratum::Occupation(name) if name == "none")) {
+            record.occupation = Stratum::Occupation(occupation.name.clone());
+        }
+
+        if host.last_offer.is_some() && host.last_offer != record.last_offer {
+            record.last_offer = host.last_offer;
+            record.offers += 1;
+            record.accepted += host.last_offer.is_some_and(|(_, _, accepted)| accepted) as u32;
+        }
+        if new_day {
+            record.days += 1;
+            if matches!(host.state(children, &inoc_query), HostState::A | HostState::C) {
+                record.infected_days += 1;
+            }
+        }
+    }
+
+    for parent in new_inocs.iter() {
+        if let Some(record) = strata.hosts.get_mut(&parent.get()) {
+            record.infections += 1;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+#[cfg(feature = "gui")]
+use egui_plot::{Line, Plot, PlotPoints};
+
+use crate::event_log::EventLog;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::scenario::DrugStockConfig;
+use crate::SimulationTime;
+
+// The scenario's [drug_stock], if any; without one, drugs never run out
+#[derive(Resource, Default, Clone)]
+pub struct DrugSupply(pub Option<DrugStockConfig>);
+
+#[derive(Resource, Default, Clone)]
+pub struct DrugStock {
+    delivered: u32,
+    used: u32,
+    pub stock_outs: u32,          // Accepted courses that could not be given
+    pub samples: Vec<(u32, u32)>, // Day and courses in stock at its start
+    out_since: Option<u32>,       // Day the stock ran out, until the next delivery
+}
+
+impl DrugStock {
+    pub fn courses(&self, config: &DrugStockConfig) -> u32 {
+        (config.initial + self.delivered).saturating_sub(self.used)
+    }
+
+    // Uses up a course if there is one left
+    pub fn take(&mut self, config: &DrugStockConfig, day: u32, event_log: &mut EventLog) -> bool {
+        if self.courses(config) > 0 {
+            self.used += 1;
+            return true;
+        }
+        self.stock_outs += 1;
+        if self.out_since.is_none() {
+            self.out_since = Some(day);
+            event_log.log(day, "Drug stock ran out");
+        }
+        false
+    }
+}
+
+// Once a day, takes in any delivery due and records the stock
+pub fn resupply_drugs(
+    supply: Res<DrugSupply>,
+    sim_time: Res<SimulationTime>,
+    mut stock: ResMut<DrugStock>,
+    mut event_log: ResMut<EventLog>,
+) {
+    let Some(config) = &supply.0 else {
+        return;
+    };
+    if stock.samples.last().is_some_and(|&(day, _)| day == sim_time.day) {
+        return;
+    }
+
+    let day = sim_time.day;
+    if day > 0 && day.is_multiple_of(config.resupply_days.max(1)) {
+        if config.missed_deliveries.iter().any(|&[first, last]| (first..=last).contains(&day)) {
+            event_log.log(day, "Drug delivery missed");
+        } else {
+            // Anything beyond the store's capacity is turned away
+            let room = config.capacity.saturating_sub(stock.courses(config));
+            stock.delivered += config.resupply_amount.min(room);
+            if let Some(since) = stock.out_since.take() {
+                event_log.log(day, format!("Drug stock replenished after {} days out", day - since));
+            }
+        }
+    }
+    let courses = stock.courses(config);
+    stock.samples.push((day, courses));
+}
+
+#[cfg(feature = "gui")]
+pub fn drug_stock_ui(
+    mut contexts: EguiContexts,
+    supply: Res<DrugSupply>,
+    stock: Res<DrugStock>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    let Some(config) = &supply.0 else {
+        return;
+    };
+
+    egui::Window::new("Drug Stock")
+        .default_pos(egui::pos2(850.0, 850.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            ui.label(format!("{} of {} courses in stock", stock.courses(config), config.capacity));
+            let days_out = stock.samples.iter().filter(|&&(_, courses)| courses == 0).count();
+            ui.label(format!("{} courses not given, {} days stocked out", stock.stock_outs, days_out));
+
+            let points: PlotPoints = stock.samples.iter().map(|&(day, courses)| [day as f64, courses as f64]).collect();
+            Plot::new("drug_stock")
+                .include_y(0.0)
+                .include_y(config.capacity as f64)
+                .height(120.0)
+                .show(ui, |plot_ui| plot_ui.line(Line::new(points).name("Courses")));
+        });
+}
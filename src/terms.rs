@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::Path;
+
+use crate::Calendar;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PeriodKind {
+    Term,
+    Holiday,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    Date(NaiveDate),
+    Yearly(u32, u32), // Month and day, every year
+}
+
+#[derive(Debug, Clone)]
+struct Period {
+    kind: PeriodKind,
+    first: Bound,
+    last: Bound,
+    name: String,
+}
+
+impl Period {
+    fn contains(&self, date: NaiveDate) -> bool {
+        match (self.first, self.last) {
+            (Bound::Date(first), Bound::Date(last)) => (first..=last).contains(&date),
+            (Bound::Yearly(first_month, first_day), Bound::Yearly(last_month, last_day)) => {
+                let (first, last, today) = ((first_month, first_day), (last_month, last_day), (date.month(), date.day()));
+                if first <= last {
+                    (first..=last).contains(&today)
+                } else {
+                    today >= first || today <= last // Across the new year
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+// School terms and holidays, e.g. assets/school_terms.csv
+#[derive(Resource, Clone)]
+pub struct TermCalendar {
+    periods: Vec<Period>,
+}
+
+fn parse_bound(field: &str) -> Result<Bound, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(field, "%Y-%m-%d") {
+        return Ok(Bound::Date(date));
+    }
+    // A leap year, so 02-29 is accepted
+    let date = NaiveDate::parse_from_str(&format!("2000-{}", field), "%Y-%m-%d")
+        .map_err(|_| format!("expected YYYY-MM-DD or MM-DD, got '{}'", field))?;
+    Ok(Bound::Yearly(date.month(), date.day()))
+}
+
+impl TermCalendar {
+    // "kind,first,last,name" lines, where kind is term or holiday and dates are YYYY-MM-DD, or MM-DD to repeat yearly
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut periods = Vec::new();
+        for (line_number, line) in contents.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("{}: line {}: {}", path.display(), line_number + 1, message);
+            let fields: Vec<&str> = line.splitn(4, ',').map(str::trim).collect();
+            let [kind, first, last, name] = fields[..] else {
+                return Err(error("expected 4 fields".to_owned()));
+            };
+            let kind = match kind {
+                "term" => PeriodKind::Term,
+                "holiday" => PeriodKind::Holiday,
+                _ => return Err(error(format!("unknown kind '{}'", kind))),
+            };
+            let (first, last) = (parse_bound(first).map_err(error)?, parse_bound(last).map_err(error)?);
+            if matches!((first, last), (Bound::Date(_), Bound::Yearly(..)) | (Bound::Yearly(..), Bound::Date(_))) {
+                return Err(error("first and last must both have a year or both repeat yearly".to_owned()));
+            }
+            periods.push(Period {
+                kind,
+                first,
+                last,
+                name: name.to_owned(),
+            });
+        }
+        Ok(Self { periods })
+    }
+
+    fn period(&self, kind: PeriodKind, date: NaiveDate) -> Option<&Period> {
+        self.periods.iter().find(|period| period.kind == kind && period.contains(date))
+    }
+
+    pub fn holiday(&self, date: NaiveDate) -> Option<&str> {
+        self.period(PeriodKind::Holiday, date).map(|period| period.name.as_str())
+    }
+
+    // In term and not on a holiday; if no terms are listed, school runs all year apart from holidays
+    pub fn school_open(&self, date: NaiveDate) -> bool {
+        let has_terms = self.periods.iter().any(|period| period.kind == PeriodKind::Term);
+        self.holiday(date).is_none() && (!has_terms || self.period(PeriodKind::Term, date).is_some())
+    }
+}
+
+// Runs without a start date are taken to begin on Monday 1 January 2001
+pub fn date_on(calendar: &Calendar, day: u32) -> NaiveDate {
+    calendar.date(day).unwrap_or_else(|| {
+        let epoch = NaiveDate::from_ymd_opt(2001, 1, 1).expect("valid date");
+        epoch + chrono::Days::new(day as u64)
+    })
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::ids::HostId;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::scenario::TestNegativeConfig;
+use crate::scheduled::{HostAction, ScheduledActions};
+use crate::stats::Diagnostic;
+use crate::treatment::{CourseDrug, TreatmentHistory};
+use crate::trial::Vaccinated;
+use crate::{Inoculation, OutputDir, Regimen, SimRng, SimulationTime};
+
+#[derive(Resource, Default)]
+pub struct TestNegativeDesign(pub Option<TestNegativeConfig>);
+
+// Care-seeking hosts by test result and intervention status: [[positive exposed, positive unexposed],
+// [negative exposed, negative unexposed]]
+#[derive(Resource, Default)]
+pub struct TestNegativeStudy {
+    pub counts: [[u32; 2]; 2],
+    rng: Option<StdRng>,
+    day: u32,
+}
+
+impl TestNegativeStudy {
+    // Odds of intervention among test-positives over test-negatives, with Woolf's 95% interval
+    pub fn odds_ratio(&self) -> Option<(f32, f32, f32)> {
+        let [[a, b], [c, d]] = self.counts.map(|row| row.map(|count| count as f32));
+        if a * b * c * d == 0.0 {
+            return None;
+        }
+        let ratio = (a * d) / (b * c);
+        let se = (1.0 / a + 1.0 / b + 1.0 / c + 1.0 / d).sqrt();
+        Some((ratio, ratio * (-1.96 * se).exp(), ratio * (1.96 * se).exp()))
+    }
+}
+
+#[derive(Resource)]
+pub struct TestNegativeOutput(BufWriter<File>);
+
+pub fn setup_test_negative_output(
+    mut commands: Commands,
+    design: Res<TestNegativeDesign>,
+    output_dir: Res<OutputDir>,
+    mut report: ResMut<RunReport>,
+) {
+    if design.0.is_none() {
+        return;
+    }
+
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("test_negative.csv"))?);
+        writeln!(writer, "day,host,reason,test_positive,intervention")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(TestNegativeOutput(writer)),
+        Err(err) => report.output_failed("test-negative output", err),
+    }
+}
+
+// Enrolls hosts presenting for clinical treatment, and others brought in by fevers from other causes, tests them all
+// and records whether they were vaccinated or recently took MDA
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn enroll_care_seekers(
+    host_query: Query<(&HostId, &ScheduledActions, &TreatmentHistory, Option<&Children>, Option<&Vaccinated>)>,
+    inoc_query: Query<&Inoculation>,
+    design: Res<TestNegativeDesign>,
+    diagnostic: Res<Diagnostic>,
+    sim_time: Res<SimulationTime>,
+    sim_rng: Res<SimRng>,
+    mut study: ResMut<TestNegativeStudy>,
+    mut output: Option<ResMut<TestNegativeOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(config) = &design.0 else {
+        return;
+    };
+    // Once a day, just before process_hosts takes up the day's treatment requests
+    if study.rng.is_some() && study.day == sim_time.day {
+        return;
+    }
+    study.day = sim_time.day;
+    // Seeded rather than drawn, so the study doesn't change the run
+    let study = &mut *study;
+    let rng = study.rng.get_or_insert_with(|| StdRng::seed_from_u64(sim_rng.seed ^ 0x7e57));
+
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort_by_key(|(id, ..)| **id);
+    let mut result = Ok(());
+    for (id, scheduled, history, children, vaccinated) in hosts {
+        let presenting = scheduled.is_due(sim_time.day, |action| *action == HostAction::Treat(Regimen::Treatment));
+        let fever = rng.gen::<f32>() < config.fever_rate;
+        if !presenting && !fever {
+            continue;
+        }
+
+        let states = children.into_iter().flatten().filter_map(|&child| inoc_query.get(child).ok()).map(|inoc| inoc.state);
+        let positive = diagnostic.0.test(states, rng);
+        let recent_mda = history.last_course.is_some_and(|(day, drug)| {
+            drug == CourseDrug::Mda && sim_time.day - day <= config.mda_window_days
+        });
+        let exposed = vaccinated.is_some() || recent_mda;
+        study.counts[!positive as usize][!exposed as usize] += 1;
+
+        if let Some(output) = output.as_mut() {
+            let reason = if presenting { "clinical" } else { "other_fever" };
+            result = result.and_then(|_| writeln!(output.0, "{},{},{},{},{}", sim_time.day, id.0, reason, positive, exposed));
+        }
+    }
+    if let Some(output) = output.as_mut() {
+        if let Err(err) = result.and_then(|_| output.0.flush()) {
+            report.output_failed("test-negative output", err);
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn test_negative_ui(
+    mut contexts: EguiContexts,
+    design: Res<TestNegativeDesign>,
+    study: Res<TestNegativeStudy>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    if design.0.is_none() {
+        return;
+    }
+
+    egui::Window::new("Test-Negative Study")
+        .default_pos(egui::pos2(850.0, 750.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            egui::Grid::new("test_negative").striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.label("Intervention");
+                ui.label("None");
+                ui.end_row();
+                for (label, row) in ["Test-positive", "Test-negative"].into_iter().zip(study.counts) {
+                    ui.label(label);
+                    ui.label(row[0].to_string());
+                    ui.label(row[1].to_string());
+                    ui.end_row();
+                }
+            });
+            match study.odds_ratio() {
+                Some((ratio, low, high)) => ui.label(format!(
+                    "Odds ratio {:.2} (95% CI {:.2}-{:.2}), effectiveness {:.0}%",
+                    ratio,
+                    low,
+                    high,
+                    100.0 * (1.0 - ratio)
+                )),
+                None => ui.label("Odds ratio needs every cell filled"),
+            };
+        });
+}
@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::ids::HostId;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::{Host, OutputDir, SimulationTime};
+
+const ARC_GROW_SECS: f32 = 0.6; // Time for an arc to reach the infectee
+const ARC_FADE_SECS: f32 = 3.0; // Time until a new arc has faded out
+const NETWORK_ALPHA: f32 = 0.15;
+
+pub struct TransmissionEdge {
+    pub infector: Entity,
+    pub infectee: Entity,
+    pub infector_id: HostId,
+    pub infectee_id: HostId,
+    pub day: u32,
+    drawn_at: Option<f32>, // Wall-clock seconds when the arc first appeared
+}
+
+// Who infected whom, for the host-to-host routes where the infector is known
+#[derive(Resource, Default)]
+pub struct TransmissionTree {
+    pub edges: Vec<TransmissionEdge>,
+}
+
+impl TransmissionTree {
+    pub fn record(&mut self, (infector, infector_id): (Entity, HostId), (infectee, infectee_id): (Entity, HostId), day: u32) {
+        self.edges.push(TransmissionEdge {
+            infector,
+            infectee,
+            infector_id,
+            infectee_id,
+            day,
+            drawn_at: None,
+        });
+    }
+}
+
+// transmission.csv, appended as edges are recorded
+#[derive(Resource)]
+pub struct TransmissionOutput {
+    writer: BufWriter<File>,
+    written: usize, // Edges of the current run already written
+}
+
+pub fn setup_transmission_output(mut commands: Commands, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("transmission.csv"))?);
+        writeln!(writer, "day,infector,infectee")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(TransmissionOutput { writer, written: 0 }),
+        Err(err) => report.output_failed("transmission edges", err),
+    }
+}
+
+pub fn write_transmission_edges(tree: Res<TransmissionTree>, output: Option<ResMut<TransmissionOutput>>, mut report: ResMut<RunReport>) {
+    let Some(mut output) = output else {
+        return;
+    };
+    // A reset clears the tree; the file carries on with the new run
+    if tree.edges.len() < output.written {
+        output.written = 0;
+    }
+    if tree.edges.len() == output.written {
+        return;
+    }
+
+    let mut result = Ok(());
+    for edge in &tree.edges[output.written..] {
+        result = result.and_then(|_| writeln!(output.writer, "{},{},{}", edge.day, edge.infector_id.0, edge.infectee_id.0));
+    }
+    output.written = tree.edges.len();
+    if let Err(err) = result.and_then(|_| output.writer.flush()) {
+        report.output_failed("transmission edges", err);
+    }
+}
+
+#[derive(Resource)]
+pub struct TransmissionView {
+    pub show_network: bool, // Keep faded arcs as a faint network
+    pub show_clusters: bool,
+    pub cluster_window_days: u32, // Only edges this recent link hosts into a cluster
+}
+
+impl Default for TransmissionView {
+    fn default() -> Self {
+        Self {
+            show_network: false,
+            show_clusters: true,
+            cluster_window_days: 30,
+        }
+    }
+}
+
+// Connected components of the recent transmission edges, largest first
+#[derive(Resource, Default)]
+pub struct Clusters {
+    pub clusters: Vec<Vec<Entity>>,
+}
+
+#[cfg(feature = "gui")]
+fn cluster_color(index: usize) -> Color {
+    const PALETTE: [Color; 6] = [Color::CYAN, Color::FUCHSIA, Color::LIME_GREEN, Color::GOLD, Color::TOMATO, Color::VIOLET];
+    PALETTE[index % PALETTE.len()]
+}
+
+pub fn detect_clusters(
+    tree: Res<TransmissionTree>,
+    view: Res<TransmissionView>,
+    sim_time: Res<SimulationTime>,
+    mut clusters: ResMut<Clusters>,
+    mut last_update: Local<Option<(u32, usize)>>,
+) {
+    // Recompute on a new day, a new edge or a new window
+    let key = (sim_time.day, tree.edges.len());
+    if *last_update == Some(key) && !view.is_changed() {
+        return;
+    }
+    *last_update = Some(key);
+
+    // Union-find over the hosts in recent edges
+    let mut parent: HashMap<Entity, Entity> = HashMap::new();
+    fn root(parent: &mut HashMap<Entity, Entity>, entity: Entity) -> Entity {
+        let next = *parent.entry(entity).or_insert(entity);
+        if next == entity {
+            return entity;
+        }
+        let found = root(parent, next);
+        parent.insert(entity, found);
+        found
+    }
+    let since = sim_time.day.saturating_sub(view.cluster_window_days);
+    for edge in tree.edges.iter().filter(|edge| edge.day >= since) {
+        let (a, b) = (root(&mut parent, edge.infector), root(&mut parent, edge.infectee));
+        if a != b {
+            parent.insert(a, b);
+        }
+    }
+
+    let mut components: BTreeMap<Entity, Vec<Entity>> = BTreeMap::new();
+    let mut hosts: Vec<Entity> = parent.keys().copied().collect();
+    hosts.sort();
+    for host in hosts {
+        let component = root(&mut parent, host);
+        components.entry(component).or_default().push(host);
+    }
+    clusters.clusters = components.into_values().collect();
+    clusters.clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+}
+
+// Outlines the hosts of each cluster in a shared color
+#[cfg(feature = "gui")]
+pub fn draw_clusters(mut gizmos: Gizmos, clusters: Res<Clusters>, view: Res<TransmissionView>, host_query: Query<&Transform, With<Host>>) {
+    if !view.show_clusters {
+        return;
+    }
+    for (index, cluster) in clusters.clusters.iter().enumerate() {
+        for transform in cluster.iter().filter_map(|&host| host_query.get(host).ok()) {
+            gizmos.rect_2d(transform.translation.truncate(), 0.0, Vec2::new(60.0, 15.0), cluster_color(index));
+        }
+    }
+}
+
+// Curving arc from infector to infectee that grows, then fades
+#[cfg(feature = "gui")]
+pub fn draw_transmission_arcs(
+    mut gizmos: Gizmos,
+    mut tree: ResMut<TransmissionTree>,
+    view: Res<TransmissionView>,
+    host_query: Query<&Transform, With<Host>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for edge in tree.edges.iter_mut() {
+        let age = now - *edge.drawn_at.get_or_insert(now);
+        let alpha = if age < ARC_GROW_SECS {
+            1.0
+        } else {
+            1.0 - (age - ARC_GROW_SECS) / (ARC_FADE_SECS - ARC_GROW_SECS)
+        };
+        let alpha = if view.show_network { alpha.max(NETWORK_ALPHA) } else { alpha };
+        if alpha <= 0.0 {
+            continue;
+        }
+        let (Ok(from), Ok(to)) = (host_query.get(edge.infector), host_query.get(edge.infectee)) else {
+            continue;
+        };
+
+        let (from, to) = (from.translation.truncate(), to.translation.truncate());
+        let bulge = (to - from).perp().normalize_or_zero() * from.distance(to) * 0.3;
+        let control = (from + to) / 2.0 + if bulge.y < 0.0 { -bulge } else { bulge }; // Always arc upwards
+        let progress = (age / ARC_GROW_SECS).min(1.0);
+        let points = (0..=20).map(|i| {
+            let t = progress * i as f32 / 20.0;
+            from.lerp(control, t).lerp(control.lerp(to, t), t)
+        });
+        gizmos.linestrip_2d(points, Color::rgba(1.0, 0.9, 0.3, alpha));
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn transmission_ui(
+    mut contexts: EguiContexts,
+    tree: Res<TransmissionTree>,
+    clusters: Res<Clusters>,
+    mut view: ResMut<TransmissionView>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    if tree.edges.is_empty() {
+        return;
+    }
+
+    egui::Window::new("Transmission")
+        .default_pos(egui::pos2(850.0, 550.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            let latest = tree.edges.last().map_or(0, |edge| edge.day);
+            ui.label(format!("Host-to-host transmissions: {} (latest on day {})", tree.edges.len(), latest));
+            ui.checkbox(&mut view.show_network, "Keep arcs as a network");
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut view.show_clusters, "Outline clusters");
+                ui.add(egui::Slider::new(&mut view.cluster_window_days, 7..=180).text("Days"));
+            });
+            let sizes: Vec<usize> = clusters.clusters.iter().map(|cluster| cluster.len()).collect();
+            if sizes.is_empty() {
+                ui.label("No recent clusters");
+                return;
+            }
+            ui.label(format!(
+                "{} clusters, largest {} hosts, mean {:.1}",
+                sizes.len(),
+                sizes[0],
+                sizes.iter().sum::<usize>() as f32 / sizes.len() as f32
+            ));
+            let mut by_size: BTreeMap<usize, usize> = BTreeMap::new();
+            for size in sizes {
+                *by_size.entry(size).or_default() += 1;
+            }
+            egui::Grid::new("cluster_sizes").striped(true).show(ui, |ui| {
+                ui.label("Size");
+                ui.label("Clusters");
+                ui.end_row();
+                for (size, count) in by_size {
+                    ui.label(size.to_string());
+                    ui.label(count.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use crate::scenario::TreatmentPolicyConfig;
+use crate::{Drug, Params, Regimen};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "inspector", derive(Reflect))]
+pub enum CourseDrug {
+    Treatment,
+    Mda,
+    SecondLine,
+}
+
+// Courses of treatment a host has taken up
+#[derive(Component, Default, Clone)]
+#[cfg_attr(feature = "inspector", derive(Reflect), reflect(Component))]
+pub struct TreatmentHistory {
+    pub courses: u32,
+    pub failures: u32, // Courses after which an inoculation survived
+    pub last_course: Option<(u32, CourseDrug)>, // Day and drug of the latest course
+}
+
+impl TreatmentHistory {
+    pub fn record(&mut self, day: u32, drug: CourseDrug, failed: bool) {
+        self.courses += 1;
+        self.failures += failed as u32;
+        self.last_course = Some((day, drug));
+    }
+}
+
+// Rules on who gets which drug, from the scenario's [treatment_policy]
+#[derive(Resource, Clone, Default)]
+pub struct TreatmentPolicy {
+    pub max_courses: Option<u32>,
+    pub switch_after_failures: Option<u32>,
+    pub second_line: Drug,
+}
+
+impl TreatmentPolicy {
+    pub fn new(config: &TreatmentPolicyConfig) -> Self {
+        Self {
+            max_courses: config.max_courses,
+            switch_after_failures: config.switch_after_failures,
+            second_line: config.second_line_drug(),
+        }
+    }
+
+    // Hosts who have reached the cumulative toxicity limit are not offered another course
+    pub fn course_allowed(&self, history: &TreatmentHistory) -> bool {
+        self.max_courses.is_none_or(|max_courses| history.courses < max_courses)
+    }
+
+    // Clinical treatment and screening move to the second-line drug after repeated failures; MDA keeps its drug
+    pub fn drug(&self, regimen: Regimen, history: &TreatmentHistory, params: &Params) -> (CourseDrug, Drug) {
+        if regimen == Regimen::Mda {
+            return (CourseDrug::Mda, *params.drug(regimen));
+        }
+        if self.switch_after_failures.is_some_and(|failures| history.failures >= failures) {
+            return (CourseDrug::SecondLine, self.second_line);
+        }
+        (CourseDrug::Treatment, *params.drug(regimen))
+    }
+}
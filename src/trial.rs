@@ -0,0 +1,336 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::autosave::write_atomic;
+use crate::clock::DayTick;
+use crate::coverage::{Dose, DoseOffered, OfferOutcome};
+use crate::event_log::EventLog;
+use crate::ids::HostId;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::scenario::{TrialConfig, TrialIntervention};
+use crate::scheduled::ScheduledActions;
+use crate::stats::Diagnostic;
+use crate::{Host, InfectionState, Inoculation, OutputDir, Params, Regimen, SimRng};
+
+#[derive(Resource, Default)]
+pub struct TrialDesign(pub Option<TrialConfig>);
+
+// Fraction of infectious exposures the host's vaccine blocks
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Vaccinated(pub f32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Arm {
+    Control,
+    Intervention,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Analysis {
+    IntentionToTreat, // Everyone by randomized arm
+    PerProtocol,      // Leaving out intervention-arm hosts who refused a dose
+}
+
+struct Participant {
+    arm: Arm,
+    protocol: bool, // Took every dose offered so far
+    days: u32,      // Person-days of follow-up so far
+    infections: u32,
+    cases: u32, // Acute infections picked up by the diagnostic
+}
+
+pub struct ArmSummary {
+    pub arm: Arm,
+    pub participants: usize,
+    pub person_years: f32,
+    pub infections: u32,
+    pub cases: u32,
+}
+
+impl ArmSummary {
+    // Events per person-year
+    pub fn rate(&self, events: u32) -> f32 {
+        if self.person_years > 0.0 {
+            events as f32 / self.person_years
+        } else {
+            0.0
+        }
+    }
+}
+
+// Intervention over control rate ratio with a Wald 95% interval on the log scale
+pub fn rate_ratio(intervention: &ArmSummary, control: &ArmSummary, events: fn(&ArmSummary) -> u32) -> Option<(f32, f32, f32)> {
+    if events(intervention) == 0 || events(control) == 0 {
+        return None;
+    }
+    let ratio = intervention.rate(events(intervention)) / control.rate(events(control));
+    let se = (1.0 / events(intervention) as f32 + 1.0 / events(control) as f32).sqrt();
+    Some((ratio, ratio * (-1.96 * se).exp(), ratio * (1.96 * se).exp()))
+}
+
+#[derive(Resource, Default)]
+pub struct Trial {
+    participants: BTreeMap<HostId, Participant>,
+    acute_seen: HashSet<Entity>, // Inoculations already considered as cases
+    rng: Option<StdRng>,
+    enrolled: bool,
+    finished: bool,
+}
+
+impl Trial {
+    pub fn summaries(&self, analysis: Analysis) -> Vec<ArmSummary> {
+        [Arm::Control, Arm::Intervention]
+            .into_iter()
+            .map(|arm| {
+                let members: Vec<&Participant> = self
+                    .participants
+                    .values()
+                    .filter(|p| p.arm == arm && (analysis == Analysis::IntentionToTreat || p.protocol))
+                    .collect();
+                ArmSummary {
+                    arm,
+                    participants: members.len(),
+                    person_years: members.iter().map(|p| p.days).sum::<u32>() as f32 / 365.0,
+                    infections: members.iter().map(|p| p.infections).sum(),
+                    cases: members.iter().map(|p| p.cases).sum(),
+                }
+            })
+            .filter(|summary| summary.participants > 0)
+            .collect()
+    }
+
+    fn write_csv(&self, config: &TrialConfig, output_dir: &OutputDir) -> std::io::Result<()> {
+        let mut csv = String::from(
+            "analysis,arm,participants,person_years,infections,cases,case_rate_per_person_year,case_rate_ratio,ratio_low,ratio_high,\
+             efficacy,true_efficacy\n",
+        );
+        let true_efficacy = match config.intervention {
+            Some(TrialIntervention::Vaccine { efficacy }) => format!("{:.3}", efficacy),
+            _ => String::new(),
+        };
+        for analysis in [Analysis::IntentionToTreat, Analysis::PerProtocol] {
+            let summaries = self.summaries(analysis);
+            let ratio = match &summaries[..] {
+                [control, intervention] => rate_ratio(intervention, control, |summary| summary.cases),
+                _ => None,
+            };
+            for summary in &summaries {
+                let estimate = match (summary.arm, ratio) {
+                    (Arm::Intervention, Some((ratio, low, high))) => {
+                        format!("{:.3},{:.3},{:.3},{:.3},{}", ratio, low, high, 1.0 - ratio, true_efficacy)
+                    }
+                    _ => ",,,,".to_owned(),
+                };
+                csv.push_str(&format!(
+                    "{:?},{:?},{},{:.2},{},{},{:.3},{}\n",
+                    analysis,
+                    summary.arm,
+                    summary.participants,
+                    summary.person_years,
+                    summary.infections,
+                    summary.cases,
+                    summary.rate(summary.cases),
+                    estimate
+                ));
+            }
+        }
+        fs::create_dir_all(&output_dir.0)?;
+        write_atomic(&output_dir.0.join("trial_results.csv"), csv)
+    }
+}
+
+// Enrolls the cohort, gives the intervention arm its doses and counts person-time, infections and detected acute cases
+// until follow-up ends
+#[allow(clippy::too_many_arguments)]
+pub fn run_trial(
+    mut commands: Commands,
+    mut host_query: Query<(Entity, &HostId, &Host, &mut ScheduledActions)>,
+    new_inocs: Query<&Parent, Added<Inoculation>>,
+    acute_inocs: Query<(Entity, &Parent, &Inoculation), Changed<Inoculation>>,
+    id_query: Query<&HostId>,
+    design: Res<TrialDesign>,
+    diagnostic: Res<Diagnostic>,
+    params: Res<Params>,
+    sim_rng: Res<SimRng>,
+    output_dir: Res<OutputDir>,
+    mut trial: ResMut<Trial>,
+    mut event_log: ResMut<EventLog>,
+    mut ticks: EventReader<DayTick>,
+    mut offers: EventWriter<DoseOffered>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(config) = &design.0 else {
+        return;
+    };
+    if trial.finished {
+        return;
+    }
+
+    if trial.enrolled {
+        let trial = &mut *trial;
+        for parent in new_inocs.iter() {
+            if let Some(participant) = id_query.get(parent.get()).ok().and_then(|id| trial.participants.get_mut(id)) {
+                participant.infections += 1;
+            }
+        }
+        for (entity, parent, inoc) in acute_inocs.iter() {
+            // Episodes already under way at enrollment don't count
+            if inoc.state != InfectionState::A || inoc.start_day < config.enroll_day || !trial.acute_seen.insert(entity) {
+                continue;
+            }
+            if let Some(participant) = id_query.get(parent.get()).ok().and_then(|id| trial.participants.get_mut(id)) {
+                let rng = trial.rng.as_mut().expect("seeded at enrollment");
+                participant.cases += (rng.gen::<f32>() < diagnostic.0.sensitivity_acute) as u32;
+            }
+        }
+    }
+
+    let Some(day) = ticks.read().last().map(|tick| tick.day) else {
+        return;
+    };
+    if day < config.enroll_day {
+        return;
+    }
+
+    if !trial.enrolled {
+        // Seeded rather than drawn, so running a trial doesn't change who gets infected in the control arm
+        let mut rng = StdRng::seed_from_u64(sim_rng.seed ^ 0x7e1a1);
+        let mut ids: Vec<HostId> = host_query.iter().map(|(_, id, _, _)| *id).collect();
+        ids.sort();
+        ids.shuffle(&mut rng);
+        ids.truncate(config.size);
+        for (index, id) in ids.into_iter().enumerate() {
+            let arm = if config.intervention.is_some() && index % 2 == 1 { Arm::Intervention } else { Arm::Control };
+            let participant = Participant {
+                arm,
+                protocol: true,
+                days: 0,
+                infections: 0,
+                cases: 0,
+            };
+            trial.participants.insert(id, participant);
+        }
+        trial.rng = Some(rng);
+        trial.enrolled = true;
+        event_log.log(day, format!("Trial enrolled {} hosts", trial.participants.len()));
+    }
+
+    let since_enrollment = day - config.enroll_day;
+    if since_enrollment >= config.follow_up_days {
+        trial.finished = true;
+        event_log.log(day, "Trial follow-up complete");
+        match trial.write_csv(config, &output_dir) {
+            Ok(()) => info!("Wrote {}", output_dir.0.join("trial_results.csv").display()),
+            Err(err) => report.output_failed("trial results", err),
+        }
+        return;
+    }
+
+    let mut hosts: Vec<_> = host_query.iter_mut().collect();
+    hosts.sort_by_key(|(_, id, _, _)| **id);
+    let trial = &mut *trial;
+    for (entity, id, host, scheduled) in hosts.iter_mut() {
+        let Some(participant) = trial.participants.get_mut(id) else {
+            continue;
+        };
+        participant.days += 1;
+        match (participant.arm, config.intervention) {
+            (Arm::Intervention, Some(TrialIntervention::Chemoprevention { repeat_days })) => {
+                let due = since_enrollment == 0 || repeat_days.is_some_and(|days| since_enrollment.is_multiple_of(days.max(1)));
+                if due {
+                    scheduled.request_treatment(day, Regimen::Mda);
+                }
+                // Uptake is decided when the course is offered, so a refusal shows up in the host's last offer
+                let refused = host.last_offer.is_some_and(|(offer_day, regimen, accepted)| {
+                    offer_day >= config.enroll_day && regimen == Regimen::Mda && !accepted
+                });
+                participant.protocol &= !refused;
+            }
+            (Arm::Intervention, Some(TrialIntervention::Vaccine { efficacy })) if since_enrollment == 0 => {
+                // Vaccine uptake follows the same propensity to refuse as MDA
+                let rng = trial.rng.as_mut().expect("seeded at enrollment");
+                participant.protocol = host.accepts(Regimen::Mda, &params, rng);
+                if participant.protocol {
+                    commands.entity(*entity).insert(Vaccinated(efficacy));
+                }
+                let outcome = if participant.protocol { OfferOutcome::Delivered } else { OfferOutcome::Refused };
+                offers.send(DoseOffered { host: *entity, dose: Dose::Vaccine, outcome });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn trial_ui(mut contexts: EguiContexts, design: Res<TrialDesign>, trial: Res<Trial>, analytics: Res<AnalyticsWindow>) {
+    let Some(config) = &design.0 else {
+        return;
+    };
+
+    egui::Window::new("Trial")
+        .default_pos(egui::pos2(450.0, 850.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            if !trial.enrolled {
+                ui.label(format!("Enrolls {} hosts on day {}", config.size, config.enroll_day));
+                return;
+            }
+            ui.label(if trial.finished {
+                "Follow-up complete".to_owned()
+            } else {
+                format!("Following up until day {}", config.enroll_day + config.follow_up_days)
+            });
+
+            for analysis in [Analysis::IntentionToTreat, Analysis::PerProtocol] {
+                let summaries = trial.summaries(analysis);
+                ui.separator();
+                ui.label(match analysis {
+                    Analysis::IntentionToTreat => "Intention to treat",
+                    Analysis::PerProtocol => "Per protocol",
+                });
+                egui::Grid::new(format!("trial_arms_{:?}", analysis)).striped(true).show(ui, |ui| {
+                    ui.label("Arm");
+                    ui.label("Hosts");
+                    ui.label("Person-years");
+                    ui.label("Infections");
+                    ui.label("Cases");
+                    ui.label("Cases /PY");
+                    ui.end_row();
+                    for summary in &summaries {
+                        ui.label(format!("{:?}", summary.arm));
+                        ui.label(summary.participants.to_string());
+                        ui.label(format!("{:.1}", summary.person_years));
+                        ui.label(summary.infections.to_string());
+                        ui.label(summary.cases.to_string());
+                        ui.label(format!("{:.2}", summary.rate(summary.cases)));
+                        ui.end_row();
+                    }
+                });
+                if let [control, intervention] = &summaries[..] {
+                    match rate_ratio(intervention, control, |summary| summary.cases) {
+                        Some((ratio, low, high)) => ui.label(format!(
+                            "Case rate ratio {:.2} (95% CI {:.2}-{:.2}), efficacy {:.0}%",
+                            ratio,
+                            low,
+                            high,
+                            100.0 * (1.0 - ratio)
+                        )),
+                        None => ui.label("Rate ratio needs cases in both arms"),
+                    };
+                }
+            }
+            if let Some(TrialIntervention::Vaccine { efficacy }) = config.intervention {
+                ui.separator();
+                ui.label(format!("True efficacy against infection: {:.0}%", 100.0 * efficacy));
+            }
+        });
+}
@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::focus::FocalHost;
+use crate::{InfectionState, Inoculation, SimulationEvent, SimulationSpeed, SimulationTime};
+
+// Fixed seed so every classroom sees the same canned scenario
+pub const TUTORIAL_SEED: u64 = 2024;
+
+#[derive(Clone, Copy)]
+enum Trigger {
+    Immediately,
+    FirstInoculation(InfectionState), // First inoculation to reach this state
+    FirstTreatment,
+    Coinfection, // First host with more than one inoculation
+    Day(u32),
+}
+
+struct Step {
+    trigger: Trigger,
+    caption: &'static str,
+}
+
+const STEPS: [Step; 6] = [
+    Step {
+        trigger: Trigger::Immediately,
+        caption: "Each bar is a host. Every host has just received an inoculation (blue square), \
+                  which spends about a week developing in the liver before reaching the blood.",
+    },
+    Step {
+        trigger: Trigger::FirstInoculation(InfectionState::A),
+        caption: "This host just developed an acute infection (red). Acute infections make the host sick, \
+                  and some hosts will seek treatment after a short delay.",
+    },
+    Step {
+        trigger: Trigger::FirstTreatment,
+        caption: "A host was treated: all of its inoculations were cleared and it is now protected by \
+                  prophylaxis (green), which blocks new inoculations at the liver stage.",
+    },
+    Step {
+        trigger: Trigger::FirstInoculation(InfectionState::C),
+        caption: "This inoculation became chronic (orange): a low-level infection that can persist \
+                  for many months without prompting treatment.",
+    },
+    Step {
+        trigger: Trigger::Coinfection,
+        caption: "This host now carries more than one inoculation at once. Squares stack above the bar, \
+                  and the host's color shows its most severe infection.",
+    },
+    Step {
+        trigger: Trigger::Day(120),
+        caption: "That's the tour! Try the sliders to change incidence or treatment and watch how the \
+                  mix of acute and chronic infections responds.",
+    },
+];
+
+#[derive(Resource, Default)]
+pub struct Tutorial {
+    step: usize,
+    showing_caption: bool,
+}
+
+// Pauses the simulation when the current step's trigger fires
+pub fn advance_tutorial(
+    host_query: Query<(Entity, &Children)>,
+    inoc_query: Query<(&Inoculation, &Parent)>,
+    mut events: EventReader<SimulationEvent>,
+    sim_time: Res<SimulationTime>,
+    mut tutorial: ResMut<Tutorial>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut focal: ResMut<FocalHost>,
+) {
+    let treated = events.read().any(|event| matches!(event, SimulationEvent::Treatment(_)));
+    if tutorial.showing_caption || tutorial.step >= STEPS.len() {
+        return;
+    }
+
+    let fired = match STEPS[tutorial.step].trigger {
+        Trigger::Immediately => Some(None),
+        Trigger::FirstInoculation(state) => inoc_query
+            .iter()
+            .find(|(inoc, _)| inoc.state == state)
+            .map(|(_, parent)| Some(parent.get())),
+        Trigger::FirstTreatment => treated.then_some(None),
+        Trigger::Coinfection => host_query
+            .iter()
+            .find(|(_, children)| children.len() > 1)
+            .map(|(entity, _)| Some(entity)),
+        Trigger::Day(day) => (sim_time.day >= day).then_some(None),
+    };
+
+    if let Some(host) = fired {
+        tutorial.showing_caption = true;
+        speed.paused = true;
+        if host.is_some() {
+            focal.select(host);
+        }
+    }
+}
+
+pub fn tutorial_ui(
+    mut contexts: EguiContexts,
+    mut tutorial: ResMut<Tutorial>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut focal: ResMut<FocalHost>,
+) {
+    if !tutorial.showing_caption {
+        return;
+    }
+
+    egui::Window::new("Tutorial")
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -80.0))
+        .collapsible(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.set_max_width(420.0);
+            ui.label(egui::RichText::new(STEPS[tutorial.step].caption).size(18.0));
+            ui.label(format!("Step {} of {}", tutorial.step + 1, STEPS.len()));
+
+            if ui.button("Continue").clicked() {
+                tutorial.showing_caption = false;
+                tutorial.step += 1;
+                speed.paused = false;
+                focal.select(None);
+            }
+        });
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::event_log::EventLog;
+use crate::param_docs::ParamInfo;
+use crate::Params;
+
+type ParamField = fn(&mut Params) -> &mut f32;
+
+struct ParamEdit {
+    label: &'static str,
+    field: ParamField,
+    before: f32,
+    after: f32,
+    open: bool, // Still being dragged, so further changes extend this edit
+}
+
+// Undo and redo stacks for the parameter sliders in the controls window
+#[derive(Resource, Default)]
+pub struct ParamHistory {
+    undo: Vec<ParamEdit>,
+    redo: Vec<ParamEdit>,
+}
+
+impl ParamHistory {
+    // A labelled slider whose changes are recorded, one edit per drag, with the param's help on hover
+    pub fn slider(&mut self, ui: &mut egui::Ui, params: &mut ResMut<Params>, info: &ParamInfo) {
+        let (label, field) = (info.label, info.field);
+        ui.label(label).on_hover_text(info.tooltip());
+
+        // Read without marking Params changed every frame
+        let before = *field(params.bypass_change_detection());
+        let mut after = before;
+        let slider = egui::Slider::new(&mut after, info.slider_range()).text(label).step_by(info.step);
+        let response = ui.add(slider).on_hover_text(info.tooltip());
+        if response.changed() {
+            *field(params) = after;
+            match self.undo.last_mut() {
+                Some(edit) if edit.open && edit.label == label => edit.after = after,
+                _ => {
+                    self.undo.push(ParamEdit {
+                        label,
+                        field,
+                        before,
+                        after,
+                        open: true,
+                    });
+                    self.redo.clear();
+                }
+            }
+        }
+        if !response.dragged() {
+            if let Some(edit) = self.undo.last_mut().filter(|edit| edit.label == label) {
+                edit.open = false;
+            }
+        }
+    }
+
+    // Undo and redo buttons, also on Ctrl+Z and Ctrl+Shift+Z
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui, params: &mut ResMut<Params>, day: u32, log: &mut EventLog) {
+        let redo_key = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+        let undo_key = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        // Redo first, since Ctrl+Z also matches with Shift held
+        let mut redo = ui.input_mut(|input| input.consume_shortcut(&redo_key));
+        let mut undo = !redo && ui.input_mut(|input| input.consume_shortcut(&undo_key));
+
+        ui.horizontal(|ui| {
+            let undo_text = self.undo.last().map_or("Undo".to_owned(), |edit| format!("Undo {}", edit.label));
+            undo |= ui.add_enabled(!self.undo.is_empty(), egui::Button::new(undo_text)).clicked();
+            let redo_text = self.redo.last().map_or("Redo".to_owned(), |edit| format!("Redo {}", edit.label));
+            redo |= ui.add_enabled(!self.redo.is_empty(), egui::Button::new(redo_text)).clicked();
+        });
+
+        if undo {
+            if let Some(mut edit) = self.undo.pop() {
+                *(edit.field)(params) = edit.before;
+                log.log(day, format!("Undid {}: {:.3} -> {:.3}", edit.label, edit.after, edit.before));
+                edit.open = false;
+                self.redo.push(edit);
+            }
+        } else if redo {
+            if let Some(edit) = self.redo.pop() {
+                *(edit.field)(params) = edit.after;
+                log.log(day, format!("Redid {}: {:.3} -> {:.3}", edit.label, edit.before, edit.after));
+                self.undo.push(edit);
+            }
+        }
+    }
+}
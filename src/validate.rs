@@ -0,0 +1,229 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::budget::EntityBudget;
+use crate::headless::HeadlessRun;
+use crate::ids::HostId;
+use crate::infectiousness::InfectiousnessCurve;
+use crate::prcc::prcc;
+use crate::scenario::Scenario;
+use crate::scheduled::ScheduledActions;
+use crate::sensitivity::latin_hypercube;
+use crate::snapshot::{HostSnapshot, Snapshot, SNAPSHOT_VERSION};
+use crate::state_hash::{record_state_hash, setup_state_hash_output, StateHashes};
+use crate::stats::StateHistory;
+use crate::{Host, Inoculation, OutputDir, Params, SimulationEvent};
+
+const SEED: u64 = 1;
+const HOSTS: u32 = 20;
+const DAYS: u32 = 90;
+const BUDGET: EntityBudget = EntityBudget {
+    max_per_host: 4, // Low enough that the caps are reached and checked
+    max_total: 60,
+};
+
+// What one run of the validation simulation saw
+struct Observed {
+    hashes: Vec<u64>,
+    infections: usize,
+    final_inoculations: usize,
+    violations: Vec<String>, // Broken invariants, the first few kept
+    snapshot: Snapshot,
+}
+
+fn run(output_dir: Option<&Path>) -> Observed {
+    let mut run = HeadlessRun::new(&Params::default(), &Scenario::default(), BUDGET, &InfectiousnessCurve::default(), HOSTS, SEED);
+    run.world.init_resource::<StateHashes>();
+    run.schedule.add_systems(record_state_hash.after(crate::stats::record_host_states));
+    if let Some(output_dir) = output_dir {
+        run.world.insert_resource(OutputDir(output_dir.to_path_buf()));
+        run.world.run_system_once(setup_state_hash_output);
+    }
+
+    let (mut hashes, mut infections, mut violations) = (Vec::new(), 0, Vec::new());
+    for _ in 0..DAYS {
+        infections += run.step().iter().filter(|event| matches!(event, SimulationEvent::Infection)).count();
+        let day = run.day();
+        let mut violation = |message: String| {
+            if violations.len() < 5 {
+                violations.push(format!("day {}: {}", day, message));
+            }
+        };
+
+        let hosts = run.world.query::<(Entity, &Host, Option<&Children>)>().iter(&run.world).count();
+        let counted: usize = run.world.resource::<StateHistory>().samples.last().map_or(0, |(_, counts)| counts.iter().sum());
+        if hosts != HOSTS as usize || counted != hosts {
+            violation(format!("{} hosts, {} counted by state, expected {}", hosts, counted, HOSTS));
+        }
+
+        let mut total = 0;
+        for (inoc, parent) in run.world.query::<(&Inoculation, Option<&Parent>)>().iter(&run.world) {
+            total += 1;
+            if parent.is_none_or(|parent| run.world.get::<Host>(parent.get()).is_none()) {
+                violation("an inoculation has no host".to_owned());
+            }
+            if inoc.infected_day > inoc.start_day || inoc.start_day > day {
+                violation(format!("inoculation infected day {}, state started day {}", inoc.infected_day, inoc.start_day));
+            }
+            if !inoc.delay_days.is_finite() || inoc.delay_days < 0.0 {
+                violation(format!("inoculation state lasts {} days", inoc.delay_days));
+            }
+        }
+        let most = run.world.query::<&Children>().iter(&run.world).map(|children| children.len()).max().unwrap_or(0);
+        if most > BUDGET.max_per_host || total > BUDGET.max_total {
+            violation(format!("{} inoculations ({} in one host) over the limits", total, most));
+        }
+
+        hashes.push(run.world.resource::<StateHashes>().latest.map_or(0, |(_, hash)| hash));
+    }
+
+    let day = run.day();
+    let params = run.world.resource::<Params>().clone();
+    let mut hosts: Vec<_> = run.world.query::<(&HostId, &Host, &ScheduledActions, Option<&Children>)>().iter(&run.world).collect();
+    hosts.sort_by_key(|(id, ..)| **id);
+    let snapshot = Snapshot {
+        format_version: SNAPSHOT_VERSION,
+        day,
+        hosts: hosts
+            .into_iter()
+            .map(|(_, host, actions, children)| {
+                let inoculations = children.into_iter().flatten().filter_map(|&child| run.world.get::<Inoculation>(child)).collect();
+                HostSnapshot::capture(host, actions, inoculations, day, &params)
+            })
+            .collect(),
+    };
+    let final_inoculations = run.world.query::<&Inoculation>().iter(&run.world).count();
+    Observed {
+        hashes,
+        infections,
+        final_inoculations,
+        violations,
+        snapshot,
+    }
+}
+
+// state_hash.csv: a `day,hash` header, then every day in order with a 16-digit hex hash
+fn check_state_hash_csv(path: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut lines = contents.lines();
+    if lines.next() != Some("day,hash") {
+        return Err("header is not `day,hash`".to_owned());
+    }
+    let mut rows = 0;
+    for (expected_day, line) in (1..).zip(lines) {
+        let (day, hash) = line.split_once(',').ok_or_else(|| format!("row `{}` has one column", line))?;
+        if day.parse::<u32>() != Ok(expected_day) {
+            return Err(format!("row `{}` should be day {}", line, expected_day));
+        }
+        if hash.len() != 16 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("row `{}` doesn't have a 16-digit hex hash", line));
+        }
+        rows += 1;
+    }
+    if rows != DAYS {
+        return Err(format!("{} rows, expected {}", rows, DAYS));
+    }
+    Ok(())
+}
+
+// A snapshot written as JSON reads back in the current format with every host unchanged
+fn check_snapshot(snapshot: &Snapshot) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(snapshot).map_err(|err| err.to_string())?;
+    let value: serde_json::Value = serde_json::from_str(&json).map_err(|err| err.to_string())?;
+    if value.get("format_version").and_then(serde_json::Value::as_u64) != Some(SNAPSHOT_VERSION as u64) {
+        return Err(format!("format_version is not {}", SNAPSHOT_VERSION));
+    }
+    let restored = Snapshot::parse(&json)?;
+    if restored.day != snapshot.day || restored.hosts.len() != snapshot.hosts.len() {
+        return Err(format!("read back as {} hosts on day {}", restored.hosts.len(), restored.day));
+    }
+    for (index, (a, b)) in snapshot.hosts.iter().zip(&restored.hosts).enumerate() {
+        if let Some(difference) = a.differences(b).first() {
+            return Err(format!("host {} changed: {}", index, difference));
+        }
+    }
+    Ok(())
+}
+
+// A Latin hypercube puts exactly one sample in each stratum of every param, and PRCCs on a design whose outcome rises
+// with one param, falls with another and ignores a third come out strongly positive, strongly negative and near zero
+fn check_sensitivity() -> Result<(), String> {
+    const SAMPLES: usize = 100;
+    let ranges = [(0.0, 1.0), (10.0, 20.0), (-5.0, 5.0)];
+    let samples = latin_hypercube(&ranges, SAMPLES as u32, &mut StdRng::seed_from_u64(SEED));
+    for (param, &(low, high)) in ranges.iter().enumerate() {
+        let mut used = [false; SAMPLES];
+        for sample in &samples {
+            let value = sample[param];
+            if !(low..high).contains(&value) {
+                return Err(format!("sample {} outside [{}, {})", value, low, high));
+            }
+            used[(((value - low) / (high - low)) * SAMPLES as f32).min(SAMPLES as f32 - 1.0) as usize] = true;
+        }
+        if let Some(stratum) = used.iter().position(|used| !used) {
+            return Err(format!("param {} has no sample in stratum {} of {}", param, stratum, SAMPLES));
+        }
+    }
+
+    let inputs: Vec<Vec<f64>> = (0..ranges.len()).map(|param| samples.iter().map(|sample| sample[param] as f64).collect()).collect();
+    let output: Vec<f64> = samples.iter().map(|sample| (sample[0] as f64).exp() - sample[2] as f64 / 5.0).collect();
+    let values = prcc(&inputs, &output);
+    match values[..] {
+        [Some(rising), Some(ignored), Some(falling)] if rising > 0.7 && falling < -0.7 && ignored.abs() < 0.3 => Ok(()),
+        _ => Err(format!("PRCCs {:?}, expected strongly positive, near zero and strongly negative", values)),
+    }
+}
+
+// Runs a small fixed-seed simulation twice with the default params and checks the model's invariants, that the runs
+// match, the layout of the state hash and snapshot outputs, and the sensitivity sampling and PRCCs; the exit code is 0
+// if every check passes and 1 if not
+pub fn validate() -> i32 {
+    let started = Instant::now();
+    let output_dir = std::env::temp_dir().join(format!("bevy_coinfection_validate_{}", std::process::id()));
+    let first = run(Some(&output_dir));
+    let second = run(None);
+    let state_hash_csv = check_state_hash_csv(&output_dir.join("state_hash.csv"));
+    let _ = fs::remove_dir_all(&output_dir);
+
+    let cleared = HOSTS as usize + first.infections - first.final_inoculations;
+    let checks = [
+        ("invariants", if first.violations.is_empty() { Ok(()) } else { Err(first.violations.join("; ")) }),
+        (
+            "dynamics",
+            if first.infections > 0 && cleared > 0 {
+                Ok(())
+            } else {
+                Err(format!("{} new infections and {} cleared inoculations, expected some of each", first.infections, cleared))
+            },
+        ),
+        (
+            "determinism",
+            match first.hashes.iter().zip(&second.hashes).position(|(a, b)| a != b) {
+                None => Ok(()),
+                Some(index) => Err(format!("runs with the same seed diverged on day {}", index + 1)),
+            },
+        ),
+        ("state_hash.csv", state_hash_csv),
+        ("snapshot", check_snapshot(&first.snapshot)),
+        ("sensitivity", check_sensitivity()),
+    ];
+
+    println!("{} hosts for {} days with seed {}: {} new infections, {} cleared", HOSTS, DAYS, SEED, first.infections, cleared);
+    let mut failed = 0;
+    for (name, result) in &checks {
+        match result {
+            Ok(()) => println!("ok      {}", name),
+            Err(err) => {
+                println!("FAILED  {}: {}", name, err);
+                failed += 1;
+            }
+        }
+    }
+    println!("{} of {} checks passed in {} ms", checks.len() - failed, checks.len(), started.elapsed().as_millis());
+    (failed > 0) as i32
+}
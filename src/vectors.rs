@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+#[cfg(feature = "gui")]
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::infectiousness::InfectiousReservoir;
+#[cfg(feature = "gui")]
+use crate::plots::{analytics_ctx, AnalyticsWindow};
+use crate::report::RunReport;
+use crate::scenario::VectorConfig;
+use crate::{OutputDir, Params, SimulationTime};
+
+#[derive(Resource, Default, Clone)]
+pub struct Vectors(pub Option<VectorConfig>);
+
+impl Vectors {
+    // With vectors, incidence_rate is the chance that an infectious bite infects the host
+    pub fn incidence_rate(&self, params: &Params, population: &VectorPopulation) -> f32 {
+        match &self.0 {
+            None => params.incidence_rate,
+            Some(config) => params.incidence_rate * population.eir(config),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VectorSample {
+    pub day: u32,
+    pub rainfall: f32,
+    pub temperature: f32,
+    pub capacity: f32,
+    pub abundance: f32,
+    pub eip_days: f32,
+    pub sporozoite_rate: f32,
+    pub eir: f32, // Infectious bites per host per day
+}
+
+// Vectors per host, by infection status
+#[derive(Resource, Default, Clone)]
+pub struct VectorPopulation {
+    pub uninfected: f32,
+    pub incubating: Vec<(f32, f32)>, // (Fraction of the EIP completed, vectors) for each day's newly infected vectors
+    pub infectious: f32,             // Sporozoite-positive
+    pub samples: Vec<VectorSample>,
+}
+
+impl VectorPopulation {
+    pub fn abundance(&self) -> f32 {
+        self.uninfected + self.incubating.iter().map(|(_, vectors)| vectors).sum::<f32>() + self.infectious
+    }
+
+    pub fn sporozoite_rate(&self) -> f32 {
+        let abundance = self.abundance();
+        if abundance > 0.0 {
+            self.infectious / abundance
+        } else {
+            0.0
+        }
+    }
+
+    pub fn eir(&self, config: &VectorConfig) -> f32 {
+        config.biting_rate * self.infectious
+    }
+
+    // One day of births, deaths, infection from hosts and sporogony
+    fn step(&mut self, config: &VectorConfig, capacity: f32, temperature: f32, host_infectiousness: f32) {
+        let abundance = self.abundance();
+        let births = config.birth_rate * abundance * (1.0 - abundance / capacity.max(1.0)).max(0.0);
+        let survival = (1.0 - config.death_rate).clamp(0.0, 1.0);
+        let newly_infected = self.uninfected * (config.biting_rate * host_infectiousness).min(1.0);
+
+        // Only vectors that outlive the incubation period become infectious
+        let progress = config.sporogony_rate(temperature);
+        let mut matured = 0.0;
+        for (completed, vectors) in self.incubating.iter_mut() {
+            *completed += progress;
+            *vectors *= survival;
+            if *completed >= 1.0 {
+                matured += *vectors;
+            }
+        }
+        self.incubating.retain(|&(completed, vectors)| completed < 1.0 && vectors > 1e-6);
+        self.incubating.push((0.0, newly_infected * survival));
+
+        self.infectious = self.infectious * survival + matured;
+        self.uninfected = (self.uninfected - newly_infected) * survival + births;
+    }
+}
+
+#[derive(Resource)]
+pub struct VectorOutput(BufWriter<File>);
+
+pub fn setup_vector_output(mut commands: Commands, vectors: Res<Vectors>, output_dir: Res<OutputDir>, mut report: ResMut<RunReport>) {
+    if vectors.0.is_none() {
+        return;
+    }
+
+    let writer = fs::create_dir_all(&output_dir.0).and_then(|_| {
+        let mut writer = BufWriter::new(File::create(output_dir.0.join("vectors.csv"))?);
+        writeln!(writer, "day,rainfall,temperature,capacity,abundance,eip_days,sporozoite_rate,eir")?;
+        Ok(writer)
+    });
+    match writer {
+        Ok(writer) => commands.insert_resource(VectorOutput(writer)),
+        Err(err) => report.output_failed("vector output", err),
+    }
+}
+
+pub fn update_vectors(
+    vectors: Res<Vectors>,
+    sim_time: Res<SimulationTime>,
+    reservoir: Res<InfectiousReservoir>,
+    mut population: ResMut<VectorPopulation>,
+    mut output: Option<ResMut<VectorOutput>>,
+    mut report: ResMut<RunReport>,
+) {
+    let Some(config) = &vectors.0 else {
+        return;
+    };
+    if population.samples.last().is_some_and(|sample| sample.day == sim_time.day) {
+        return;
+    }
+
+    let rainfall = config.rainfall_on(sim_time.day);
+    let temperature = config.temperature_on(sim_time.day);
+    let capacity = config.capacity(rainfall);
+    if population.samples.is_empty() {
+        population.uninfected = config.equilibrium(capacity);
+    } else {
+        population.step(config, capacity, temperature, reservoir.mean);
+    }
+
+    let progress = config.sporogony_rate(temperature);
+    let sample = VectorSample {
+        day: sim_time.day,
+        rainfall,
+        temperature,
+        capacity,
+        abundance: population.abundance(),
+        eip_days: if progress > 0.0 { 1.0 / progress } else { f32::INFINITY },
+        sporozoite_rate: population.sporozoite_rate(),
+        eir: population.eir(config),
+    };
+    if let Some(output) = output.as_mut() {
+        let result = writeln!(
+            output.0,
+            "{},{:.1},{:.1},{:.2},{:.2},{:.1},{:.4},{:.4}",
+            sample.day,
+            sample.rainfall,
+            sample.temperature,
+            sample.capacity,
+            sample.abundance,
+            sample.eip_days,
+            sample.sporozoite_rate,
+            sample.eir
+        )
+        .and_then(|_| output.0.flush());
+        if let Err(err) = result {
+            report.output_failed("vector output", err);
+        }
+    }
+    population.samples.push(sample);
+}
+
+#[cfg(feature = "gui")]
+pub fn vectors_ui(
+    mut contexts: EguiContexts,
+    vectors: Res<Vectors>,
+    population: Res<VectorPopulation>,
+    analytics: Res<AnalyticsWindow>,
+) {
+    let Some(config) = &vectors.0 else {
+        return;
+    };
+
+    egui::Window::new("Vectors")
+        .default_pos(egui::pos2(450.0, 700.0))
+        .default_open(false)
+        .show(analytics_ctx(&mut contexts, &analytics), |ui| {
+            if let Some(sample) = population.samples.last() {
+                ui.label(format!(
+                    "{:.1} vectors per host, {:.1}% sporozoite-positive, EIP {:.0} days",
+                    sample.abundance,
+                    100.0 * sample.sporozoite_rate,
+                    sample.eip_days
+                ));
+                ui.label(format!("EIR {:.3} infectious bites per host per day ({:.0} per year)", sample.eir, 365.0 * sample.eir));
+            }
+
+            // Rainfall and abundance relative to their peak so they share an axis with the sporozoite rate
+            let peak_rainfall = config.rainfall.iter().cloned().fold(0.0, f32::max).max(1.0);
+            let peak_abundance = population.samples.iter().map(|s| s.abundance).fold(0.0, f32::max).max(1.0);
+            let line = |value: fn(&VectorSample) -> f32, peak: f32| -> PlotPoints {
+                population.samples.iter().map(|s| [s.day as f64, (value(s) / peak) as f64]).collect()
+            };
+            Plot::new("vectors")
+                .legend(Legend::default())
+                .include_y(0.0)
+                .include_y(1.0)
+                .height(150.0)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(line(|s| s.rainfall, peak_rainfall)).name("Rainfall"));
+                    plot_ui.line(Line::new(line(|s| s.abundance, peak_abundance)).name("Vector abundance"));
+                    plot_ui.line(Line::new(line(|s| s.sporozoite_rate, 1.0)).name("Sporozoite rate"));
+                });
+        });
+}
@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+#[cfg(feature = "gui")]
+use bevy_egui::{egui, EguiContexts};
+use chrono::NaiveDate;
+use rand::Rng;
+
+use crate::clock::DayTick;
+use crate::event_log::EventLog;
+use crate::ids::HostId;
+use crate::infectiousness::{host_infectiousness, InfectiousnessCurve};
+use crate::rng::Subsystem;
+use crate::scenario::{VenueConfig, VenueKind};
+use crate::terms::{date_on, TermCalendar};
+use crate::transmission::TransmissionTree;
+use crate::{Calendar, Host, Inoculation, Params, SimRng, SimulationTime};
+
+const TEMPORARY_CLOSURE_DAYS: u32 = 14;
+
+// Venues from the scenario, spawned as entities at startup
+#[derive(Resource, Default)]
+pub struct VenueSetup(pub Vec<VenueConfig>);
+
+#[derive(Component)]
+pub struct Venue {
+    pub config: VenueConfig,
+    pub closed: bool, // Closed by hand from the Venues window
+    open_today: bool,
+    mean_infectiousness: f32, // Among members, as of the last daily update
+}
+
+impl Venue {
+    fn is_open(&self, day: u32, date: NaiveDate, terms: Option<&TermCalendar>) -> bool {
+        if self.closed || self.config.closures.iter().any(|&[first, last]| (first..=last).contains(&day)) {
+            return false;
+        }
+        match (self.config.kind, terms) {
+            (VenueKind::School, Some(terms)) => terms.school_open(date),
+            (VenueKind::Work, Some(terms)) => terms.holiday(date).is_none(),
+            _ => true,
+        }
+    }
+
+    pub fn intensity(&self, day: u32, date: NaiveDate, weekday: usize, terms: Option<&TermCalendar>) -> f32 {
+        if !self.is_open(day, date, terms) {
+            return 0.0;
+        }
+        // Community venues mix as on a Sunday during holidays
+        let holiday = terms.is_some_and(|terms| terms.holiday(date).is_some());
+        self.config.weekly()[if self.config.kind == VenueKind::Community && holiday { 6 } else { weekday }]
+    }
+}
+
+// Venues the host belongs to
+#[derive(Component, Default)]
+pub struct Memberships(pub Vec<Entity>);
+
+// Extra daily chance of infection from mixing at the host's venues today
+#[derive(Component, Debug, Clone, Copy)]
+pub struct VenueExposure(pub f32);
+
+pub fn spawn_venues(mut commands: Commands, setup: Res<VenueSetup>) {
+    for config in &setup.0 {
+        commands.spawn(Venue {
+            config: config.clone(),
+            closed: false,
+            open_today: true,
+            mean_infectiousness: 0.0,
+        });
+    }
+}
+
+pub fn assign_memberships(
+    mut commands: Commands,
+    host_query: Query<Entity, Added<Host>>,
+    venue_query: Query<(Entity, &Venue)>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    if venue_query.is_empty() {
+        return;
+    }
+
+    let mut venues: Vec<_> = venue_query.iter().collect();
+    venues.sort_by_key(|(entity, _)| *entity);
+    let mut hosts: Vec<_> = host_query.iter().collect();
+    hosts.sort();
+
+    let rng = &mut sim_rng.stream(Subsystem::Spawn);
+    for host in hosts {
+        let memberships = venues
+            .iter()
+            .filter(|(_, venue)| rng.gen::<f32>() < venue.config.membership)
+            .map(|(entity, _)| *entity)
+            .collect();
+        commands.entity(host).insert((Memberships(memberships), VenueExposure(0.0)));
+    }
+}
+
+// Once a day, sets each host's venue exposure from how infectious the other members are
+#[allow(clippy::too_many_arguments)]
+pub fn update_venue_exposure(
+    mut host_query: Query<(&Memberships, Option<&Children>, &mut VenueExposure)>,
+    inoc_query: Query<&Inoculation>,
+    mut venue_query: Query<(Entity, &mut Venue)>,
+    curve: Res<InfectiousnessCurve>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    terms: Option<Res<TermCalendar>>,
+    mut event_log: ResMut<EventLog>,
+    mut ticks: EventReader<DayTick>,
+) {
+    if ticks.read().count() == 0 {
+        return;
+    }
+
+    let date = date_on(&calendar, sim_time.day);
+    for (_, mut venue) in venue_query.iter_mut() {
+        let open = venue.is_open(sim_time.day, date, terms.as_deref());
+        if open != venue.open_today {
+            venue.open_today = open;
+            let message = format!("{} {}", venue.config.name, if open { "reopened" } else { "closed" });
+            event_log.log(sim_time.day, message);
+        }
+        venue.mean_infectiousness = 0.0;
+    }
+
+    let mut totals: Vec<(Entity, f32, usize)> = venue_query.iter().map(|(entity, _)| (entity, 0.0, 0)).collect();
+    for (memberships, children, _) in host_query.iter() {
+        let infectiousness = host_infectiousness(children, &inoc_query, &curve, &params, sim_time.day);
+        for (venue, sum, count) in totals.iter_mut() {
+            if memberships.0.contains(venue) {
+                *sum += infectiousness;
+                *count += 1;
+            }
+        }
+    }
+    for (entity, sum, count) in totals {
+        if let Ok((_, mut venue)) = venue_query.get_mut(entity) {
+            venue.mean_infectiousness = if count > 0 { sum / count as f32 } else { 0.0 };
+        }
+    }
+
+    let weekday = calendar.weekday(sim_time.day);
+    for (memberships, _, mut exposure) in host_query.iter_mut() {
+        exposure.0 = memberships
+            .0
+            .iter()
+            .filter_map(|&entity| venue_query.get(entity).ok())
+            .map(|(_, venue)| {
+                venue.config.contact_rate * venue.intensity(sim_time.day, date, weekday, terms.as_deref()) * venue.mean_infectiousness
+            })
+            .sum();
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn venues_ui(
+    mut contexts: EguiContexts,
+    mut venue_query: Query<(Entity, &mut Venue)>,
+    host_query: Query<&Memberships>,
+    mut sim_time: ResMut<SimulationTime>,
+    calendar: Res<Calendar>,
+    terms: Option<Res<TermCalendar>>,
+) {
+    if venue_query.is_empty() {
+        return;
+    }
+
+    let weekday = calendar.weekday(sim_time.day);
+    let date = date_on(&calendar, sim_time.day);
+    egui::Window::new("Venues")
+        .default_pos(egui::pos2(450.0, 750.0))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(holiday) = terms.as_ref().and_then(|terms| terms.holiday(date)) {
+                ui.label(format!("Holiday: {}", holiday));
+            }
+            egui::Grid::new("venues").striped(true).show(ui, |ui| {
+                ui.label("Venue");
+                ui.label("Members");
+                ui.label("Mixing today");
+                ui.label("Closed");
+                ui.label("");
+                ui.end_row();
+
+                let mut venues: Vec<_> = venue_query.iter_mut().collect();
+                venues.sort_by_key(|(entity, _)| *entity);
+                for (entity, venue) in venues.iter_mut() {
+                    let members = host_query.iter().filter(|memberships| memberships.0.contains(entity)).count();
+                    ui.label(format!("{} ({:?})", venue.config.name, venue.config.kind));
+                    ui.label(members.to_string());
+                    ui.label(format!("{:.0}%", 100.0 * venue.intensity(sim_time.day, date, weekday, terms.as_deref())));
+                    ui.checkbox(&mut venue.closed, "");
+                    // Reopens on its own, e.g. to try a short school closure
+                    if ui.add_enabled(!venue.closed, egui::Button::new(format!("Close {} days", TEMPORARY_CLOSURE_DAYS))).clicked() {
+                        venue.closed = true;
+                        let entity = *entity;
+                        sim_time.schedule_in(TEMPORARY_CLOSURE_DAYS, move |world| {
+                            if let Some(mut venue) = world.get_mut::<Venue>(entity) {
+                                venue.closed = false;
+                            }
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+// A new inoculation in a venue member, with the share of its infection chance that came from venue mixing
+#[derive(Event)]
+pub struct VenueInfection {
+    pub host: Entity,
+    pub venue_share: f32,
+}
+
+// Decides which infections were caught at a venue and picks the infector among the other members, weighted by how
+// much each contributed to the infectee's exposure
+#[allow(clippy::too_many_arguments)]
+pub fn attribute_venue_infections(
+    mut infections: EventReader<VenueInfection>,
+    host_query: Query<(Entity, &HostId, &Memberships, Option<&Children>)>,
+    inoc_query: Query<&Inoculation>,
+    venue_query: Query<&Venue>,
+    curve: Res<InfectiousnessCurve>,
+    params: Res<Params>,
+    sim_time: Res<SimulationTime>,
+    calendar: Res<Calendar>,
+    terms: Option<Res<TermCalendar>>,
+    mut sim_rng: ResMut<SimRng>,
+    mut tree: ResMut<TransmissionTree>,
+) {
+    let date = date_on(&calendar, sim_time.day);
+    let weekday = calendar.weekday(sim_time.day);
+    for infection in infections.read() {
+        let rng = &mut sim_rng.stream(Subsystem::Infection);
+        let Ok((_, &infectee_id, memberships, _)) = host_query.get(infection.host) else {
+            continue;
+        };
+        if rng.gen::<f32>() >= infection.venue_share {
+            continue; // Infected from the background force of infection
+        }
+
+        let mut candidates: Vec<(Entity, HostId, f32)> = Vec::new();
+        for (other, &other_id, other_memberships, children) in host_query.iter() {
+            if other == infection.host {
+                continue;
+            }
+            let shared: f32 = memberships
+                .0
+                .iter()
+                .filter(|venue| other_memberships.0.contains(venue))
+                .filter_map(|&venue| venue_query.get(venue).ok())
+                .map(|venue| venue.config.contact_rate * venue.intensity(sim_time.day, date, weekday, terms.as_deref()))
+                .sum();
+            if shared > 0.0 {
+                let weight = shared * host_infectiousness(children, &inoc_query, &curve, &params, sim_time.day);
+                if weight > 0.0 {
+                    candidates.push((other, other_id, weight));
+                }
+            }
+        }
+        candidates.sort_by_key(|(entity, _, _)| *entity);
+
+        let total: f32 = candidates.iter().map(|(_, _, weight)| weight).sum();
+        let mut draw = rng.gen::<f32>() * total;
+        if let Some(&(infector, infector_id, _)) = candidates.iter().find(|(_, _, weight)| {
+            draw -= weight;
+            draw < 0.0
+        }) {
+            tree.record((infector, infector_id), (infection.host, infectee_id), sim_time.day);
+        }
+    }
+}